@@ -1,12 +1,16 @@
 //! Command-line interface definition
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use clutchctl_core::device::PedalNumbering;
+use std::path::PathBuf;
+
+use crate::theme::Theme;
 
 /// USB HID pedal device configuration tool
 #[derive(Parser, Debug)]
 #[command(name = "clutchctl")]
-#[command(version, about, long_about = None)]
+#[command(version, long_version = long_version(), about, long_about = None)]
 pub struct Cli {
     /// Enable verbose output
     #[arg(short, long, global = true)]
@@ -16,34 +20,283 @@ pub struct Cli {
     #[arg(long = "no-color", global = true)]
     pub no_color: bool,
 
+    /// Count numeric pedal selectors from 0 instead of 1 (output stays 1-based)
+    #[arg(long, global = true, env = "CLUTCHCTL_ZERO_BASED", value_parser = clap::builder::BoolishValueParser::new())]
+    pub zero_based: bool,
+
+    /// Use an in-memory device instead of real hardware (`--simulate=N` for N pedals)
+    #[cfg(feature = "simulate")]
+    #[arg(long, global = true, env = "CLUTCHCTL_SIMULATE", value_name = "PEDALS",
+          num_args = 0..=1, require_equals = true, default_missing_value = "3")]
+    pub simulate: Option<usize>,
+
+    /// Color palette
+    #[arg(long, global = true, value_enum, env = "CLUTCHCTL_THEME", default_value_t = Theme::Default)]
+    pub theme: Theme,
+
     /// Command to execute
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// `--version` output: CLI and library versions plus the HID backend
+fn long_version() -> &'static str {
+    // Built once per process, when clap assembles the command
+    Box::leak(
+        format!(
+            "{} (core {})\nHID backend: {}",
+            env!("CARGO_PKG_VERSION"),
+            clutchctl_core::VERSION,
+            clutchctl_core::usb::HID_BACKEND
+        )
+        .into_boxed_str(),
+    )
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// List all connected pedal devices
-    List,
+    List {
+        /// Show every HID device, not just supported pedals
+        #[arg(long)]
+        all: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Print only the number of pedal devices found
+        #[arg(long, conflicts_with_all = ["all", "json"])]
+        count: bool,
+
+        /// Open every device and report the ones that fail (busy, no permission)
+        #[arg(long, conflicts_with_all = ["all", "count"])]
+        open: bool,
+    },
 
     /// Show configuration of a device
     Show {
         /// Device ID (from list command)
-        device: usize,
+        #[arg(required_unless_present = "path")]
+        device: Option<usize>,
+
+        /// Open the device at this HID path instead (e.g. /dev/hidraw3)
+        #[arg(long, conflicts_with = "device")]
+        path: Option<String>,
+
+        /// Only show this pedal (index starting at 1, name, or "all")
+        #[arg(long)]
+        pedal: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ShowFormat::Pretty, visible_alias = "output-format")]
+        format: ShowFormat,
     },
 
     /// Set pedal configuration
+    #[command(allow_missing_positional = true)]
     Set {
+        /// Device ID (omit with --all-devices)
+        #[arg(required_unless_present = "all_devices")]
+        device: Option<usize>,
+
+        /// Apply to every connected device, continuing past failures
+        #[arg(long, conflicts_with = "device")]
+        all_devices: bool,
+
+        /// Pedal to configure (name, index, or "all")
+        pedal: String,
+
+        /// Print the resulting configuration and packet without writing to the device
+        #[arg(long, global = true)]
+        dry_run: bool,
+
+        /// Configuration subcommand
+        #[command(subcommand)]
+        config: SetConfig,
+    },
+
+    /// Stage a pedal configuration without writing it; see commit
+    Stage {
         /// Device ID
         device: usize,
 
-        /// Pedal to configure (name or index)
+        /// Pedal to configure (name, index, or "all")
         pedal: String,
 
         /// Configuration subcommand
         #[command(subcommand)]
         config: SetConfig,
     },
+
+    /// Write every staged configuration of a device in one save
+    Commit {
+        /// Device ID
+        device: usize,
+
+        /// Drop the staged configurations instead of writing them
+        #[arg(long)]
+        discard: bool,
+    },
+
+    /// Export the configuration of every pedal as JSON
+    Export {
+        /// Device ID
+        device: usize,
+
+        /// Write to this file instead of standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Write the raw configuration bytes in the binary backup format
+        #[arg(long, requires = "output")]
+        binary: bool,
+    },
+
+    /// Export every connected device into one JSON array, tagged with serial numbers
+    ExportAll {
+        /// Write to this file instead of standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import pedal configuration from a JSON file made by export
+    #[command(allow_missing_positional = true)]
+    Import {
+        /// Device ID (omit with --all-devices)
+        #[arg(required_unless_present = "all_devices")]
+        device: Option<usize>,
+
+        /// File to import, or "-" for standard input
+        file: PathBuf,
+
+        /// Only import these pedals (comma-separated names or indices); others are left as they are
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<String>,
+
+        /// Import into every connected device, continuing past failures
+        #[arg(long, conflicts_with = "device")]
+        all_devices: bool,
+
+        /// Print the configuration that would be imported without writing it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Read a binary backup made by export --binary
+        #[arg(long, conflicts_with_all = ["only", "all_devices"])]
+        binary: bool,
+
+        /// Read a script of footswitch command lines instead of JSON
+        #[arg(long, conflicts_with = "binary")]
+        from_vendor: bool,
+    },
+
+    /// Flip the trigger of configured pedals between press and release
+    Invert {
+        /// Device ID
+        device: usize,
+
+        /// Pedal to invert (name, index, or "all")
+        pedal: String,
+    },
+
+    /// Change the triggers of every pedal at once
+    Trigger {
+        /// Device ID
+        device: usize,
+
+        /// What to do: reset (every pedal triggers on press)
+        #[arg(value_enum)]
+        action: TriggerAction,
+    },
+
+    /// Make pedals do nothing, keeping their configuration for 'enable'
+    Disable {
+        /// Device ID
+        device: usize,
+
+        /// Pedal to disable (name, index, or "all")
+        pedal: String,
+    },
+
+    /// Restore the configuration of pedals disabled with 'disable'
+    Enable {
+        /// Device ID
+        device: usize,
+
+        /// Pedal to enable (name, index, or "all")
+        pedal: String,
+    },
+
+    /// Exchange the configurations of two pedals
+    Swap {
+        /// Device ID
+        device: usize,
+
+        /// First pedal (name or index)
+        pedal_a: String,
+
+        /// Second pedal (name or index)
+        pedal_b: String,
+    },
+
+    /// Unconfigure every pedal of a device
+    Reset {
+        /// Device ID (omit with --all-devices)
+        #[arg(required_unless_present = "all_devices")]
+        device: Option<usize>,
+
+        /// Reset every connected device, continuing past failures
+        #[arg(long, conflicts_with = "device")]
+        all_devices: bool,
+    },
+
+    /// Show what a device supports: pedals, configuration types and triggers
+    Capabilities {
+        /// Device ID
+        device: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show a device's manufacturer, product, serial number, USB IDs and firmware
+    Info {
+        /// Device ID
+        device: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show or set the persistent LED mode of a device
+    Led {
+        /// Device ID
+        device: usize,
+
+        /// New mode: on, off or blink (lit while a pedal is pressed); omit to show the mode
+        mode: Option<clutchctl_core::device::LedMode>,
+    },
+
+    /// Show library, backend and device firmware versions (for bug reports)
+    Version {
+        /// Device ID to include
+        device: Option<usize>,
+    },
+
+    /// Generate a shell completion script (e.g. `clutchctl completions bash > clutchctl.bash`)
+    #[command(hide = true, alias = "generate-completions")]
+    Completions {
+        /// Shell to generate the script for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Browse devices and pedal configuration interactively
+    #[cfg(feature = "tui")]
+    Tui,
 }
 
 #[derive(Subcommand, Debug)]
@@ -107,26 +360,68 @@ pub enum SetConfig {
     None,
 }
 
+/// Output format of the show command
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShowFormat {
+    /// Human-readable, colored output
+    Pretty,
+    /// JSON document
+    Json,
+    /// One tab-separated line per pedal
+    Compact,
+    /// Shell variable assignments (CLUTCHCTL_PEDAL_<N>_<FIELD>=value)
+    Env,
+}
+
+/// Action of the trigger command
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerAction {
+    /// Make every pedal trigger on press
+    Reset,
+}
+
+/// Shell of the completions command
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum MouseMode {
     /// Mouse buttons
     Buttons {
         /// Button combination (e.g., "left", "left+right")
         buttons: String,
+
+        /// Clicks per press; only 1 is supported by any known device
+        #[arg(long, default_value_t = 1)]
+        clicks: u8,
     },
 
     /// Mouse axis movement
     Axis {
         /// X movement (-100 to 100)
+        #[arg(allow_negative_numbers = true)]
         x: i8,
 
         /// Y movement (-100 to 100)
+        #[arg(allow_negative_numbers = true)]
         y: i8,
 
         /// Wheel movement (-100 to 100)
-        #[arg(default_value = "0")]
+        #[arg(default_value = "0", allow_negative_numbers = true)]
         wheel: i8,
     },
+
+    /// Wheel movement only; the same as `axis 0 0 <AMOUNT>`
+    Scroll {
+        /// Wheel movement (-100 to 100, positive scrolls up)
+        #[arg(allow_negative_numbers = true)]
+        amount: i8,
+    },
 }
 
 impl Cli {
@@ -137,12 +432,56 @@ impl Cli {
             log::set_max_level(log::LevelFilter::Debug);
         }
 
+        if self.zero_based {
+            crate::commands::set_pedal_numbering(PedalNumbering::ZeroBased);
+        }
+
+        #[cfg(feature = "simulate")]
+        if let Some(pedal_count) = self.simulate {
+            clutchctl_core::device::enable_simulation(pedal_count);
+        }
+
+        let verbose = self.verbose;
         match self.command {
-            Command::List => crate::commands::list::execute(),
-            Command::Show { device } => crate::commands::show::execute(device),
-            Command::Set { device, pedal, config } => {
-                crate::commands::set::execute(device, pedal, config)
+            Command::List { all, json, count, open } => crate::commands::list::execute(all, json, count, open),
+            Command::Show { device, path, pedal, format } => {
+                crate::commands::show::execute(device, path, pedal, format, verbose)
+            }
+            Command::Set { device, all_devices, pedal, dry_run, config } => {
+                crate::commands::set::execute(device, all_devices, pedal, config, dry_run)
+            }
+            Command::Stage { device, pedal, config } => {
+                crate::commands::stage::execute(device, pedal, config)
+            }
+            Command::Commit { device, discard } => crate::commands::commit::execute(device, discard),
+            Command::Export { device, output, binary } => crate::commands::export::execute(device, output, binary),
+            Command::ExportAll { output } => crate::commands::export_all::execute(output),
+            Command::Import { device, file, only, all_devices, dry_run, binary, from_vendor } => {
+                if binary {
+                    crate::commands::import::execute_binary(device, &file, dry_run)
+                } else {
+                    crate::commands::import::execute(device, all_devices, &file, &only, dry_run, from_vendor)
+                }
+            }
+            Command::Invert { device, pedal } => crate::commands::invert::execute(device, &pedal),
+            Command::Trigger { device, action } => crate::commands::trigger::execute(device, action),
+            Command::Disable { device, pedal } => crate::commands::disable::execute(device, &pedal, false),
+            Command::Enable { device, pedal } => crate::commands::disable::execute(device, &pedal, true),
+            Command::Swap { device, pedal_a, pedal_b } => {
+                crate::commands::swap::execute(device, &pedal_a, &pedal_b)
+            }
+            Command::Reset { device, all_devices } => {
+                crate::commands::reset::execute(device, all_devices)
+            }
+            Command::Capabilities { device, json } => {
+                crate::commands::capabilities::execute(device, json)
             }
+            Command::Info { device, json } => crate::commands::info::execute(device, json),
+            Command::Led { device, mode } => crate::commands::led::execute(device, mode),
+            Command::Version { device } => crate::commands::version::execute(device),
+            Command::Completions { shell } => crate::commands::completions::execute(shell),
+            #[cfg(feature = "tui")]
+            Command::Tui => crate::commands::tui::execute(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+//! Capabilities command implementation
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use clutchctl_core::configuration::Trigger;
+use clutchctl_core::device::find_device_by_id;
+use serde::Serialize;
+
+use crate::theme;
+
+/// JSON representation of a device's capabilities
+#[derive(Serialize)]
+struct CapabilitiesEntry<'a> {
+    id: usize,
+    model: &'a str,
+    pedal_count: usize,
+    pedal_names: &'a [String],
+    first_pedal_index: usize,
+    config_types: Vec<&'static str>,
+    triggers: Vec<&'static str>,
+    mouse_axis: bool,
+    max_text_length: usize,
+    /// 1-based pedal numbers from the leftmost pedal on
+    physical_order: Vec<usize>,
+}
+
+/// Execute the capabilities command
+pub fn execute(device_id: usize, json: bool) -> Result<()> {
+    let device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+    let capabilities = device.capabilities();
+
+    let config_types: Vec<&str> = capabilities.supported_config_types.iter()
+        .map(|config_type| config_type.as_str())
+        .collect();
+    let triggers: Vec<&str> = capabilities.supported_triggers.iter()
+        .map(|&trigger| trigger_name(trigger))
+        .collect();
+
+    if json {
+        let entry = CapabilitiesEntry {
+            id: device_id,
+            model: device.model(),
+            pedal_count: capabilities.pedal_count,
+            pedal_names: &capabilities.pedal_names,
+            first_pedal_index: capabilities.first_pedal_index,
+            config_types,
+            triggers,
+            mouse_axis: capabilities.supports_mouse_axis,
+            max_text_length: capabilities.max_text_length,
+            physical_order: capabilities.physical_order.iter().map(|i| i + 1).collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&entry)?);
+        return Ok(());
+    }
+
+    println!("{} {} {}",
+             "Device".bold(),
+             theme::id(format!("[{}]", device_id)).bold(),
+             theme::value(device.model()));
+    println!("  Pedals:       {} ({})", capabilities.pedal_count, capabilities.pedal_names.join(", "));
+    println!("  First index:  {}", capabilities.first_pedal_index);
+    println!("  Config types: {}", config_types.join(", "));
+    println!("  Triggers:     {}", triggers.join(", "));
+    println!("  Mouse axis:   {}", if capabilities.supports_mouse_axis { "yes" } else { "no" });
+    println!("  Max text:     {} bytes", capabilities.max_text_length);
+    let order: Vec<String> = capabilities.physical_order.iter().map(|i| (i + 1).to_string()).collect();
+    println!("  Pedal order:  {} (left to right)", order.join(", "));
+
+    Ok(())
+}
+
+/// Short trigger name; release is what `--invert` selects
+fn trigger_name(trigger: Trigger) -> &'static str {
+    match trigger {
+        Trigger::OnPress => "press",
+        Trigger::OnRelease => "release",
+    }
+}
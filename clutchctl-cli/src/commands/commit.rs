@@ -0,0 +1,52 @@
+//! Commit command implementation
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+
+use crate::theme;
+
+use super::stage::{check_session, find_handle, load_session, session_path};
+
+/// Execute the commit command
+pub fn execute(device_id: usize, discard: bool) -> Result<()> {
+    let handle = find_handle(device_id)?;
+    let key = handle.info().device_key();
+    let path = session_path(&key)?;
+    let label = theme::id(format!("[{}]", device_id)).bold();
+
+    if discard {
+        match std::fs::remove_file(&path) {
+            Ok(()) => println!("Discarded staged changes for device {}", label),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                println!("No staged changes for device {}", label)
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+        }
+        return Ok(());
+    }
+
+    let session = load_session(&key)?
+        .ok_or_else(|| anyhow!("No staged changes for device {}. Use 'clutchctl stage' first.", device_id))?;
+
+    let mut device = handle.open().context("Failed to open device")?;
+    check_session(&session, device_id, &key, device.model())?;
+
+    let device_mut = std::sync::Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+
+    // Read first so pedals that weren't staged are written back unchanged
+    device_mut.load_configuration()
+        .context("Failed to load device configuration")?;
+
+    let indices = session.apply(device_mut, None)?;
+    device_mut.save_configuration()
+        .context("Failed to save configuration to device")?;
+
+    std::fs::remove_file(&path)
+        .with_context(|| format!("Saved, but failed to remove {}", path.display()))?;
+
+    let pedals: Vec<String> = indices.iter().map(|index| (index + 1).to_string()).collect();
+    println!("{} Committed pedal(s) {} on device {}", theme::ok_mark(), pedals.join(", "), label);
+
+    Ok(())
+}
@@ -0,0 +1,395 @@
+//! Completions command implementation
+//!
+//! Scripts are generated from the clap definition, so new commands and flags
+//! complete without changes here. Arguments taking media, gamepad, mouse or key
+//! names complete from the name lists in `clutchctl-core`; names containing
+//! shell metacharacters (`!`, `$`, quotes, ...) are left out of the candidates
+//! but are still accepted on the command line.
+
+use anyhow::Result;
+use clap::{Arg, Command, CommandFactory};
+use clutchctl_core::configuration::mouse::MouseButton;
+use clutchctl_core::configuration::{GamepadConfiguration, MediaConfiguration};
+use clutchctl_core::protocol::HID_KEYMAP;
+use std::fmt::Write;
+
+use crate::cli::{Cli, Shell};
+
+const BIN: &str = "clutchctl";
+
+/// A named or short option of a command
+struct Flag {
+    /// Every spelling, e.g. `--format`, `--output-format`
+    spellings: Vec<String>,
+    long: Option<String>,
+    short: Option<char>,
+    help: String,
+    /// Completion candidates for the value, `None` if the flag takes no value
+    values: Option<Vec<String>>,
+}
+
+/// One command or subcommand
+struct Node {
+    /// Function-safe identifier, e.g. `clutchctl__set__media`
+    id: String,
+    /// Subcommand names with their help line
+    subcommands: Vec<(String, String)>,
+    flags: Vec<Flag>,
+    /// Candidates for each positional argument, in order
+    positionals: Vec<Vec<String>>,
+}
+
+impl Node {
+    /// Flag spellings and subcommand names, for shells that complete everything at once
+    fn all_words(&self) -> Vec<String> {
+        let mut words: Vec<String> = self.subcommands.iter().map(|(name, _)| name.clone()).collect();
+        words.extend(self.positionals.iter().flatten().cloned());
+        words.extend(self.flags.iter().flat_map(|flag| flag.spellings.iter().cloned()));
+        words
+    }
+
+    fn value_flags(&self) -> impl Iterator<Item = (&Flag, &Vec<String>)> {
+        self.flags.iter().filter_map(|flag| flag.values.as_ref().map(|values| (flag, values)))
+    }
+}
+
+/// Execute the completions command
+pub fn execute(shell: Shell) -> Result<()> {
+    print!("{}", script(shell));
+    Ok(())
+}
+
+/// Completion script of a shell
+fn script(shell: Shell) -> String {
+    let nodes = nodes();
+    match shell {
+        Shell::Bash => bash(&nodes),
+        Shell::Zsh => zsh(&nodes),
+        Shell::Fish => fish(&nodes),
+        Shell::Powershell => powershell(&nodes),
+    }
+}
+
+/// Every visible command of the CLI, parents before children
+fn nodes() -> Vec<Node> {
+    let mut cli = Cli::command();
+    // Propagates global flags into the subcommands
+    cli.build();
+
+    let mut nodes = Vec::new();
+    collect(&cli, BIN.to_string(), BIN, &mut nodes);
+    nodes
+}
+
+/// Flatten the command tree, parents before children
+fn collect(cmd: &Command, id: String, name: &str, nodes: &mut Vec<Node>) {
+    let mut node = Node {
+        id: id.clone(),
+        subcommands: Vec::new(),
+        flags: Vec::new(),
+        positionals: Vec::new(),
+    };
+
+    for arg in cmd.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        if arg.is_positional() {
+            node.positionals.push(values(name, arg));
+            continue;
+        }
+
+        let long = arg.get_long().map(str::to_string);
+        let mut spellings: Vec<String> = long.iter().map(|long| format!("--{}", long)).collect();
+        spellings.extend(arg.get_visible_aliases().into_iter().flatten().map(|alias| format!("--{}", alias)));
+        spellings.extend(arg.get_short().map(|short| format!("-{}", short)));
+
+        node.flags.push(Flag {
+            spellings,
+            long,
+            short: arg.get_short(),
+            help: first_line(arg.get_help().map(|help| help.to_string())),
+            values: arg.get_action().takes_values().then(|| values(name, arg)),
+        });
+    }
+
+    let subcommands: Vec<&Command> = cmd.get_subcommands().filter(|sub| !sub.is_hide_set()).collect();
+    node.subcommands = subcommands
+        .iter()
+        .map(|sub| (sub.get_name().to_string(), first_line(sub.get_about().map(|about| about.to_string()))))
+        .collect();
+    nodes.push(node);
+
+    for sub in subcommands {
+        collect(sub, format!("{}__{}", id, sub.get_name()), sub.get_name(), nodes);
+    }
+}
+
+/// Completion candidates for an argument of the named command
+fn values(command: &str, arg: &Arg) -> Vec<String> {
+    let names: Vec<String> = match (command, arg.get_id().as_str()) {
+        ("keyboard", "keys") => HID_KEYMAP.all_key_names().into_iter().map(str::to_string).collect(),
+        ("media", "button") => MediaConfiguration::all_names().into_iter().map(str::to_string).collect(),
+        ("game", "button") => GamepadConfiguration::all_names().into_iter().map(str::to_string).collect(),
+        ("buttons", "buttons") => MouseButton::all_names().into_iter().map(str::to_string).collect(),
+        _ => arg
+            .get_possible_values()
+            .into_iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect(),
+    };
+
+    names
+        .into_iter()
+        .filter(|name| name.chars().all(|c| c.is_ascii_alphanumeric() || "-_+.".contains(c)))
+        .collect()
+}
+
+fn first_line(text: Option<String>) -> String {
+    text.unwrap_or_default().lines().next().unwrap_or_default().to_string()
+}
+
+fn bash(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "_{}() {{", BIN);
+    out.push_str("    local cur prev word cmd npos i\n");
+    out.push_str("    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    out.push_str("    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    let _ = writeln!(out, "    cmd=\"{}\"", BIN);
+    out.push_str("    npos=0\n\n");
+
+    // Walk the words before the cursor to find the current subcommand
+    out.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+    out.push_str("        word=\"${COMP_WORDS[i]}\"\n");
+    out.push_str("        case \"${cmd},${word}\" in\n");
+    for node in nodes {
+        for (name, _) in &node.subcommands {
+            let _ = writeln!(out, "            {},{})\n                cmd=\"{}__{}\"; npos=0 ;;", node.id, name, node.id, name);
+        }
+        for (flag, _) in node.value_flags() {
+            let patterns: Vec<String> = flag.spellings.iter().map(|s| format!("{},{}", node.id, s)).collect();
+            let _ = writeln!(out, "            {})\n                i=$((i + 1)) ;;", patterns.join("|"));
+        }
+    }
+    out.push_str("            *,-*) ;;\n");
+    out.push_str("            *) npos=$((npos + 1)) ;;\n");
+    out.push_str("        esac\n");
+    out.push_str("    done\n\n");
+
+    // Values of the option before the cursor
+    out.push_str("    case \"${cmd},${prev}\" in\n");
+    for node in nodes {
+        for (flag, values) in node.value_flags() {
+            let patterns: Vec<String> = flag.spellings.iter().map(|s| format!("{},{}", node.id, s)).collect();
+            let _ = writeln!(out, "        {})\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;",
+                             patterns.join("|"), values.join(" "));
+        }
+    }
+    out.push_str("    esac\n\n");
+
+    // Options
+    out.push_str("    if [[ \"$cur\" == -* ]]; then\n");
+    out.push_str("        case \"$cmd\" in\n");
+    for node in nodes {
+        let spellings: Vec<&str> = node.flags.iter().flat_map(|flag| flag.spellings.iter().map(String::as_str)).collect();
+        let _ = writeln!(out, "            {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;", node.id, spellings.join(" "));
+    }
+    out.push_str("        esac\n");
+    out.push_str("        return\n");
+    out.push_str("    fi\n\n");
+
+    // Positional values, then subcommands once the positionals are filled
+    out.push_str("    case \"${cmd},${npos}\" in\n");
+    for node in nodes {
+        for (index, values) in node.positionals.iter().enumerate().filter(|(_, values)| !values.is_empty()) {
+            let _ = writeln!(out, "        {},{})\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;",
+                             node.id, index, values.join(" "));
+        }
+    }
+    out.push_str("    esac\n\n");
+
+    out.push_str("    case \"$cmd\" in\n");
+    for node in nodes.iter().filter(|node| !node.subcommands.is_empty()) {
+        let names: Vec<&str> = node.subcommands.iter().map(|(name, _)| name.as_str()).collect();
+        let _ = writeln!(out, "        {})\n            [[ $npos -ge {} ]] && COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+                         node.id, node.positionals.len(), names.join(" "));
+    }
+    out.push_str("    esac\n");
+    out.push_str("}\n\n");
+    let _ = writeln!(out, "complete -F _{} {}", BIN, BIN);
+    out
+}
+
+/// zsh runs the bash completion through its compatibility layer
+fn zsh(nodes: &[Node]) -> String {
+    format!("#compdef {}\n\nautoload -U +X bashcompinit && bashcompinit\n\n{}", BIN, bash(nodes))
+}
+
+fn fish(nodes: &[Node]) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+    let mut out = String::new();
+
+    // Prints the current subcommand identifier, like the bash walk
+    let _ = writeln!(out, "function __{}_cmd", BIN);
+    out.push_str("    set -l cmd clutchctl\n");
+    out.push_str("    set -l skip 0\n");
+    out.push_str("    for word in (commandline -opc)[2..-1]\n");
+    out.push_str("        if test $skip = 1\n            set skip 0\n            continue\n        end\n");
+    out.push_str("        switch \"$cmd,$word\"\n");
+    for node in nodes {
+        for (name, _) in &node.subcommands {
+            let _ = writeln!(out, "            case '{},{}'\n                set cmd {}__{}", node.id, name, node.id, name);
+        }
+        for (flag, _) in node.value_flags() {
+            let patterns: Vec<String> = flag.spellings.iter().map(|s| format!("'{},{}'", node.id, s)).collect();
+            let _ = writeln!(out, "            case {}\n                set skip 1", patterns.join(" "));
+        }
+    }
+    out.push_str("        end\n");
+    out.push_str("    end\n");
+    out.push_str("    echo $cmd\n");
+    out.push_str("end\n\n");
+
+    let _ = writeln!(out, "complete -c {} -f", BIN);
+    for node in nodes {
+        let condition = format!("-n 'test (__{}_cmd) = {}'", BIN, node.id);
+
+        for (name, help) in &node.subcommands {
+            let _ = writeln!(out, "complete -c {} {} -a {} -d {}", BIN, condition, name, quote(help));
+        }
+        for values in node.positionals.iter().filter(|values| !values.is_empty()) {
+            let _ = writeln!(out, "complete -c {} {} -a {}", BIN, condition, quote(&values.join(" ")));
+        }
+        for flag in &node.flags {
+            let mut line = format!("complete -c {} {}", BIN, condition);
+            if let Some(long) = &flag.long {
+                let _ = write!(line, " -l {}", long);
+            }
+            if let Some(short) = flag.short {
+                let _ = write!(line, " -s {}", short);
+            }
+            if let Some(values) = &flag.values {
+                line.push_str(" -r");
+                if !values.is_empty() {
+                    let _ = write!(line, " -a {}", quote(&values.join(" ")));
+                }
+            }
+            let _ = writeln!(out, "{} -d {}", line, quote(&flag.help));
+        }
+    }
+    out
+}
+
+fn powershell(nodes: &[Node]) -> String {
+    let list = |words: &[String]| {
+        words.iter().map(|word| format!("'{}'", word.replace('\'', "''"))).collect::<Vec<_>>().join(", ")
+    };
+    let mut out = String::new();
+
+    let _ = writeln!(out, "Register-ArgumentCompleter -Native -CommandName '{}' -ScriptBlock {{", BIN);
+    out.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n\n");
+    let _ = writeln!(out, "    $cmd = '{}'", BIN);
+    out.push_str("    $valueFlag = $null\n");
+    out.push_str("    foreach ($element in $commandAst.CommandElements | Select-Object -Skip 1) {\n");
+    out.push_str("        if ($element.Extent.EndOffset -ge $cursorPosition) { break }\n");
+    out.push_str("        $word = $element.ToString()\n");
+    out.push_str("        if ($valueFlag) { $valueFlag = $null; continue }\n");
+    out.push_str("        switch (\"$cmd,$word\") {\n");
+    for node in nodes {
+        for (name, _) in &node.subcommands {
+            let _ = writeln!(out, "            '{},{}' {{ $cmd = '{}__{}' }}", node.id, name, node.id, name);
+        }
+        for (flag, _) in node.value_flags() {
+            for spelling in &flag.spellings {
+                let _ = writeln!(out, "            '{},{}' {{ $valueFlag = '{},{}' }}", node.id, spelling, node.id, flag.spellings[0]);
+            }
+        }
+    }
+    out.push_str("        }\n");
+    out.push_str("    }\n\n");
+
+    out.push_str("    $candidates = if ($valueFlag) {\n");
+    out.push_str("        switch ($valueFlag) {\n");
+    for node in nodes {
+        for (flag, values) in node.value_flags() {
+            let _ = writeln!(out, "            '{},{}' {{ @({}) }}", node.id, flag.spellings[0], list(values));
+        }
+    }
+    out.push_str("        }\n");
+    out.push_str("    } else {\n");
+    out.push_str("        switch ($cmd) {\n");
+    for node in nodes {
+        let _ = writeln!(out, "            '{}' {{ @({}) }}", node.id, list(&node.all_words()));
+    }
+    out.push_str("        }\n");
+    out.push_str("    }\n\n");
+
+    out.push_str("    $candidates | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n");
+    out.push_str("        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n");
+    out.push_str("    }\n");
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+    use std::io::Write as _;
+    use std::process::{Command as Process, Stdio};
+
+    /// Identifiers of every visible subcommand, e.g. `clutchctl__set__media`
+    fn subcommand_ids(cmd: &Command, id: &str, ids: &mut Vec<String>) {
+        for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+            let sub_id = format!("{}__{}", id, sub.get_name());
+            subcommand_ids(sub, &sub_id, ids);
+            ids.push(sub_id);
+        }
+    }
+
+    fn positional(nodes: &[Node], id: &str) -> Vec<String> {
+        nodes.iter().find(|node| node.id == id).unwrap().positionals[0].clone()
+    }
+
+    #[test]
+    fn test_scripts_cover_every_subcommand() {
+        let mut ids = Vec::new();
+        subcommand_ids(&Cli::command(), BIN, &mut ids);
+        assert!(ids.contains(&"clutchctl__set__keyboard".to_string()));
+
+        for &shell in Shell::value_variants() {
+            let script = script(shell);
+            for id in &ids {
+                assert!(script.contains(id.as_str()), "{:?} completion lacks {}", shell, id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scripts_list_key_and_media_names() {
+        let nodes = nodes();
+        let keys = positional(&nodes, "clutchctl__set__keyboard");
+        let media = positional(&nodes, "clutchctl__set__media");
+        assert!(keys.iter().any(|key| key == "enter"));
+        assert!(keys.iter().any(|key| key == "pagedown"));
+        assert!(media.iter().any(|button| button == "play"));
+
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(shell);
+            assert!(script.contains(&keys.join(" ")), "{:?} completion lacks key names", shell);
+            assert!(script.contains(&media.join(" ")), "{:?} completion lacks media names", shell);
+        }
+
+        let script = script(Shell::Powershell);
+        assert!(script.contains("'enter'") && script.contains("'play'"));
+    }
+
+    #[test]
+    fn test_bash_script_parses() {
+        let mut bash = match Process::new("bash").arg("-n").stdin(Stdio::piped()).spawn() {
+            Ok(bash) => bash,
+            // Nothing to check against where bash isn't installed
+            Err(_) => return,
+        };
+        bash.stdin.take().unwrap().write_all(script(Shell::Bash).as_bytes()).unwrap();
+        assert!(bash.wait().unwrap().success());
+    }
+}
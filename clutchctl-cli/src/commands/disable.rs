@@ -0,0 +1,85 @@
+//! Disable and enable command implementation
+//!
+//! Disabling is emulated on the host for every model; see
+//! `clutchctl_core::disabled`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use clutchctl_core::device::discover_devices_lazy;
+use clutchctl_core::disabled::{store_key, DisabledPedals};
+
+use crate::theme;
+
+/// Execute the disable command, or the enable command when `enable` is set
+pub fn execute(device_id: usize, pedal_str: &str, enable: bool) -> Result<()> {
+    let handle = discover_devices_lazy()
+        .context("Failed to discover USB devices")?
+        .into_iter()
+        .find(|h| h.id() == device_id)
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    let mut device = handle.open().context("Failed to open device")?;
+    let device_mut = std::sync::Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+
+    device_mut.load_configuration()
+        .context("Failed to load device configuration")?;
+
+    let indices = super::resolve_pedals(device_mut.capabilities(), pedal_str)?;
+    let mut store = DisabledPedals::load(&store_key(handle.info(), device_id))
+        .context("Failed to read the disabled pedals")?;
+
+    // Pedals already in the requested state are left alone, e.g. for "all"
+    let selected: Vec<usize> = indices.iter().copied()
+        .filter(|&i| store.is_disabled(i) == enable)
+        .collect();
+    if selected.is_empty() {
+        let state = if enable { "enabled" } else { "disabled" };
+        bail!("{} already {}", if indices.len() == 1 { "Pedal is" } else { "Pedals are" }, state);
+    }
+
+    let label = theme::id(format!("[{}]", device_id)).bold();
+    if enable {
+        let mut restored = Vec::new();
+        for &i in &selected {
+            restored.push((i, store.enable(device_mut, i)?));
+        }
+
+        // Device first: if the save fails, the store still holds the configurations
+        device_mut.save_configuration()
+            .context("Failed to save configuration to device")?;
+        store.save()
+            .with_context(|| format!("Enabled, but failed to update {}", store.path().display()))?;
+
+        for (i, config) in &restored {
+            println!("{} Enabled pedal {} on device {}: {}",
+                     theme::ok_mark(),
+                     theme::id(format!("[{}]", i + 1)),
+                     label,
+                     theme::value(config.to_string()));
+        }
+    } else {
+        for &i in &selected {
+            store.disable(device_mut, i)?;
+        }
+
+        // Store first: if the device save fails, nothing is lost
+        store.save()
+            .with_context(|| format!("Failed to write {}", store.path().display()))?;
+        device_mut.save_configuration()
+            .context("Failed to save configuration to device")?;
+
+        for &i in &selected {
+            println!("{} Disabled pedal {} on device {}",
+                     theme::ok_mark(),
+                     theme::id(format!("[{}]", i + 1)),
+                     label);
+        }
+        println!("{}", theme::hint(format!(
+            "The configuration is kept on this computer; use 'clutchctl enable {} <PEDAL>' to restore it.",
+            device_id
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Export command implementation
+
+use anyhow::{anyhow, Context, Result};
+use clutchctl_core::backup::RawBackup;
+use clutchctl_core::config_file::ConfigFile;
+use clutchctl_core::device::find_device_by_id;
+use std::path::PathBuf;
+
+/// Execute the export command; `binary` writes a raw backup to `output` instead of JSON
+pub fn execute(device_id: usize, output: Option<PathBuf>, binary: bool) -> Result<()> {
+    let mut device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    std::sync::Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?
+        .load_configuration()
+        .context("Failed to load device configuration")?;
+
+    if binary {
+        let path = output.ok_or_else(|| anyhow!("A binary export needs --output"))?;
+        let bytes = RawBackup::from_device(device.as_ref())
+            .context("Failed to read the raw configuration")?
+            .to_bytes();
+        std::fs::write(&path, bytes)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        eprintln!("Exported device [{}] to {}", device_id, path.display());
+        return Ok(());
+    }
+
+    let json = ConfigFile::from_device(device.as_ref())?.to_json()?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, json + "\n")
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Exported device [{}] to {}", device_id, path.display());
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Export-all command implementation
+//!
+//! Writes a JSON array with one export-format object per device, each with
+//! the device's serial number, so a backup of a whole rig can be matched back
+//! to the units it came from.
+
+use anyhow::{anyhow, bail, Context, Result};
+use clutchctl_core::config_file::ConfigFile;
+use clutchctl_core::device::{discover_devices_lazy, DeviceHandle};
+use std::path::PathBuf;
+
+use crate::theme;
+
+/// Execute the export-all command
+pub fn execute(output: Option<PathBuf>) -> Result<()> {
+    let handles = discover_devices_lazy().context("Failed to discover USB devices")?;
+    if handles.is_empty() {
+        bail!("No pedal devices found");
+    }
+
+    // A device that can't be read is reported, not a reason to lose the others
+    let mut files = Vec::new();
+    let mut failed = 0;
+    for handle in &handles {
+        match snapshot(handle) {
+            Ok(file) => files.push(file),
+            Err(e) => {
+                failed += 1;
+                eprintln!("{} Device {} ({}): {:#}",
+                          theme::fail_mark(),
+                          theme::id(format!("[{}]", handle.id())),
+                          handle.device_type(),
+                          e);
+            }
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&files)?;
+    match &output {
+        Some(path) => {
+            std::fs::write(path, json + "\n")
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Exported {} device(s) to {}", files.len(), path.display());
+        }
+        None => println!("{}", json),
+    }
+
+    if failed > 0 {
+        bail!("{} of {} device(s) could not be read and are missing from the export", failed, handles.len());
+    }
+
+    Ok(())
+}
+
+/// Read a device's configuration as a file tagged with its serial number
+fn snapshot(handle: &DeviceHandle) -> Result<ConfigFile> {
+    let mut device = handle.open().context("Failed to open device")?;
+    std::sync::Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?
+        .load_configuration()
+        .context("Failed to load device configuration")?;
+
+    let mut file = ConfigFile::from_device(device.as_ref())?;
+    file.serial = handle.info().serial_number.clone();
+    Ok(file)
+}
@@ -0,0 +1,147 @@
+//! Import command implementation
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use clutchctl_core::backup::RawBackup;
+use clutchctl_core::config_file::ConfigFile;
+use clutchctl_core::device::{find_device_by_id, PedalDevice};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::theme;
+
+/// Execute the import command; `from_vendor` reads a footswitch script instead of JSON
+pub fn execute(
+    device_id: Option<usize>,
+    all_devices: bool,
+    file: &Path,
+    only: &[String],
+    dry_run: bool,
+    from_vendor: bool,
+) -> Result<()> {
+    let contents = if file == Path::new("-") {
+        let mut contents = String::new();
+        std::io::stdin().read_to_string(&mut contents).context("Failed to read standard input")?;
+        contents
+    } else {
+        std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?
+    };
+    let config_file = if from_vendor {
+        ConfigFile::from_footswitch(&contents)
+            .with_context(|| format!("Invalid footswitch script {}", file.display()))?
+    } else {
+        ConfigFile::from_json(&contents)
+            .with_context(|| format!("Invalid configuration file {}", file.display()))?
+    };
+
+    if all_devices {
+        return super::for_each_device(|handle| {
+            let device = handle.open().context("Failed to open device")?;
+            apply(device, handle.id(), &config_file, only, dry_run)
+        });
+    }
+
+    let device_id = device_id.ok_or_else(|| anyhow!("No device ID given"))?;
+    let device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    apply(device, device_id, &config_file, only, dry_run)
+}
+
+/// Execute the import command for a binary backup made by `export --binary`
+///
+/// Every pedal is restored; the backup must be of the same USB device and
+/// pedal count.
+pub fn execute_binary(device_id: Option<usize>, file: &Path, dry_run: bool) -> Result<()> {
+    let bytes = if file == Path::new("-") {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes).context("Failed to read standard input")?;
+        bytes
+    } else {
+        std::fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?
+    };
+    let backup = RawBackup::from_bytes(&bytes)
+        .with_context(|| format!("Invalid binary backup {}", file.display()))?;
+
+    let device_id = device_id.ok_or_else(|| anyhow!("No device ID given"))?;
+    let mut device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+    let device_mut = Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+
+    let label = theme::id(format!("[{}]", device_id)).bold();
+
+    if dry_run {
+        let configurations = backup.configurations(device_mut)?;
+        println!("\n{} No changes written to device {}", theme::warning("Dry run:").bold(), label);
+        for (index, config) in configurations.iter().enumerate() {
+            println!("  {} {}", theme::id(format!("[{}]", index + 1)), theme::value(config.to_string()));
+        }
+        return Ok(());
+    }
+
+    backup.restore(device_mut)?;
+    device_mut.save_configuration()
+        .context("Failed to save configuration to device")?;
+
+    println!("{} Restored {} pedal(s) on device {}", theme::ok_mark(), backup.configs.len(), label);
+
+    Ok(())
+}
+
+/// Import into one device; pedals not in the file (or not in `only`) keep their configuration
+fn apply(
+    mut device: Arc<dyn PedalDevice + Send + Sync>,
+    device_id: usize,
+    config_file: &ConfigFile,
+    only: &[String],
+    dry_run: bool,
+) -> Result<()> {
+    let device_mut = Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+
+    // Read first: saving may rewrite pedals the file doesn't mention
+    device_mut.load_configuration()
+        .context("Failed to load device configuration")?;
+
+    let only: Option<Vec<usize>> = if only.is_empty() {
+        None
+    } else {
+        let mut indices = Vec::new();
+        for selector in only {
+            indices.extend(super::resolve_pedals(device_mut.capabilities(), selector)?);
+        }
+        Some(indices)
+    };
+
+    let label = theme::id(format!("[{}]", device_id)).bold();
+
+    if dry_run {
+        let configurations = config_file.configurations(device_mut, only.as_deref())?;
+        println!("\n{} No changes written to device {}", theme::warning("Dry run:").bold(), label);
+        if let Some(saved_at) = &config_file.saved_at {
+            println!("  Exported at {}", theme::value(saved_at));
+        }
+        for (index, config) in configurations {
+            println!("  {} {}", theme::id(format!("[{}]", index + 1)), theme::value(config.to_string()));
+        }
+        return Ok(());
+    }
+
+    let indices = config_file.apply(device_mut, only.as_deref())?;
+    if indices.is_empty() {
+        println!("Nothing to import for device {}", label);
+        return Ok(());
+    }
+
+    device_mut.save_configuration()
+        .context("Failed to save configuration to device")?;
+
+    let pedals: Vec<String> = indices.iter().map(|index| (index + 1).to_string()).collect();
+    println!("{} Imported pedal(s) {} on device {}", theme::ok_mark(), pedals.join(", "), label);
+
+    Ok(())
+}
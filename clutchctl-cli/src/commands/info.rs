@@ -0,0 +1,76 @@
+//! Info command implementation
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use clutchctl_core::device::discover_devices_lazy;
+use serde::Serialize;
+
+use crate::theme;
+
+/// JSON representation of a device's hardware info
+#[derive(Serialize)]
+struct InfoEntry<'a> {
+    id: usize,
+    device_type: &'a str,
+    model: &'a str,
+    firmware: &'a str,
+    manufacturer: Option<&'a str>,
+    product: Option<&'a str>,
+    serial_number: Option<&'a str>,
+    vendor_id: String,
+    product_id: String,
+    interface: i32,
+    path: String,
+}
+
+/// Execute the info command: print the HID strings, USB IDs and firmware of a device
+pub fn execute(device_id: usize, json: bool) -> Result<()> {
+    let handle = discover_devices_lazy()
+        .context("Failed to discover USB devices")?
+        .into_iter()
+        .find(|h| h.id() == device_id)
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    // Model and firmware come from the device itself, everything else from enumeration
+    let device = handle.open().context("Failed to open device")?;
+    let info = handle.info();
+    let path = info.path.to_string_lossy();
+
+    if json {
+        let entry = InfoEntry {
+            id: device_id,
+            device_type: handle.device_type(),
+            model: device.model(),
+            firmware: device.version(),
+            manufacturer: info.manufacturer.as_deref(),
+            product: info.product.as_deref(),
+            serial_number: info.serial_number.as_deref(),
+            vendor_id: format!("{:04x}", info.vendor_id),
+            product_id: format!("{:04x}", info.product_id),
+            interface: info.interface_number,
+            path: path.into_owned(),
+        };
+        println!("{}", serde_json::to_string_pretty(&entry)?);
+        return Ok(());
+    }
+
+    let or_unknown = |value: Option<&str>| match value {
+        Some(value) if !value.is_empty() => theme::value(value),
+        _ => theme::unconfigured("unknown"),
+    };
+
+    println!("{} {} {}",
+             "Device".bold(),
+             theme::id(format!("[{}]", device_id)).bold(),
+             theme::value(device.model()));
+    println!("  Type:         {}", handle.device_type());
+    println!("  Firmware:     {}", device.version());
+    println!("  Manufacturer: {}", or_unknown(info.manufacturer.as_deref()));
+    println!("  Product:      {}", or_unknown(info.product.as_deref()));
+    println!("  Serial:       {}", or_unknown(info.serial_number.as_deref()));
+    println!("  USB ID:       {:04x}:{:04x}", info.vendor_id, info.product_id);
+    println!("  Interface:    {}", info.interface_number);
+    println!("  Path:         {}", path);
+
+    Ok(())
+}
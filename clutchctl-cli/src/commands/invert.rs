@@ -0,0 +1,64 @@
+//! Invert command implementation
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use clutchctl_core::configuration::Trigger;
+use clutchctl_core::device::find_device_by_id;
+
+use crate::theme;
+
+/// Execute the invert command: flip the trigger of the selected pedals
+pub fn execute(device_id: usize, pedal_str: &str) -> Result<()> {
+    let mut device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    let device_mut = std::sync::Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+
+    device_mut.load_configuration()
+        .context("Failed to load device configuration")?;
+
+    let indices = super::resolve_pedals(device_mut.capabilities(), pedal_str)?;
+
+    // Check every pedal before changing any, so a bad one doesn't leave a partial update
+    let mut inverted = Vec::new();
+    for &i in &indices {
+        let mut config = device_mut.get_pedal_configuration(i)
+            .context("Failed to get pedal configuration")?;
+        let trigger = match config.trigger() {
+            Some(Trigger::OnPress) => Trigger::OnRelease,
+            Some(Trigger::OnRelease) => Trigger::OnPress,
+            None => bail!("Pedal {} has no trigger to invert ({})", i + 1, config),
+        };
+        if !device_mut.capabilities().supports_trigger(trigger) {
+            bail!("{} does not support triggering on release", device_mut.model());
+        }
+        config.set_trigger(trigger);
+        inverted.push((i, config, trigger));
+    }
+
+    for (i, config, _) in &inverted {
+        device_mut.set_pedal_configuration(*i, config.clone())
+            .context("Failed to set pedal configuration")?;
+    }
+
+    device_mut.save_configuration()
+        .context("Failed to save configuration to device")?;
+
+    let label = theme::id(format!("[{}]", device_id)).bold();
+    for (i, config, trigger) in &inverted {
+        let trigger_str = match trigger {
+            Trigger::OnPress => "on press",
+            Trigger::OnRelease => "on release",
+        };
+        println!("{} Pedal {} on device {} now triggers {}: {}",
+                 theme::ok_mark(),
+                 theme::id(format!("[{}]", i + 1)),
+                 label,
+                 trigger_str,
+                 theme::value(config.to_string()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! LED command implementation
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use clutchctl_core::device::{find_device_by_id, LedMode};
+
+use crate::theme;
+
+/// Execute the led command: print the LED mode, or set it when `mode` is given
+pub fn execute(device_id: usize, mode: Option<LedMode>) -> Result<()> {
+    let mut device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    let label = theme::id(format!("[{}]", device_id)).bold();
+
+    match mode {
+        None => {
+            let mode = device.get_led_mode().context("Failed to read the LED mode")?;
+            println!("LED of device {}: {}", label, theme::value(mode.as_str()));
+        }
+        Some(mode) => {
+            std::sync::Arc::get_mut(&mut device)
+                .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?
+                .set_led_mode(mode)
+                .context("Failed to set the LED mode")?;
+            println!("{} Set LED of device {} to {}", theme::ok_mark(), label, theme::value(mode.as_str()));
+        }
+    }
+
+    Ok(())
+}
@@ -2,49 +2,269 @@
 
 use anyhow::{Context, Result};
 use colored::Colorize;
-use clutchctl_core::device::discover_devices;
+use clutchctl_core::device::{discover_devices_detailed, discover_devices_lazy, supported_device_ids};
+use clutchctl_core::usb::list_all_devices;
+use serde::Serialize;
+
+use crate::theme;
+
+/// JSON representation of a discovered pedal device
+#[derive(Serialize)]
+struct DeviceEntry<'a> {
+    id: usize,
+    device_type: &'a str,
+    vendor_id: String,
+    product_id: String,
+    manufacturer: Option<&'a str>,
+    product: Option<&'a str>,
+}
+
+/// JSON representation of a device that opened
+#[derive(Serialize)]
+struct OpenedEntry<'a> {
+    id: usize,
+    model: &'a str,
+    version: &'a str,
+    vendor_id: String,
+    product_id: String,
+}
+
+/// JSON representation of a device that was found but failed to open
+#[derive(Serialize)]
+struct FailedEntry<'a> {
+    vendor_id: String,
+    product_id: String,
+    product: Option<&'a str>,
+    error: String,
+}
+
+/// JSON output of `list --open`
+#[derive(Serialize)]
+struct OpenReport<'a> {
+    devices: Vec<OpenedEntry<'a>>,
+    failed: Vec<FailedEntry<'a>>,
+}
+
+/// JSON representation of a raw HID device
+#[derive(Serialize)]
+struct HidEntry {
+    vendor_id: String,
+    product_id: String,
+    interface: i32,
+    manufacturer: Option<String>,
+    product: Option<String>,
+    supported: Option<String>,
+}
 
 /// Execute the list command
-pub fn execute() -> Result<()> {
-    println!("Discovering USB pedal devices...\n");
+pub fn execute(all: bool, json: bool, count: bool, open: bool) -> Result<()> {
+    if all {
+        return execute_all(json);
+    }
+
+    if open {
+        return execute_open(json);
+    }
+
+    if count {
+        let devices = discover_devices_lazy().context("Failed to discover USB devices")?;
+        println!("{}", devices.len());
+        return Ok(());
+    }
 
+    if !json {
+        println!("Discovering USB pedal devices...\n");
+    }
+
+    // Devices are only enumerated, not opened; `show` reads model and configuration
     #[cfg(target_os = "linux")]
-    let devices = discover_devices()
+    let devices = discover_devices_lazy()
         .context("Failed to discover USB devices. Try running with sudo if you see permission errors.")?;
 
     #[cfg(not(target_os = "linux"))]
-    let devices = discover_devices()
+    let devices = discover_devices_lazy()
         .context("Failed to discover USB devices. Try running as Administrator if you see permission errors.")?;
 
+    if json {
+        let entries: Vec<DeviceEntry> = devices
+            .iter()
+            .map(|device| DeviceEntry {
+                id: device.id(),
+                device_type: device.device_type(),
+                vendor_id: format!("{:04x}", device.info().vendor_id),
+                product_id: format!("{:04x}", device.info().product_id),
+                manufacturer: device.info().manufacturer.as_deref(),
+                product: device.info().product.as_deref(),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
     if devices.is_empty() {
-        println!("{}", "No pedal devices found.".yellow());
+        println!("{}", theme::warning("No pedal devices found."));
         println!("\nMake sure your device is connected and you have the necessary permissions.");
         #[cfg(target_os = "linux")]
         println!("On Linux, you may need to install udev rules or run with sudo.");
+        println!("Use 'clutchctl list --all' to see every HID device.");
         return Ok(());
     }
 
     println!("Found {} device(s):\n", devices.len());
 
-    for device in devices {
-        let id = device.id();
-        let model = device.model();
-        let version = device.version();
-        let capabilities = device.capabilities();
+    for device in &devices {
+        let info = device.info();
+        let name = info.product.as_deref().unwrap_or(device.device_type());
+
+        println!("  {} {}", theme::id(format!("[{}]", device.id())).bold(), theme::value(name));
+        println!("      Type:     {}", device.device_type());
+        println!("      USB ID:   {:04x}:{:04x}", info.vendor_id, info.product_id);
+        if let Some(manufacturer) = &info.manufacturer {
+            println!("      Vendor:   {}", manufacturer);
+        }
+
+        println!();
+    }
+
+    println!("{}", theme::hint("Use 'clutchctl show <ID>' to see model, version and configuration."));
 
-        println!("  {} {}", format!("[{}]", id).cyan().bold(), model.green());
-        println!("      Version:  {}", version);
-        println!("      Pedals:   {}", capabilities.pedal_count);
+    Ok(())
+}
 
-        if !capabilities.pedal_names.is_empty() {
-            let names = capabilities.pedal_names.join(", ");
-            println!("      Names:    {}", names);
+/// Open every supported device, listing the ones that opened and why the others didn't
+fn execute_open(json: bool) -> Result<()> {
+    let report = discover_devices_detailed().context("Failed to discover USB devices")?;
+
+    if json {
+        let output = OpenReport {
+            devices: report.devices
+                .iter()
+                .map(|device| {
+                    let (vendor_id, product_id) = device.usb_ids();
+                    OpenedEntry {
+                        id: device.id(),
+                        model: device.model(),
+                        version: device.version(),
+                        vendor_id: format!("{:04x}", vendor_id),
+                        product_id: format!("{:04x}", product_id),
+                    }
+                })
+                .collect(),
+            failed: report.failed
+                .iter()
+                .map(|(info, error)| FailedEntry {
+                    vendor_id: format!("{:04x}", info.vendor_id),
+                    product_id: format!("{:04x}", info.product_id),
+                    product: info.product.as_deref(),
+                    error: error.to_string(),
+                })
+                .collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if report.devices.is_empty() && report.failed.is_empty() {
+        println!("{}", theme::warning("No pedal devices found."));
+        println!("Use 'clutchctl list --all' to see every HID device.");
+        return Ok(());
+    }
+
+    if !report.devices.is_empty() {
+        println!("Opened {} device(s):\n", report.devices.len());
+        for device in &report.devices {
+            let (vendor_id, product_id) = device.usb_ids();
+            println!("  {} {} {}", theme::ok_mark(), theme::id(format!("[{}]", device.id())).bold(), theme::value(device.model()));
+            println!("      Version:  {}", device.version());
+            println!("      USB ID:   {:04x}:{:04x}", vendor_id, product_id);
+            println!();
         }
+    }
 
+    if !report.failed.is_empty() {
+        println!("{}\n", theme::warning(format!(
+            "{} device{} found but failed to open:",
+            report.failed.len(),
+            if report.failed.len() == 1 { "" } else { "s" }
+        )));
+        for (info, error) in &report.failed {
+            let name = info.product.as_deref().unwrap_or("Unknown device");
+            println!("  {} {} ({:04x}:{:04x})", theme::fail_mark(), theme::value(name), info.vendor_id, info.product_id);
+            println!("      {}", error);
+        }
         println!();
     }
 
-    println!("{}", "Use 'clutchctl show <ID>' to see device configuration.".dimmed());
+    Ok(())
+}
+
+/// List every HID device, flagging the ones clutchctl supports
+fn execute_all(json: bool) -> Result<()> {
+    let devices = list_all_devices().context("Failed to enumerate HID devices")?;
+    let supported_ids = supported_device_ids();
+
+    let entries: Vec<HidEntry> = devices
+        .into_iter()
+        .map(|d| HidEntry {
+            vendor_id: format!("{:04x}", d.vendor_id),
+            product_id: format!("{:04x}", d.product_id),
+            interface: d.interface_number,
+            manufacturer: d.manufacturer,
+            product: d.product,
+            supported: supported_ids
+                .iter()
+                .find(|&&(vid, pid, _)| vid == d.vendor_id && pid == d.product_id)
+                .map(|(_, _, device_type)| device_type.clone()),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("{}", theme::warning("No HID devices found."));
+        return Ok(());
+    }
+
+    let manufacturer_width = entries.iter()
+        .map(|e| e.manufacturer.as_deref().unwrap_or("-").len())
+        .max()
+        .unwrap_or(0)
+        .max("MANUFACTURER".len());
+    let product_width = entries.iter()
+        .map(|e| e.product.as_deref().unwrap_or("-").len())
+        .max()
+        .unwrap_or(0)
+        .max("PRODUCT".len());
+
+    println!("{}", format!(
+        "{:<4}  {:<4}  {:<9}  {:<mw$}  {:<pw$}  {}",
+        "VID", "PID", "INTERFACE", "MANUFACTURER", "PRODUCT", "SUPPORTED",
+        mw = manufacturer_width, pw = product_width
+    ).bold());
+
+    for entry in &entries {
+        let supported = match &entry.supported {
+            Some(device_type) => theme::value(device_type),
+            None => theme::hint("-"),
+        };
+        println!(
+            "{:<4}  {:<4}  {:<9}  {:<mw$}  {:<pw$}  {}",
+            entry.vendor_id,
+            entry.product_id,
+            entry.interface,
+            entry.manufacturer.as_deref().unwrap_or("-"),
+            entry.product.as_deref().unwrap_or("-"),
+            supported,
+            mw = manufacturer_width,
+            pw = product_width
+        );
+    }
+
+    let supported_count = entries.iter().filter(|e| e.supported.is_some()).count();
+    println!("\n{} HID device(s), {} supported", entries.len(), supported_count);
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,5 +1,84 @@
 //! Command implementations
 
+pub mod capabilities;
+pub mod commit;
+pub mod completions;
+pub mod disable;
+pub mod export;
+pub mod export_all;
+pub mod import;
+pub mod info;
+pub mod invert;
+pub mod led;
 pub mod list;
+pub mod reset;
 pub mod show;
-pub mod set;
\ No newline at end of file
+pub mod set;
+pub mod stage;
+pub mod swap;
+pub mod trigger;
+pub mod version;
+#[cfg(feature = "tui")]
+pub mod tui;
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use clutchctl_core::device::{discover_devices_lazy, DeviceCapabilities, DeviceHandle, PedalNumbering};
+use clutchctl_core::error::PedalError;
+use std::sync::OnceLock;
+
+use crate::theme;
+
+/// Numbering of pedal selectors given on the command line
+static PEDAL_NUMBERING: OnceLock<PedalNumbering> = OnceLock::new();
+
+/// Select how numeric pedal selectors are counted; only the first call has an effect
+pub fn set_pedal_numbering(numbering: PedalNumbering) {
+    let _ = PEDAL_NUMBERING.set(numbering);
+}
+
+/// Resolve a pedal selector (or "all"), listing the valid pedal names when it doesn't match
+///
+/// Numbers are 1-based unless `--zero-based` was given.
+pub fn resolve_pedals(capabilities: &DeviceCapabilities, selector: &str) -> Result<Vec<usize>> {
+    let numbering = PEDAL_NUMBERING.get().copied().unwrap_or_default();
+    capabilities.resolve_pedals_numbered(selector, numbering).map_err(|e| match e {
+        PedalError::InvalidPedalSelector(_) => {
+            anyhow!("{}. Available pedals: {}, or 'all'", e, capabilities.pedal_names.join(", "))
+        }
+        e => e.into(),
+    })
+}
+
+/// Run an operation on every discovered device, for `--all-devices`
+///
+/// A failing device doesn't stop the others. Prints a per-device summary and
+/// fails if any device failed.
+pub fn for_each_device(mut operation: impl FnMut(&DeviceHandle) -> Result<()>) -> Result<()> {
+    let handles = discover_devices_lazy().context("Failed to discover USB devices")?;
+    if handles.is_empty() {
+        bail!("No pedal devices found");
+    }
+
+    let results: Vec<(&DeviceHandle, Result<()>)> = handles
+        .iter()
+        .map(|handle| (handle, operation(handle)))
+        .collect();
+
+    println!("\n{}", "Summary:".bold());
+    let mut failures = 0;
+    for (handle, result) in &results {
+        let label = theme::id(format!("[{}]", handle.id())).bold();
+        match result {
+            Ok(()) => println!("  {} {} {}", theme::ok_mark(), label, handle.device_type()),
+            Err(e) => {
+                failures += 1;
+                println!("  {} {} {}: {:#}", theme::fail_mark(), label, handle.device_type(), e);
+            }
+        }
+    }
+
+    if failures > 0 {
+        bail!("{} of {} device(s) failed", failures, results.len());
+    }
+    Ok(())
+}
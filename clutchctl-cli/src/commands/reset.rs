@@ -0,0 +1,22 @@
+//! Reset command implementation
+
+use anyhow::{anyhow, Context, Result};
+use clutchctl_core::configuration::Configuration;
+use clutchctl_core::device::{find_device_by_id, ALL_PEDALS};
+
+/// Execute the reset command: unconfigure every pedal
+pub fn execute(device_id: Option<usize>, all_devices: bool) -> Result<()> {
+    if all_devices {
+        return super::for_each_device(|handle| {
+            let device = handle.open().context("Failed to open device")?;
+            super::set::apply(device, handle.id(), ALL_PEDALS, &Configuration::Unconfigured, false)
+        });
+    }
+
+    let device_id = device_id.ok_or_else(|| anyhow!("No device ID given"))?;
+    let device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    super::set::apply(device, device_id, ALL_PEDALS, &Configuration::Unconfigured, false)
+}
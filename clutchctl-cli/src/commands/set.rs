@@ -7,77 +7,53 @@ use clutchctl_core::configuration::{
     MouseConfiguration, TextConfiguration, Trigger, BaseConfiguration,
     keyboard::KeyMode,
 };
-use clutchctl_core::device::discover_devices;
+use clutchctl_core::device::{find_device_by_id, PedalDevice};
+use std::sync::Arc;
 
 use crate::cli::{MouseMode, SetConfig};
+use crate::theme;
 
 /// Execute the set command
-pub fn execute(device_id: usize, pedal_str: String, config: SetConfig) -> Result<()> {
-    // Find the device
-    let devices = discover_devices()
-        .context("Failed to discover USB devices")?;
-
-    let device = devices
-        .into_iter()
-        .find(|d| d.id() == device_id)
-        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
-
-    // Get mutable device reference
-    let mut device = device;
-    let device_mut = std::sync::Arc::get_mut(&mut device)
-        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+pub fn execute(
+    device_id: Option<usize>,
+    all_devices: bool,
+    pedal_str: String,
+    config: SetConfig,
+    dry_run: bool,
+) -> Result<()> {
+    let new_config = build_config(config)?;
+
+    if all_devices {
+        return super::for_each_device(|handle| {
+            let device = handle.open().context("Failed to open device")?;
+            apply(device, handle.id(), &pedal_str, &new_config, dry_run)
+        });
+    }
 
-    // Load current configuration
-    device_mut.load_configuration()
-        .context("Failed to load device configuration")?;
+    let device_id = device_id.ok_or_else(|| anyhow!("No device ID given"))?;
 
-    // Parse pedal index (get capabilities, parse, then drop the borrow)
-    let (pedal_index, pedal_name) = {
-        let capabilities = device_mut.capabilities();
+    // Find and open only the requested device
+    let device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
 
-        let pedal_index = if let Ok(num) = pedal_str.parse::<usize>() {
-            // 1-based index from user
-            if num == 0 || num > capabilities.pedal_count {
-                return Err(anyhow!(
-                    "Invalid pedal index {}. Device has {} pedal(s)",
-                    num,
-                    capabilities.pedal_count
-                ));
-            }
-            num - 1 // Convert to 0-based
-        } else {
-            // Try to find by name
-            capabilities.find_pedal_by_name(&pedal_str)
-                .ok_or_else(|| {
-                    let names = capabilities.pedal_names.join(", ");
-                    anyhow!(
-                        "Unknown pedal '{}'. Available pedals: {}",
-                        pedal_str,
-                        names
-                    )
-                })?
-        };
-
-        let pedal_name = capabilities.get_pedal_name(pedal_index)
-            .unwrap_or(&format!("pedal{}", pedal_index + 1))
-            .to_string();
-
-        (pedal_index, pedal_name)
-    };
+    apply(device, device_id, &pedal_str, &new_config, dry_run)
+}
 
+/// Build the configuration requested on the command line
+pub fn build_config(config: SetConfig) -> Result<Configuration> {
     // Create configuration based on the command
     let new_config = match config {
         SetConfig::None => Configuration::Unconfigured,
 
         SetConfig::Keyboard { keys, once, invert } => {
             let mode = if once { KeyMode::OneShot } else { KeyMode::Standard };
-            let (modifiers, main_key) = KeyboardConfiguration::parse_modifiers(&keys);
+            let (modifiers, key_list) = KeyboardConfiguration::parse_keys(&keys)?;
 
-            let key_list = if let Some(key) = main_key {
-                vec![key]
-            } else {
-                return Err(anyhow!("No main key specified"));
-            };
+            // Modifiers alone make the pedal act as a held modifier
+            if key_list.is_empty() && modifiers.is_empty() {
+                return Err(anyhow!("No key specified"));
+            }
 
             let mut kbd_config = KeyboardConfiguration::with_modifiers(mode, key_list, modifiers);
             if invert {
@@ -88,14 +64,15 @@ pub fn execute(device_id: usize, pedal_str: String, config: SetConfig) -> Result
 
         SetConfig::Mouse { mode, invert } => {
             let mut mouse_config = match mode {
-                MouseMode::Buttons { buttons } => {
+                MouseMode::Buttons { buttons, clicks } => {
                     let button_set = MouseConfiguration::parse_buttons(&buttons)
                         .ok_or_else(|| anyhow!("Invalid mouse button: {}", buttons))?;
-                    MouseConfiguration::buttons(button_set)
+                    MouseConfiguration::buttons_with_clicks(button_set, clicks)?
                 }
                 MouseMode::Axis { x, y, wheel } => {
                     MouseConfiguration::axis(x, y, wheel)
                 }
+                MouseMode::Scroll { amount } => MouseConfiguration::axis(0, 0, amount),
             };
 
             if invert {
@@ -105,10 +82,9 @@ pub fn execute(device_id: usize, pedal_str: String, config: SetConfig) -> Result
         }
 
         SetConfig::Text { text, invert } => {
-            if text.len() > 38 {
-                return Err(anyhow!("Text too long (max 38 characters)"));
-            }
-            let mut text_config = TextConfiguration::new(text);
+            // The length limit depends on the model; apply checks it
+            let mut text_config = TextConfiguration::from_escaped(&text)?;
+            text_config.validate_characters()?;
             if invert {
                 text_config.set_trigger(Trigger::OnRelease);
             }
@@ -118,6 +94,10 @@ pub fn execute(device_id: usize, pedal_str: String, config: SetConfig) -> Result
         SetConfig::Media { button, invert } => {
             let media_button = MediaConfiguration::parse_button(&button)
                 .ok_or_else(|| anyhow!("Unknown media button: {}", button))?;
+            if media_button.is_experimental() {
+                eprintln!("{} the code for '{}' is unconfirmed; the pedal may send a different key or nothing",
+                          theme::warning("Warning:").bold(), button);
+            }
             let mut media_config = MediaConfiguration::new(media_button);
             if invert {
                 media_config.set_trigger(Trigger::OnRelease);
@@ -136,27 +116,129 @@ pub fn execute(device_id: usize, pedal_str: String, config: SetConfig) -> Result
         }
     };
 
-    // Set the configuration
-    device_mut.set_pedal_configuration(pedal_index, new_config.clone())
-        .context("Failed to set pedal configuration")?;
+    Ok(new_config)
+}
+
+/// Set the selected pedals of an opened device and save them in one session
+pub fn apply(
+    mut device: Arc<dyn PedalDevice + Send + Sync>,
+    device_id: usize,
+    pedal_str: &str,
+    new_config: &Configuration,
+    dry_run: bool,
+) -> Result<()> {
+    // Get mutable device reference
+    let device_mut = Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+
+    // Load current configuration
+    device_mut.load_configuration()
+        .context("Failed to load device configuration")?;
+
+    // Resolve the selected pedals (get capabilities, parse, then drop the borrow)
+    let pedals: Vec<(usize, String)> = {
+        let capabilities = device_mut.capabilities();
+
+        super::resolve_pedals(capabilities, pedal_str)?
+            .into_iter()
+            .map(|pedal_index| {
+                let pedal_name = capabilities.get_pedal_name(pedal_index)
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("pedal{}", pedal_index + 1));
+                (pedal_index, pedal_name)
+            })
+            .collect()
+    };
+
+    // Reject what the model can't encode or the firmware would silently ignore
+    if let Some(config_type) = new_config.configuration_type() {
+        if !device_mut.capabilities().supports_config_type(config_type) {
+            return Err(anyhow!("{} does not support {} configurations", device_mut.model(), config_type));
+        }
+    }
+    if let Some(trigger) = new_config.trigger() {
+        if !device_mut.capabilities().supports_trigger(trigger) {
+            return Err(anyhow!(
+                "{} does not support triggering on release (--invert)",
+                device_mut.model()
+            ));
+        }
+    }
+
+    // Checks the encoded length against the model's limit
+    if let Configuration::Text(text) = new_config {
+        device_mut.capabilities().validate_text(text)?;
+    }
+
+    // In dry-run mode, show what would be written and stop before touching the device
+    if dry_run {
+        let reports = pedals
+            .iter()
+            .map(|(pedal_index, _)| device_mut.encode_pedal_reports(*pedal_index, new_config))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context("Failed to encode configuration")?;
+
+        println!("\n{} No changes written to device {}",
+                 theme::warning("Dry run:").bold(),
+                 theme::id(format!("[{}]", device_id)).bold());
+        println!("  Config: {}", theme::value(new_config.to_string()));
+        if let Some(trigger) = new_config.trigger() {
+            let trigger_str = match trigger {
+                Trigger::OnPress => "on press",
+                Trigger::OnRelease => "on release",
+            };
+            println!("  Trigger: {}", trigger_str);
+        }
+        for ((pedal_index, pedal_name), reports) in pedals.iter().zip(&reports) {
+            println!("  Pedal:  {} {} ({} reports):",
+                     theme::name(pedal_name),
+                     theme::id(format!("[{}]", pedal_index + 1)),
+                     reports.len());
+            for report in reports {
+                let hex: Vec<String> = report.iter().map(|b| format!("{:02x}", b)).collect();
+                println!("    {}", hex.join(" "));
+            }
+        }
+
+        return Ok(());
+    }
+
+    // Set the configuration of every selected pedal, then save them in one session
+    for (pedal_index, _) in &pedals {
+        device_mut.set_pedal_configuration(*pedal_index, new_config.clone())
+            .context("Failed to set pedal configuration")?;
+    }
+
+    if !device_mut.has_modifications() {
+        println!("\nNo change: {} already set to {} on device {}",
+                 if pedals.len() == 1 { "pedal is" } else { "pedals are" },
+                 theme::value(new_config.to_string()),
+                 theme::id(format!("[{}]", device_id)).bold());
+        return Ok(());
+    }
 
     // Save to device
     device_mut.save_configuration()
         .context("Failed to save configuration to device")?;
 
     // Display success message
-    println!("\n{} Configuration updated for {} {} on device {}",
-             "✓".green().bold(),
-             pedal_name.yellow().bold(),
-             format!("[{}]", pedal_index + 1).cyan(),
-             format!("[{}]", device_id).cyan().bold());
-
-    match &new_config {
+    let pedal_list: Vec<String> = pedals
+        .iter()
+        .map(|(pedal_index, pedal_name)| {
+            format!("{} {}", theme::name(pedal_name), theme::id(format!("[{}]", pedal_index + 1)))
+        })
+        .collect();
+    println!("\n{} Configuration updated for {} on device {}",
+             theme::ok_mark(),
+             pedal_list.join(", "),
+             theme::id(format!("[{}]", device_id)).bold());
+
+    match new_config {
         Configuration::Unconfigured => {
-            println!("  Set to: {}", "Unconfigured".red());
+            println!("  Set to: {}", theme::unconfigured("Unconfigured"));
         }
         config => {
-            println!("  Set to: {}", config.to_string().green());
+            println!("  Set to: {}", theme::value(config.to_string()));
         }
     }
 
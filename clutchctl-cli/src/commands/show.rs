@@ -1,20 +1,58 @@
 //! Show command implementation
+//!
+//! Besides the default pretty output, `--format env` prints one shell
+//! assignment per line for use with `eval`:
+//!
+//! - `CLUTCHCTL_DEVICE_MODEL`, `CLUTCHCTL_DEVICE_VERSION`, `CLUTCHCTL_PEDAL_COUNT`
+//! - `CLUTCHCTL_PEDAL_<N>_NAME`, `CLUTCHCTL_PEDAL_<N>_TYPE` and, when configured,
+//!   `CLUTCHCTL_PEDAL_<N>_TRIGGER` (`press` or `release`), where `<N>` is the
+//!   pedal index starting at 1
+//! - type specific fields: `KEYS` and `MODE` (keyboard), `BUTTONS` or `X`, `Y`,
+//!   `WHEEL` (mouse), `TEXT` (text), `BUTTON` (media and gamepad) and
+//!   `CONFIG_TYPE`, `DATA` (raw configurations clutchctl doesn't model, as hex)
+//!
+//! Every value is single-quoted so text configurations can't inject shell code.
+//!
+//! With `-v`, the pretty output also prints the raw 40-byte packet under each
+//! pedal and the JSON output adds it as a `raw` hex string.
 
 use anyhow::{anyhow, Context, Result};
 use colored::Colorize;
+use clutchctl_core::configuration::keyboard::KeyMode;
+use clutchctl_core::configuration::mouse::MouseMode;
 use clutchctl_core::configuration::{Configuration, Trigger};
-use clutchctl_core::device::discover_devices;
+use clutchctl_core::device::{find_device_by_id, open_by_path};
+use serde_json::{json, Map, Value};
+use std::ffi::CString;
 
-/// Execute the show command
-pub fn execute(device_id: usize) -> Result<()> {
-    // Find the device
-    let devices = discover_devices()
-        .context("Failed to discover USB devices")?;
+use crate::cli::ShowFormat;
+use crate::theme;
 
-    let device = devices
-        .into_iter()
-        .find(|d| d.id() == device_id)
-        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+/// Execute the show command
+pub fn execute(
+    device_id: Option<usize>,
+    path: Option<String>,
+    pedal: Option<String>,
+    format: ShowFormat,
+    verbose: bool,
+) -> Result<()> {
+    // Open only the requested device, by path or by discovery ID
+    let (device_id, device) = match path {
+        Some(path) => {
+            let c_path = CString::new(path.as_str())
+                .map_err(|_| anyhow!("Invalid device path: {}", path))?;
+            let device = open_by_path(&c_path)
+                .with_context(|| format!("Failed to open device at {}", path))?;
+            (device.id(), device)
+        }
+        None => {
+            let device_id = device_id.ok_or_else(|| anyhow!("No device ID given"))?;
+            let device = find_device_by_id(device_id)
+                .context("Failed to discover USB devices")?
+                .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+            (device_id, device)
+        }
+    };
 
     // Load configuration
     let mut device = device;
@@ -25,52 +63,194 @@ pub fn execute(device_id: usize) -> Result<()> {
             .context("Failed to load device configuration")?;
     }
 
-    // Display device information
-    println!("\n{} {} {}",
-             "Device".bold(),
-             format!("[{}]", device_id).cyan().bold(),
-             device.model().green());
-    println!("Version: {}", device.version());
-    println!();
-
     let capabilities = device.capabilities();
-    println!("Pedals: {}\n", capabilities.pedal_count);
 
-    // Display each pedal configuration
-    for i in 0..capabilities.pedal_count {
-        let default_name = format!("pedal{}", i + 1);
+    let indices = match &pedal {
+        Some(selector) => super::resolve_pedals(capabilities, selector)?,
+        // Left to right as on the unit, still labeled by pedal number
+        None => capabilities.physical_order.clone(),
+    };
+
+    // Collect (index, name, configuration, raw bytes) for each selected pedal
+    let mut pedals = Vec::new();
+    for i in indices {
         let pedal_name = capabilities.get_pedal_name(i)
-            .unwrap_or(&default_name);
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("pedal{}", i + 1));
 
         let config = device.get_pedal_configuration(i)
             .context("Failed to get pedal configuration")?;
 
-        print!("  {} {} ",
-               format!("[{}]", i + 1).cyan(),
-               pedal_name.yellow().bold());
+        // Raw bytes are only for debugging; a device without them just omits them
+        let raw = if verbose { device.get_pedal_raw(i).ok() } else { None };
 
-        // Display trigger mode
-        if let Some(trigger) = config.trigger() {
-            let trigger_str = match trigger {
-                Trigger::OnPress => "(on press)",
-                Trigger::OnRelease => "(on release)",
-            };
-            print!("{} ", trigger_str.dimmed());
+        pedals.push((i, pedal_name, config, raw));
+    }
+
+    match format {
+        ShowFormat::Pretty => {
+            // Display device information
+            println!("\n{} {} {}",
+                     "Device".bold(),
+                     theme::id(format!("[{}]", device_id)).bold(),
+                     theme::value(device.model()));
+            println!("Version: {}", device.version());
+            println!();
+            println!("Pedals: {}\n", capabilities.pedal_count);
+
+            for (i, pedal_name, config, raw) in &pedals {
+                print!("  {} {} ",
+                       theme::id(format!("[{}]", i + 1)),
+                       theme::name(pedal_name));
+
+                // Display trigger mode
+                if let Some(trigger) = config.trigger() {
+                    let trigger_str = match trigger {
+                        Trigger::OnPress => "(on press)",
+                        Trigger::OnRelease => "(on release)",
+                    };
+                    print!("{} ", theme::hint(trigger_str));
+                }
+
+                // Display configuration
+                match config {
+                    Configuration::Unconfigured => {
+                        println!("{}", theme::unconfigured("Unconfigured"));
+                    }
+                    config => {
+                        println!("{}", theme::value(config.to_string()));
+                    }
+                }
+
+                if let Some(raw) = raw {
+                    for chunk in raw.chunks(8) {
+                        println!("      {}", theme::hint(hex_bytes(chunk, " ")));
+                    }
+                }
+            }
+
+            println!("\n{}",
+                     theme::hint("Use 'clutchctl set <ID> <PEDAL> <CONFIG>' to change configuration."));
         }
+        ShowFormat::Json => {
+            let pedals: Vec<Value> = pedals
+                .iter()
+                .map(|(i, pedal_name, config, raw)| {
+                    let mut settings: Map<String, Value> = fields(config)
+                        .into_iter()
+                        .map(|(key, value)| (key.to_lowercase(), Value::String(value)))
+                        .collect();
+                    if let Configuration::Keyboard(kb) = config {
+                        settings.insert("modifiers".to_string(), json!(kb.modifiers_display()));
+                        settings.insert("main_keys".to_string(), json!(kb.main_keys()));
+                    }
+                    let mut entry = json!({
+                        "index": i + 1,
+                        "name": pedal_name,
+                        "type": type_name(config),
+                        "trigger": config.trigger().map(trigger_name),
+                        "description": config.to_string(),
+                        "settings": settings,
+                    });
+                    if let Some(raw) = raw {
+                        entry["raw"] = json!(hex_bytes(raw, ""));
+                    }
+                    entry
+                })
+                .collect();
 
-        // Display configuration
-        match &config {
-            Configuration::Unconfigured => {
-                println!("{}", "Unconfigured".red());
+            let (vendor_id, product_id) = device.usb_ids();
+            let document = json!({
+                "id": device_id,
+                "vendor_id": format!("{:04x}", vendor_id),
+                "product_id": format!("{:04x}", product_id),
+                "model": device.model(),
+                "version": device.version(),
+                "pedal_count": capabilities.pedal_count,
+                "pedals": pedals,
+            });
+            println!("{}", serde_json::to_string_pretty(&document)?);
+        }
+        ShowFormat::Compact => {
+            for (i, pedal_name, config, _) in &pedals {
+                println!("{}\t{}\t{}\t{}",
+                         i + 1,
+                         pedal_name,
+                         config.trigger().map(trigger_name).unwrap_or("-"),
+                         config);
             }
-            config => {
-                println!("{}", config.to_string().green());
+        }
+        ShowFormat::Env => {
+            println!("CLUTCHCTL_DEVICE_MODEL={}", shell_quote(device.model()));
+            println!("CLUTCHCTL_DEVICE_VERSION={}", shell_quote(device.version()));
+            println!("CLUTCHCTL_PEDAL_COUNT={}", capabilities.pedal_count);
+
+            for (i, pedal_name, config, _) in &pedals {
+                let prefix = format!("CLUTCHCTL_PEDAL_{}", i + 1);
+                println!("{}_NAME={}", prefix, shell_quote(pedal_name));
+                println!("{}_TYPE={}", prefix, type_name(config));
+                if let Some(trigger) = config.trigger() {
+                    println!("{}_TRIGGER={}", prefix, trigger_name(trigger));
+                }
+                for (key, value) in fields(config) {
+                    println!("{}_{}={}", prefix, key, shell_quote(&value));
+                }
             }
         }
     }
 
-    println!("\n{}",
-             "Use 'clutchctl set <ID> <PEDAL> <CONFIG>' to change configuration.".dimmed());
-
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Lowercase name of the configuration type
+fn type_name(config: &Configuration) -> &'static str {
+    match config {
+        Configuration::Raw { .. } => "raw",
+        config => config.configuration_type().map_or("none", |t| t.as_str()),
+    }
+}
+
+fn trigger_name(trigger: Trigger) -> &'static str {
+    match trigger {
+        Trigger::OnPress => "press",
+        Trigger::OnRelease => "release",
+    }
+}
+
+/// Type specific fields of a configuration, as (FIELD, value) pairs
+fn fields(config: &Configuration) -> Vec<(&'static str, String)> {
+    match config {
+        Configuration::Keyboard(kb) => {
+            let mode = match kb.mode {
+                KeyMode::Standard => "standard",
+                KeyMode::OneShot => "oneshot",
+            };
+            vec![("KEYS", kb.format_keys()), ("MODE", mode.to_string())]
+        }
+        Configuration::Mouse(m) => match &m.mode {
+            MouseMode::Buttons(_) => vec![("BUTTONS", m.format())],
+            MouseMode::Axis { x, y, wheel } => vec![
+                ("X", x.to_string()),
+                ("Y", y.to_string()),
+                ("WHEEL", wheel.to_string()),
+            ],
+        },
+        Configuration::Text(text) => vec![("TEXT", text.text.clone())],
+        Configuration::Media(media) => vec![("BUTTON", media.button_name().to_string())],
+        Configuration::Gamepad(gamepad) => vec![("BUTTON", gamepad.button_name().to_string())],
+        Configuration::Raw { config_type, data, .. } => {
+            vec![("CONFIG_TYPE", format!("0x{:02x}", config_type)), ("DATA", hex_bytes(data, ""))]
+        }
+        Configuration::Unconfigured => Vec::new(),
+    }
+}
+
+/// Lowercase hex of some bytes, joined by a separator
+fn hex_bytes(bytes: &[u8], separator: &str) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(separator)
+}
+
+/// Quote a value for POSIX shells; single quotes disable all expansion
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
@@ -0,0 +1,124 @@
+//! Stage command implementation
+//!
+//! Staged configurations accumulate in a session file per device, in the
+//! export format, until `commit` writes them all in one save. Session files
+//! live in the clutchctl config directory as `staged-<key>.json`, keyed by the
+//! device key the device lock uses rather than the enumeration ID, and record
+//! that key so `commit` can check it.
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use clutchctl_core::config_file::{ConfigFile, PedalEntry, PedalSpec};
+use clutchctl_core::device::{discover_devices_lazy, DeviceHandle};
+use clutchctl_core::user_config::config_dir;
+use std::path::PathBuf;
+
+use crate::cli::SetConfig;
+use crate::theme;
+
+/// Execute the stage command
+pub fn execute(device_id: usize, pedal_str: String, config: SetConfig) -> Result<()> {
+    let new_config = super::set::build_config(config)?;
+
+    // Opening is enough to resolve pedal names; the configuration is read on commit
+    let handle = find_handle(device_id)?;
+    let device = handle.open().context("Failed to open device")?;
+    let capabilities = device.capabilities();
+    let indices = super::resolve_pedals(capabilities, &pedal_str)?;
+
+    if let Some(config_type) = new_config.configuration_type() {
+        if !capabilities.supports_config_type(config_type) {
+            bail!("{} does not support {} configurations", device.model(), config_type);
+        }
+    }
+    if let Some(trigger) = new_config.trigger() {
+        if !capabilities.supports_trigger(trigger) {
+            bail!("{} does not support triggering on release (--invert)", device.model());
+        }
+    }
+
+    let key = handle.info().device_key();
+    let path = session_path(&key)?;
+    let mut session = load_session(&key)?.unwrap_or_default();
+    check_session(&session, device_id, &key, device.model())?;
+    session.model = Some(device.model().to_string());
+    session.device = Some(key);
+
+    for &i in &indices {
+        let key = capabilities.get_pedal_name(i)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| (i + 1).to_string());
+        session.pedals.insert(key, PedalEntry::Config(PedalSpec::from_configuration(&new_config)));
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(&path, session.to_json()? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let pedals: Vec<String> = indices.iter().map(|i| (i + 1).to_string()).collect();
+    println!("{} Staged {} for pedal(s) {} on device {}",
+             theme::ok_mark(),
+             theme::value(new_config.to_string()),
+             pedals.join(", "),
+             theme::id(format!("[{}]", device_id)).bold());
+    println!("{}", theme::hint(format!(
+        "{} pedal(s) staged. Use 'clutchctl commit {}' to write them.",
+        session.pedals.len(), device_id
+    )));
+
+    Ok(())
+}
+
+/// Find a device by ID without opening it
+pub fn find_handle(device_id: usize) -> Result<DeviceHandle> {
+    discover_devices_lazy()
+        .context("Failed to discover USB devices")?
+        .into_iter()
+        .find(|h| h.id() == device_id)
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))
+}
+
+/// Session file of a device key
+pub fn session_path(key: &str) -> Result<PathBuf> {
+    let name: String = key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    config_dir()
+        .map(|dir| dir.join(format!("staged-{}.json", name)))
+        .ok_or_else(|| anyhow!("Cannot determine the clutchctl config directory"))
+}
+
+/// Read the staged configurations of a device, if any
+pub fn load_session(key: &str) -> Result<Option<ConfigFile>> {
+    let path = session_path(key)?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    ConfigFile::from_json(&contents)
+        .map(Some)
+        .with_context(|| format!("Invalid session file {}", path.display()))
+}
+
+/// Refuse a session recorded for another device or model
+///
+/// Keys that differ only in characters a file name can't hold share a file,
+/// so the key stored in the session is compared as well.
+pub fn check_session(session: &ConfigFile, device_id: usize, key: &str, model: &str) -> Result<()> {
+    if let Some(staged) = session.device.as_deref().filter(|&staged| staged != key) {
+        bail!(
+            "Changes in {} were staged for the device at {}, not device {} at {}. Remove it to start over.",
+            session_path(key)?.display(), staged, device_id, key
+        );
+    }
+    match &session.model {
+        Some(staged) if staged != model => bail!(
+            "Changes for device {} were staged for {}, but it is now {}. Remove {} to start over.",
+            device_id, staged, model, session_path(key)?.display()
+        ),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,61 @@
+//! Swap command implementation
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use clutchctl_core::device::{find_device_by_id, DeviceCapabilities};
+
+use crate::theme;
+
+/// Execute the swap command: exchange the configurations of two pedals
+pub fn execute(device_id: usize, pedal_a: &str, pedal_b: &str) -> Result<()> {
+    let mut device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    let device_mut = std::sync::Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+
+    let a = resolve_one(device_mut.capabilities(), pedal_a)?;
+    let b = resolve_one(device_mut.capabilities(), pedal_b)?;
+    let label = theme::id(format!("[{}]", device_id)).bold();
+
+    if a == b {
+        println!("Pedal {} swapped with itself; nothing to do on device {}", a + 1, label);
+        return Ok(());
+    }
+
+    device_mut.load_configuration()
+        .context("Failed to load device configuration")?;
+
+    // Configurations carry their trigger, so it moves with them
+    let config_a = device_mut.get_pedal_configuration(a)
+        .context("Failed to get pedal configuration")?;
+    let config_b = device_mut.get_pedal_configuration(b)
+        .context("Failed to get pedal configuration")?;
+
+    device_mut.set_pedal_configuration(a, config_b.clone())
+        .context("Failed to set pedal configuration")?;
+    device_mut.set_pedal_configuration(b, config_a.clone())
+        .context("Failed to set pedal configuration")?;
+
+    device_mut.save_configuration()
+        .context("Failed to save configuration to device")?;
+
+    println!("\n{} Swapped pedals {} and {} on device {}",
+             theme::ok_mark(),
+             theme::id(format!("[{}]", a + 1)),
+             theme::id(format!("[{}]", b + 1)),
+             label);
+    println!("  {} {}", theme::id(format!("[{}]", a + 1)), theme::value(config_b.to_string()));
+    println!("  {} {}", theme::id(format!("[{}]", b + 1)), theme::value(config_a.to_string()));
+
+    Ok(())
+}
+
+/// Resolve a selector that must name exactly one pedal
+fn resolve_one(capabilities: &DeviceCapabilities, selector: &str) -> Result<usize> {
+    match super::resolve_pedals(capabilities, selector)?.as_slice() {
+        [index] => Ok(*index),
+        _ => bail!("'{}' selects more than one pedal; swap needs a single pedal", selector),
+    }
+}
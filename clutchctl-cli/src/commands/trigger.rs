@@ -0,0 +1,33 @@
+//! Trigger command implementation
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use clutchctl_core::device::find_device_by_id;
+
+use crate::cli::TriggerAction;
+use crate::theme;
+
+/// Execute the trigger command
+pub fn execute(device_id: usize, action: TriggerAction) -> Result<()> {
+    let mut device = find_device_by_id(device_id)
+        .context("Failed to discover USB devices")?
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    let device_mut = std::sync::Arc::get_mut(&mut device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?;
+
+    device_mut.load_configuration()
+        .context("Failed to load device configuration")?;
+
+    match action {
+        TriggerAction::Reset => {
+            device_mut.reset_triggers()
+                .context("Failed to reset the triggers")?;
+            println!("{} Every pedal of device {} now triggers on press",
+                     theme::ok_mark(),
+                     theme::id(format!("[{}]", device_id)).bold());
+        }
+    }
+
+    Ok(())
+}
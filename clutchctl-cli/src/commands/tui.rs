@@ -0,0 +1,207 @@
+//! Interactive terminal browser (requires the `tui` feature)
+//!
+//! A minimal, dependency-free browser for connected devices and their live
+//! pedal configuration. Input is line based so it works in any terminal:
+//! move with `j`/`k` (or the arrow keys) and press Enter, or type a number.
+//! The browser is read-only; use `clutchctl set` to change configuration.
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use clutchctl_core::configuration::{Configuration, Trigger};
+use clutchctl_core::device::{discover_devices, PedalDevice};
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+
+use crate::theme;
+
+type Device = Arc<dyn PedalDevice + Send + Sync>;
+
+/// Screen currently shown
+enum Screen {
+    /// Device list
+    Devices,
+    /// Pedal configuration of the device at this position
+    Pedals(usize),
+}
+
+/// A parsed line of user input
+enum Input {
+    Up,
+    Down,
+    Enter,
+    Select(usize),
+    Back,
+    Refresh,
+    Quit,
+}
+
+/// Parse a line of input; arrow keys arrive as ANSI escape sequences
+fn parse_input(line: &str) -> Option<Input> {
+    match line.trim() {
+        "" => Some(Input::Enter),
+        "k" | "\x1b[A" => Some(Input::Up),
+        "j" | "\x1b[B" => Some(Input::Down),
+        "b" | "h" | "\x1b[D" => Some(Input::Back),
+        "r" => Some(Input::Refresh),
+        "q" => Some(Input::Quit),
+        other => other.parse::<usize>().ok().filter(|&n| n > 0).map(Input::Select),
+    }
+}
+
+/// Execute the tui command
+pub fn execute() -> Result<()> {
+    let mut devices = discover_devices().context("Failed to discover USB devices")?;
+    let mut screen = Screen::Devices;
+    let mut cursor = 0;
+    let mut status = String::new();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        render(&devices, &screen, cursor, &status)?;
+        status.clear();
+
+        let Some(line) = lines.next() else {
+            break; // EOF
+        };
+        let Some(input) = parse_input(&line?) else {
+            status = "Unknown input".to_string();
+            continue;
+        };
+
+        let item_count = match screen {
+            Screen::Devices => devices.len(),
+            Screen::Pedals(i) => devices[i].capabilities().pedal_count,
+        };
+
+        match input {
+            Input::Quit => break,
+            Input::Up => cursor = cursor.saturating_sub(1),
+            Input::Down => cursor = (cursor + 1).min(item_count.saturating_sub(1)),
+            Input::Back => {
+                if let Screen::Pedals(i) = screen {
+                    screen = Screen::Devices;
+                    cursor = i;
+                }
+            }
+            Input::Select(n) if n > item_count => {
+                status = format!("No item {}", n);
+            }
+            Input::Select(n) => {
+                cursor = n - 1;
+                if let Screen::Devices = screen {
+                    status = open_device(&mut devices, &mut cursor, &mut screen);
+                }
+            }
+            Input::Enter => {
+                if let (Screen::Devices, true) = (&screen, cursor < devices.len()) {
+                    status = open_device(&mut devices, &mut cursor, &mut screen);
+                }
+            }
+            Input::Refresh => match screen {
+                Screen::Devices => {
+                    // Open devices hold their lock; release them before reopening
+                    devices.clear();
+                    devices = discover_devices().context("Failed to discover USB devices")?;
+                    cursor = 0;
+                }
+                Screen::Pedals(i) => {
+                    status = match load(&mut devices[i]) {
+                        Ok(()) => "Configuration reloaded".to_string(),
+                        Err(e) => format!("{:#}", e),
+                    };
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Load the configuration of the device under the cursor and switch to its
+/// pedal screen, with the cursor on the first pedal
+fn open_device(devices: &mut [Device], cursor: &mut usize, screen: &mut Screen) -> String {
+    let index = *cursor;
+    match load(&mut devices[index]) {
+        Ok(()) => {
+            *screen = Screen::Pedals(index);
+            *cursor = 0;
+            String::new()
+        }
+        Err(e) => format!("{:#}", e),
+    }
+}
+
+/// Load configuration from the device
+fn load(device: &mut Device) -> Result<()> {
+    Arc::get_mut(device)
+        .ok_or_else(|| anyhow!("Failed to get mutable device reference"))?
+        .load_configuration()
+        .context("Failed to load device configuration")
+}
+
+/// Redraw the current screen
+fn render(devices: &[Device], screen: &Screen, cursor: usize, status: &str) -> Result<()> {
+    let mut out = io::stdout().lock();
+
+    // Clear the screen and move the cursor home
+    write!(out, "\x1b[2J\x1b[H")?;
+    writeln!(out, "{}\n", "clutchctl".bold())?;
+
+    let marker = |i: usize| if i == cursor { theme::id(">").bold() } else { " ".normal() };
+
+    match screen {
+        Screen::Devices => {
+            if devices.is_empty() {
+                writeln!(out, "{}", theme::warning("No pedal devices found."))?;
+            }
+            for (i, device) in devices.iter().enumerate() {
+                writeln!(out, "{} {} {} {}",
+                         marker(i),
+                         theme::id(format!("[{}]", i + 1)),
+                         theme::value(device.model()),
+                         theme::hint(format!("({} pedals, {})", device.capabilities().pedal_count, device.version())))?;
+            }
+            writeln!(out, "\n{}", theme::hint("j/k move  Enter/number open  r rescan  q quit"))?;
+        }
+        Screen::Pedals(index) => {
+            let device = &devices[*index];
+            let capabilities = device.capabilities();
+            writeln!(out, "{} {}\n", theme::value(device.model()), theme::hint(format!("({})", device.version())))?;
+
+            for i in 0..capabilities.pedal_count {
+                let default_name = format!("pedal{}", i + 1);
+                let pedal_name = capabilities.get_pedal_name(i).unwrap_or(&default_name);
+                let config = device.get_pedal_configuration(i)
+                    .context("Failed to get pedal configuration")?;
+
+                let trigger = match config.trigger() {
+                    Some(Trigger::OnPress) => "(on press)",
+                    Some(Trigger::OnRelease) => "(on release)",
+                    None => "",
+                };
+                let description = match &config {
+                    Configuration::Unconfigured => theme::unconfigured("Unconfigured"),
+                    config => theme::value(config.to_string()),
+                };
+
+                writeln!(out, "{} {} {} {} {}",
+                         marker(i),
+                         theme::id(format!("[{}]", i + 1)),
+                         theme::name(pedal_name),
+                         theme::hint(trigger),
+                         description)?;
+            }
+            writeln!(out, "\n{}", theme::hint("j/k move  b back  r reload  q quit"))?;
+        }
+    }
+
+    if !status.is_empty() {
+        writeln!(out, "\n{}", theme::warning(status))?;
+    }
+    write!(out, "> ")?;
+    out.flush()?;
+
+    Ok(())
+}
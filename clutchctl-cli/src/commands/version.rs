@@ -0,0 +1,37 @@
+//! Version command implementation
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use clutchctl_core::device::discover_devices_lazy;
+use clutchctl_core::usb::HID_BACKEND;
+
+use crate::theme;
+
+/// Execute the version command
+pub fn execute(device_id: Option<usize>) -> Result<()> {
+    println!("clutchctl {} (core {})", env!("CARGO_PKG_VERSION"), clutchctl_core::VERSION);
+    println!("HID backend: {}", HID_BACKEND);
+
+    let Some(device_id) = device_id else {
+        return Ok(());
+    };
+
+    let handle = discover_devices_lazy()
+        .context("Failed to discover USB devices")?
+        .into_iter()
+        .find(|h| h.id() == device_id)
+        .ok_or_else(|| anyhow!("Device with ID {} not found", device_id))?;
+
+    let device = handle.open().context("Failed to open device")?;
+    let info = handle.info();
+
+    println!("\n{} {} {}",
+             "Device".bold(),
+             theme::id(format!("[{}]", device_id)).bold(),
+             theme::value(device.model()));
+    println!("  Firmware: {}", device.version());
+    println!("  USB ID:   {:04x}:{:04x}", info.vendor_id, info.product_id);
+    println!("  Type:     {}", handle.device_type());
+
+    Ok(())
+}
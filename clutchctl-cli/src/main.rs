@@ -2,6 +2,7 @@
 
 mod cli;
 mod commands;
+mod theme;
 
 use anyhow::Result;
 use clap::Parser;
@@ -16,12 +17,14 @@ fn main() -> Result<()> {
 
     // Configure colored output based on platform and user preference
     configure_colored_output(cli.no_color);
+    theme::set(cli.theme);
 
     // Execute the command
     cli.execute()
 }
 
 /// Configure colored output based on the platform and terminal capabilities
+#[cfg_attr(not(windows), allow(clippy::needless_return))]
 fn configure_colored_output(no_color: bool) {
     use colored::control;
 
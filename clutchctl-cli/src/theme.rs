@@ -0,0 +1,110 @@
+//! Output colors
+//!
+//! Commands style text by its role (a device ID, a configured value, a
+//! failure) through these functions instead of picking colors themselves, so
+//! the palette can be switched with `--theme` or `CLUTCHCTL_THEME`.
+//! `--no-color` and `NO_COLOR` still turn all colors off on top of any theme.
+
+use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
+use std::sync::OnceLock;
+
+/// Built-in palettes
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Green for success, red for failures and unconfigured pedals
+    #[default]
+    Default,
+    /// Bright blue and yellow instead of green and red, without dimmed text
+    HighContrast,
+    /// No colors; success and failure are spelled out
+    MonochromeSymbols,
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Select the palette; only the first call has an effect
+pub fn set(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+fn current() -> Theme {
+    THEME.get().copied().unwrap_or_default()
+}
+
+/// Device and pedal IDs such as `[0]`
+pub fn id(text: impl AsRef<str>) -> ColoredString {
+    let text = text.as_ref();
+    match current() {
+        Theme::Default => text.cyan(),
+        Theme::HighContrast => text.bright_white().bold(),
+        Theme::MonochromeSymbols => text.bold(),
+    }
+}
+
+/// Pedal names
+pub fn name(text: impl AsRef<str>) -> ColoredString {
+    let text = text.as_ref();
+    match current() {
+        Theme::Default => text.yellow().bold(),
+        Theme::HighContrast => text.bright_cyan().bold(),
+        Theme::MonochromeSymbols => text.bold(),
+    }
+}
+
+/// Configured values, models and device types
+pub fn value(text: impl AsRef<str>) -> ColoredString {
+    let text = text.as_ref();
+    match current() {
+        Theme::Default => text.green(),
+        Theme::HighContrast => text.bright_blue(),
+        Theme::MonochromeSymbols => text.normal(),
+    }
+}
+
+/// Unconfigured pedals
+pub fn unconfigured(text: impl AsRef<str>) -> ColoredString {
+    let text = text.as_ref();
+    match current() {
+        Theme::Default => text.red(),
+        Theme::HighContrast => text.bright_yellow(),
+        Theme::MonochromeSymbols => text.italic(),
+    }
+}
+
+/// Warnings and notices such as "Dry run:"
+pub fn warning(text: impl AsRef<str>) -> ColoredString {
+    let text = text.as_ref();
+    match current() {
+        Theme::Default => text.yellow(),
+        Theme::HighContrast => text.bright_yellow().bold(),
+        Theme::MonochromeSymbols => text.bold(),
+    }
+}
+
+/// Hints and secondary details
+pub fn hint(text: impl AsRef<str>) -> ColoredString {
+    let text = text.as_ref();
+    match current() {
+        Theme::Default | Theme::MonochromeSymbols => text.dimmed(),
+        Theme::HighContrast => text.normal(),
+    }
+}
+
+/// Marker for a successful operation
+pub fn ok_mark() -> ColoredString {
+    match current() {
+        Theme::Default => "✓".green().bold(),
+        Theme::HighContrast => "✓".bright_blue().bold(),
+        Theme::MonochromeSymbols => "[ok]".bold(),
+    }
+}
+
+/// Marker for a failed operation
+pub fn fail_mark() -> ColoredString {
+    match current() {
+        Theme::Default => "✗".red().bold(),
+        Theme::HighContrast => "✗".bright_yellow().bold(),
+        Theme::MonochromeSymbols => "[failed]".bold(),
+    }
+}
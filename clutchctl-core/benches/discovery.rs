@@ -0,0 +1,41 @@
+//! Discovery benchmark: lazy enumeration vs. opening every device
+//!
+//! Run with `cargo bench -p clutchctl-core --bench discovery` with pedals
+//! attached. Uses a plain timing loop so it needs no extra dependencies.
+
+use clutchctl_core::device::{discover_devices, discover_devices_lazy};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 5;
+
+fn time<T>(name: &str, mut f: impl FnMut() -> clutchctl_core::Result<Vec<T>>) -> Duration {
+    let mut total = Duration::ZERO;
+    let mut found = 0;
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        match f() {
+            Ok(devices) => found = devices.len(),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                return Duration::ZERO;
+            }
+        }
+        total += start.elapsed();
+        // Give the devices time to settle between runs
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let average = total / ITERATIONS;
+    println!("{:<24} {:>10.2?} per run, {} device(s)", name, average, found);
+    average
+}
+
+fn main() {
+    let lazy = time("discover_devices_lazy", discover_devices_lazy);
+    let full = time("discover_devices", discover_devices);
+
+    if !lazy.is_zero() && !full.is_zero() {
+        println!("speedup: {:.1}x", full.as_secs_f64() / lazy.as_secs_f64());
+    }
+}
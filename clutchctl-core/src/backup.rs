@@ -0,0 +1,270 @@
+//! Binary backups holding the raw configuration bytes of every pedal
+//!
+//! Unlike the JSON export, a binary backup keeps the bytes the device answered
+//! with, so configurations clutchctl can't describe survive a round trip
+//! unchanged. All multi-byte fields are little-endian:
+//!
+//! | Offset      | Size   | Field                                              |
+//! |-------------|--------|----------------------------------------------------|
+//! | 0           | 4      | Magic `CLCB`                                       |
+//! | 4           | 1      | Format version, currently 1                        |
+//! | 5           | 1      | Pedal count `N`                                    |
+//! | 6           | 2      | USB vendor ID                                      |
+//! | 8           | 2      | USB product ID                                     |
+//! | 10          | 2      | Reserved, zero                                     |
+//! | 12          | 16     | Firmware version, ASCII, NUL-padded                |
+//! | 28          | `N`    | Trigger of each pedal: 0 on press, 1 on release    |
+//! | 28 + `N`    | 40 `N` | Raw configuration of each pedal, in pedal order    |
+//!
+//! Each raw configuration is what [`PedalDevice::get_pedal_raw`] returns: the
+//! 40-byte config packet on iKKEGOL devices, the zero-padded reports on
+//! PCsensor devices. The trigger is kept separately because iKKEGOL devices
+//! store it in a table outside the config packet; it is taken from that
+//! table, so it survives for configurations clutchctl keeps as raw bytes.
+
+use crate::configuration::{Configuration, Trigger};
+use crate::device::PedalDevice;
+use crate::error::{PedalError, Result};
+
+/// Magic bytes at the start of every binary backup
+pub const MAGIC: &[u8; 4] = b"CLCB";
+
+/// Format version written by [`RawBackup::to_bytes`]
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Size of the fixed header, before the trigger bytes
+pub const HEADER_SIZE: usize = 28;
+
+/// Size of the raw configuration of one pedal
+pub const RAW_CONFIG_SIZE: usize = 40;
+
+/// Size of the firmware version field
+const VERSION_SIZE: usize = 16;
+
+/// Raw configuration bytes of every pedal of one device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawBackup {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    /// Firmware version, truncated to 16 bytes; informational only
+    pub version: String,
+    pub triggers: Vec<Trigger>,
+    pub configs: Vec<[u8; RAW_CONFIG_SIZE]>,
+}
+
+impl RawBackup {
+    /// Back up a loaded device
+    pub fn from_device(device: &dyn PedalDevice) -> Result<Self> {
+        let (vendor_id, product_id) = device.usb_ids();
+        let pedal_count = device.capabilities().pedal_count;
+        if pedal_count > u8::MAX as usize {
+            return Err(PedalError::UnsupportedDevice(format!(
+                "{} has too many pedals for a binary backup", device.model()
+            )));
+        }
+
+        let mut triggers = Vec::with_capacity(pedal_count);
+        let mut configs = Vec::with_capacity(pedal_count);
+        for i in 0..pedal_count {
+            triggers.push(device.get_pedal_trigger(i)?);
+            configs.push(device.get_pedal_raw(i)?);
+        }
+
+        Ok(Self {
+            vendor_id,
+            product_id,
+            version: device.version().to_string(),
+            triggers,
+            configs,
+        })
+    }
+
+    /// Encode in the layout documented in the module docs
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let pedal_count = self.configs.len();
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + pedal_count * (RAW_CONFIG_SIZE + 1));
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.push(pedal_count as u8);
+        bytes.extend_from_slice(&self.vendor_id.to_le_bytes());
+        bytes.extend_from_slice(&self.product_id.to_le_bytes());
+        bytes.extend_from_slice(&[0, 0]);
+
+        let mut version = [0u8; VERSION_SIZE];
+        let ascii: Vec<u8> = self.version.bytes().filter(u8::is_ascii).take(VERSION_SIZE).collect();
+        version[..ascii.len()].copy_from_slice(&ascii);
+        bytes.extend_from_slice(&version);
+
+        bytes.extend(self.triggers.iter().map(|trigger| match trigger {
+            Trigger::OnPress => 0u8,
+            Trigger::OnRelease => 1,
+        }));
+        for config in &self.configs {
+            bytes.extend_from_slice(config);
+        }
+        bytes
+    }
+
+    /// Decode a backup, checking the magic, format version and length
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(PedalError::ParseError("not a clutchctl binary backup".to_string()));
+        }
+        if bytes[4] != FORMAT_VERSION {
+            return Err(PedalError::ParseError(format!(
+                "unsupported binary backup format version {} (expected {})",
+                bytes[4], FORMAT_VERSION
+            )));
+        }
+
+        let pedal_count = bytes[5] as usize;
+        let expected = HEADER_SIZE + pedal_count * (RAW_CONFIG_SIZE + 1);
+        if bytes.len() != expected {
+            return Err(PedalError::ParseError(format!(
+                "binary backup of {} pedals should be {} bytes, found {}",
+                pedal_count, expected, bytes.len()
+            )));
+        }
+
+        let version = bytes[12..HEADER_SIZE].iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| b as char)
+            .collect();
+
+        let triggers = bytes[HEADER_SIZE..HEADER_SIZE + pedal_count].iter()
+            .enumerate()
+            .map(|(i, &b)| match b {
+                0 => Ok(Trigger::OnPress),
+                1 => Ok(Trigger::OnRelease),
+                _ => Err(PedalError::ParseError(format!(
+                    "invalid trigger 0x{:02x} for pedal {}", b, i + 1
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let configs = bytes[HEADER_SIZE + pedal_count..]
+            .chunks_exact(RAW_CONFIG_SIZE)
+            .map(|chunk| {
+                let mut config = [0u8; RAW_CONFIG_SIZE];
+                config.copy_from_slice(chunk);
+                config
+            })
+            .collect();
+
+        Ok(Self {
+            vendor_id: u16::from_le_bytes([bytes[6], bytes[7]]),
+            product_id: u16::from_le_bytes([bytes[8], bytes[9]]),
+            version,
+            triggers,
+            configs,
+        })
+    }
+
+    /// Refuse a backup of another kind of device
+    pub fn check_device(&self, device: &dyn PedalDevice) -> Result<()> {
+        let (vendor_id, product_id) = device.usb_ids();
+        if (self.vendor_id, self.product_id) != (vendor_id, product_id) {
+            return Err(PedalError::InvalidConfiguration(format!(
+                "backup is of a {:04x}:{:04x} device, but the device is {:04x}:{:04x}",
+                self.vendor_id, self.product_id, vendor_id, product_id
+            )));
+        }
+
+        let pedal_count = device.capabilities().pedal_count;
+        if self.configs.len() != pedal_count {
+            return Err(PedalError::InvalidConfiguration(format!(
+                "backup has {} pedals, but {} has {}",
+                self.configs.len(), device.model(), pedal_count
+            )));
+        }
+        Ok(())
+    }
+
+    /// Configuration of each pedal, as the device would parse it
+    pub fn configurations(&self, device: &dyn PedalDevice) -> Result<Vec<Configuration>> {
+        self.check_device(device)?;
+        self.configs.iter()
+            .zip(&self.triggers)
+            .map(|(raw, &trigger)| {
+                let mut config = device.parse_pedal_raw(raw)?;
+                config.set_trigger(trigger);
+                Ok(config)
+            })
+            .collect()
+    }
+
+    /// Set every pedal of a device to the backed-up configuration, without saving
+    pub fn restore(&self, device: &mut dyn PedalDevice) -> Result<()> {
+        for (i, config) in self.configurations(device)?.into_iter().enumerate() {
+            device.set_pedal_configuration(i, config)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::keyboard::KeyMode;
+    use crate::configuration::{KeyboardConfiguration, TextConfiguration};
+    use crate::device::MockDevice;
+
+    #[test]
+    fn test_round_trip() {
+        let mut device = MockDevice::new(0, 3).unwrap();
+        let mut keyboard = Configuration::Keyboard(KeyboardConfiguration::new(KeyMode::Standard, vec!["a".to_string()]));
+        keyboard.set_trigger(Trigger::OnRelease);
+        device.set_pedal_configuration(0, keyboard.clone()).unwrap();
+        device.set_pedal_configuration(2, Configuration::Text(TextConfiguration::new("hi".to_string()))).unwrap();
+
+        let bytes = RawBackup::from_device(&device).unwrap().to_bytes();
+        assert_eq!(&bytes[..6], b"CLCB\x01\x03");
+        assert_eq!(bytes.len(), HEADER_SIZE + 3 * 41);
+        let backup = RawBackup::from_bytes(&bytes).unwrap();
+        assert_eq!(backup.triggers, vec![Trigger::OnRelease, Trigger::OnPress, Trigger::OnPress]);
+
+        let mut restored = MockDevice::new(1, 3).unwrap();
+        backup.restore(&mut restored).unwrap();
+        for i in 0..3 {
+            assert_eq!(restored.get_pedal_configuration(i).unwrap(), device.get_pedal_configuration(i).unwrap());
+            assert_eq!(restored.get_pedal_raw(i).unwrap(), device.get_pedal_raw(i).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_round_trip_keeps_raw_trigger() {
+        let mut device = MockDevice::new(0, 3).unwrap();
+        let raw = Configuration::Raw { config_type: 0x0b, data: [7; 38], trigger: Trigger::OnRelease };
+        device.set_pedal_configuration(1, raw.clone()).unwrap();
+
+        let backup = RawBackup::from_bytes(&RawBackup::from_device(&device).unwrap().to_bytes()).unwrap();
+        assert_eq!(backup.triggers[1], Trigger::OnRelease);
+
+        let mut restored = MockDevice::new(1, 3).unwrap();
+        backup.restore(&mut restored).unwrap();
+        assert_eq!(restored.get_pedal_configuration(1).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_rejects_bad_backups() {
+        let device = MockDevice::new(0, 3).unwrap();
+        let bytes = RawBackup::from_device(&device).unwrap().to_bytes();
+
+        assert!(RawBackup::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert!(RawBackup::from_bytes(&bad).is_err());
+        let mut bad = bytes.clone();
+        bad[4] = 2;
+        assert!(RawBackup::from_bytes(&bad).is_err());
+        let mut bad = bytes.clone();
+        bad[HEADER_SIZE] = 7;
+        assert!(RawBackup::from_bytes(&bad).is_err());
+
+        let backup = RawBackup::from_bytes(&bytes).unwrap();
+        assert!(backup.restore(&mut MockDevice::new(0, 1).unwrap()).is_err());
+        let mut other = backup.clone();
+        other.product_id ^= 1;
+        assert!(other.restore(&mut MockDevice::new(0, 3).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,491 @@
+//! Pedal configuration files for export and import (requires the `serialization` feature)
+//!
+//! A file maps pedals, by name or 1-based index, to their configuration in the
+//! same vocabulary the CLI uses:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "model": "FS2020U1IR",
+//!   "pedals": {
+//!     "left": { "type": "keyboard", "value": "LCtrl+c" },
+//!     "middle": "skip",
+//!     "right": { "type": "media", "value": "play", "trigger": "release" }
+//!   }
+//! }
+//! ```
+//!
+//! Pedals that are missing or set to `"skip"` are left as they are on the
+//! device. Importing still has to load the device first: some protocols (the
+//! PCsensor one) rewrite every pedal on save, so the untouched pedals are
+//! written back with the configuration just read.
+//!
+//! Files carry a `schema_version`. Files without one predate it and are read as
+//! version 1. Reading a newer version logs a warning and reads the fields this
+//! version knows; versions older than [`MIN_SCHEMA_VERSION`] are rejected.
+
+use crate::configuration::keyboard::KeyMode;
+use crate::configuration::mouse::MouseMode;
+use crate::configuration::{
+    Configuration, GamepadConfiguration, KeyboardConfiguration, MediaConfiguration,
+    MouseConfiguration, TextConfiguration, Trigger,
+};
+use crate::device::PedalDevice;
+use crate::error::{PedalError, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Keyword that leaves a pedal untouched
+pub const SKIP: &str = "skip";
+
+/// Schema version written by this version of clutchctl
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Oldest schema version that can still be read
+pub const MIN_SCHEMA_VERSION: u32 = 1;
+
+/// Configuration of some or all pedals of a device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigFile {
+    /// Format version of the file
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+    /// Model the file was exported from; informational only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Serial number of the device, in whole-rig backups; informational only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// [Device key](crate::usb::HidDeviceInfo::device_key) of the device a
+    /// stage session was recorded for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// When the file was exported, as an RFC 3339 UTC timestamp; informational only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<String>,
+    /// Pedal name or 1-based index to configuration
+    pub pedals: BTreeMap<String, PedalEntry>,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            model: None,
+            serial: None,
+            device: None,
+            saved_at: None,
+            pedals: BTreeMap::new(),
+        }
+    }
+}
+
+/// Version of files written before `schema_version` existed
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// Configuration of one pedal, or a keyword such as `"skip"`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PedalEntry {
+    Keyword(String),
+    Config(PedalSpec),
+}
+
+/// Serializable form of a [`Configuration`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PedalSpec {
+    /// `keyboard`, `mouse`, `text`, `media`, `gamepad`, `raw` or `none`
+    #[serde(rename = "type")]
+    pub config_type: String,
+    /// Keys, mouse buttons, text, button name, or raw data as hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Keyboard mode: `standard` (default) or `oneshot`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    /// Mouse movement, used instead of `value`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<i8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<i8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wheel: Option<i8>,
+    /// Config type byte of a raw configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<u8>,
+    /// `press` (default) or `release`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+}
+
+impl PedalSpec {
+    /// Describe a configuration
+    pub fn from_configuration(config: &Configuration) -> Self {
+        let mut spec = PedalSpec {
+            trigger: config.trigger().and_then(|trigger| match trigger {
+                Trigger::OnPress => None,
+                Trigger::OnRelease => Some("release".to_string()),
+            }),
+            ..Default::default()
+        };
+
+        match config {
+            Configuration::Keyboard(kb) => {
+                spec.config_type = "keyboard".to_string();
+                spec.value = Some(kb.format_keys());
+                if kb.mode == KeyMode::OneShot {
+                    spec.mode = Some("oneshot".to_string());
+                }
+            }
+            Configuration::Mouse(m) => {
+                spec.config_type = "mouse".to_string();
+                match &m.mode {
+                    MouseMode::Buttons(_) => spec.value = Some(m.format()),
+                    MouseMode::Axis { x, y, wheel } => {
+                        spec.x = Some(*x);
+                        spec.y = Some(*y);
+                        spec.wheel = Some(*wheel);
+                    }
+                }
+            }
+            Configuration::Text(text) => {
+                spec.config_type = "text".to_string();
+                spec.value = Some(text.text.clone());
+            }
+            Configuration::Media(media) => {
+                spec.config_type = "media".to_string();
+                spec.value = Some(media.canonical_name().to_string());
+            }
+            Configuration::Gamepad(gamepad) => {
+                spec.config_type = "gamepad".to_string();
+                spec.value = Some(gamepad.canonical_name().to_string());
+            }
+            Configuration::Raw { config_type, data, .. } => {
+                spec.config_type = "raw".to_string();
+                spec.raw_type = Some(*config_type);
+                spec.value = Some(data.iter().map(|b| format!("{:02x}", b)).collect());
+            }
+            Configuration::Unconfigured => spec.config_type = "none".to_string(),
+        }
+
+        spec
+    }
+
+    /// Build the configuration this spec describes
+    pub fn to_configuration(&self) -> Result<Configuration> {
+        let value = || {
+            self.value.as_deref().ok_or_else(|| {
+                PedalError::InvalidConfiguration(format!("{} configuration needs a value", self.config_type))
+            })
+        };
+        let unknown = |what: &str, name: &str| {
+            PedalError::InvalidConfiguration(format!("Unknown {}: {}", what, name))
+        };
+
+        let mut config = match self.config_type.to_lowercase().as_str() {
+            "none" => Configuration::Unconfigured,
+            "keyboard" => {
+                let (modifiers, keys) = KeyboardConfiguration::parse_keys(value()?)?;
+                if keys.is_empty() && modifiers.is_empty() {
+                    return Err(PedalError::InvalidConfiguration("No key specified".to_string()));
+                }
+                let mode = match self.mode.as_deref().map(str::to_lowercase).as_deref() {
+                    None | Some("standard") => KeyMode::Standard,
+                    Some("oneshot") => KeyMode::OneShot,
+                    Some(other) => return Err(unknown("keyboard mode", other)),
+                };
+                Configuration::Keyboard(KeyboardConfiguration::with_modifiers(mode, keys, modifiers))
+            }
+            "mouse" => match (&self.value, self.x, self.y) {
+                (Some(buttons), _, _) => {
+                    let buttons = MouseConfiguration::parse_buttons(buttons)
+                        .ok_or_else(|| unknown("mouse button", buttons))?;
+                    Configuration::Mouse(MouseConfiguration::buttons(buttons))
+                }
+                (None, Some(x), Some(y)) => {
+                    Configuration::Mouse(MouseConfiguration::axis(x, y, self.wheel.unwrap_or(0)))
+                }
+                _ => {
+                    return Err(PedalError::InvalidConfiguration(
+                        "mouse configuration needs buttons as value, or x and y".to_string(),
+                    ))
+                }
+            },
+            "text" => {
+                let text = TextConfiguration::new(value()?.to_string());
+                // The length limit depends on the model it is imported to
+                text.validate_characters()?;
+                Configuration::Text(text)
+            }
+            "media" => {
+                let button = MediaConfiguration::parse_button(value()?)
+                    .ok_or_else(|| unknown("media button", value().unwrap_or_default()))?;
+                Configuration::Media(MediaConfiguration::new(button))
+            }
+            "gamepad" | "game" => {
+                let button = GamepadConfiguration::parse_button(value()?)
+                    .ok_or_else(|| unknown("game button", value().unwrap_or_default()))?;
+                Configuration::Gamepad(GamepadConfiguration::new(button))
+            }
+            "raw" => {
+                let config_type = self.raw_type.ok_or_else(|| {
+                    PedalError::InvalidConfiguration("raw configuration needs raw_type".to_string())
+                })?;
+                Configuration::Raw { config_type, data: parse_hex(value()?)?, trigger: Trigger::OnPress }
+            }
+            other => return Err(unknown("configuration type", other)),
+        };
+
+        match self.trigger.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("press") => {}
+            Some("release") => config.set_trigger(Trigger::OnRelease),
+            Some(other) => return Err(unknown("trigger", other)),
+        }
+
+        Ok(config)
+    }
+}
+
+impl ConfigFile {
+    /// Describe every pedal of a loaded device, stamped with the current time
+    pub fn from_device(device: &dyn PedalDevice) -> Result<Self> {
+        let capabilities = device.capabilities();
+        let mut pedals = BTreeMap::new();
+        for i in 0..capabilities.pedal_count {
+            let key = capabilities.get_pedal_name(i)
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| (i + 1).to_string());
+            let config = device.get_pedal_configuration(i)?;
+            pedals.insert(key, PedalEntry::Config(PedalSpec::from_configuration(&config)));
+        }
+
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            model: Some(device.model().to_string()),
+            serial: None,
+            device: None,
+            saved_at: Some(format_rfc3339(SystemTime::now())),
+            pedals,
+        })
+    }
+
+    /// Convert a script for the footswitch tool (see [`crate::footswitch`])
+    ///
+    /// The pedals it programs are keyed by 1-based index; the others are left out.
+    pub fn from_footswitch(script: &str) -> Result<Self> {
+        let pedals = crate::footswitch::parse_script(script)?
+            .into_iter()
+            .map(|(i, config)| ((i + 1).to_string(), PedalEntry::Config(PedalSpec::from_configuration(&config))))
+            .collect();
+        Ok(Self { pedals, ..Self::default() })
+    }
+
+    /// Parse a file from JSON, checking its schema version
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: Self = serde_json::from_str(json).map_err(|e| PedalError::ParseError(e.to_string()))?;
+
+        if file.schema_version < MIN_SCHEMA_VERSION {
+            return Err(PedalError::ParseError(format!(
+                "Unsupported schema version {} (this version reads {} to {})",
+                file.schema_version, MIN_SCHEMA_VERSION, SCHEMA_VERSION
+            )));
+        }
+        if file.schema_version > SCHEMA_VERSION {
+            warn!(
+                "Configuration file has schema version {}, newer than {}; unknown fields are ignored",
+                file.schema_version, SCHEMA_VERSION
+            );
+        }
+
+        Ok(file)
+    }
+
+    /// Serialize to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| PedalError::ParseError(e.to_string()))
+    }
+
+    /// Resolve the pedals this file configures into (0-based index, configuration)
+    ///
+    /// Skipped pedals are left out, as are pedals not in `only` when it is given
+    /// (0-based indices). Every entry is validated, skipped or not.
+    pub fn configurations(
+        &self,
+        device: &dyn PedalDevice,
+        only: Option<&[usize]>,
+    ) -> Result<Vec<(usize, Configuration)>> {
+        let capabilities = device.capabilities();
+        let mut configurations = Vec::new();
+
+        for (selector, entry) in &self.pedals {
+            let index = capabilities.resolve_pedal(selector)?;
+            let config = match entry {
+                PedalEntry::Keyword(keyword) if keyword.eq_ignore_ascii_case(SKIP) => continue,
+                PedalEntry::Keyword(keyword) => {
+                    return Err(PedalError::InvalidConfiguration(format!(
+                        "Unknown keyword '{}' for pedal {}", keyword, selector
+                    )))
+                }
+                PedalEntry::Config(spec) => spec.to_configuration()?,
+            };
+            if let Some(config_type) = config.configuration_type() {
+                if !capabilities.supports_config_type(config_type) {
+                    return Err(PedalError::InvalidConfiguration(format!(
+                        "{} does not support {} configurations (pedal {})",
+                        device.model(), config_type, selector
+                    )));
+                }
+            }
+            if let Some(trigger) = config.trigger() {
+                if !capabilities.supports_trigger(trigger) {
+                    return Err(PedalError::InvalidConfiguration(format!(
+                        "{} does not support triggering on release (pedal {})",
+                        device.model(), selector
+                    )));
+                }
+            }
+
+            if only.map_or(true, |only| only.contains(&index)) {
+                configurations.push((index, config));
+            }
+        }
+
+        Ok(configurations)
+    }
+
+    /// Apply the file to a loaded device without saving; returns the pedals set
+    pub fn apply(&self, device: &mut dyn PedalDevice, only: Option<&[usize]>) -> Result<Vec<usize>> {
+        let configurations = self.configurations(device, only)?;
+        let indices = configurations.iter().map(|&(index, _)| index).collect();
+        for (index, config) in configurations {
+            device.set_pedal_configuration(index, config)?;
+        }
+        Ok(indices)
+    }
+}
+
+/// Format a time as an RFC 3339 UTC timestamp with second precision
+fn format_rfc3339(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since the epoch, over 400-year eras of 146097 days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, rem / 3_600, rem % 3_600 / 60, rem % 60
+    )
+}
+
+/// Parse the 38 data bytes of a raw configuration from hex
+fn parse_hex(hex: &str) -> Result<[u8; 38]> {
+    let invalid = || PedalError::InvalidConfiguration("raw data must be 38 bytes of hex".to_string());
+    if hex.len() != 76 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let mut data = [0u8; 38];
+    for (byte, pair) in data.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+        *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::BaseConfiguration;
+    use crate::protocol::GameKey;
+
+    #[test]
+    fn test_spec_round_trip() {
+        let mut keyboard = KeyboardConfiguration::with_modifiers(
+            KeyMode::OneShot,
+            vec!["a".to_string(), "b".to_string()],
+            crate::protocol::ModifierKeys::LEFT_CONTROL,
+        );
+        keyboard.set_trigger(Trigger::OnRelease);
+
+        let configs = [
+            Configuration::Keyboard(keyboard),
+            Configuration::Mouse(MouseConfiguration::axis(-5, 3, 1)),
+            Configuration::Mouse(MouseConfiguration::parse_buttons("left+right").map(MouseConfiguration::buttons).unwrap()),
+            Configuration::Text(TextConfiguration::new("Hi there".to_string())),
+            Configuration::Gamepad(GamepadConfiguration::new(GameKey::Button3)),
+            Configuration::Raw { config_type: 0x0b, data: [7; 38], trigger: Trigger::OnRelease },
+            Configuration::Unconfigured,
+        ];
+
+        for config in configs {
+            let spec = PedalSpec::from_configuration(&config);
+            let json = serde_json::to_string(&spec).unwrap();
+            let parsed: PedalSpec = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.to_configuration().unwrap(), config, "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_schema_version() {
+        let json = ConfigFile::default().to_json().unwrap();
+        assert!(json.contains(r#""schema_version": 1"#), "{}", json);
+        assert_eq!(ConfigFile::from_json(&json).unwrap().schema_version, SCHEMA_VERSION);
+
+        // Older files have no version; newer ones are read with a warning
+        assert_eq!(ConfigFile::from_json(r#"{ "pedals": {} }"#).unwrap().schema_version, 1);
+        assert_eq!(ConfigFile::from_json(r#"{ "schema_version": 2, "pedals": {} }"#).unwrap().schema_version, 2);
+        assert!(matches!(
+            ConfigFile::from_json(r#"{ "schema_version": 0, "pedals": {} }"#),
+            Err(PedalError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_serial() {
+        assert!(!ConfigFile::default().to_json().unwrap().contains("serial"));
+
+        let file = ConfigFile { serial: Some("A1B2".to_string()), ..Default::default() };
+        let json = serde_json::to_string(&vec![file.clone()]).unwrap();
+        let parsed: Vec<ConfigFile> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, vec![file]);
+    }
+
+    #[test]
+    fn test_saved_at() {
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap_day = UNIX_EPOCH + std::time::Duration::from_secs(1_709_210_096);
+        assert_eq!(format_rfc3339(leap_day), "2024-02-29T12:34:56Z");
+
+        let file = ConfigFile::from_device(&crate::device::MockDevice::new(0, 3).unwrap()).unwrap();
+        let saved_at = file.saved_at.clone().unwrap();
+        assert!(saved_at.len() == 20 && saved_at.ends_with('Z'), "{}", saved_at);
+        assert_eq!(ConfigFile::from_json(&file.to_json().unwrap()).unwrap(), file);
+        assert!(!ConfigFile::default().to_json().unwrap().contains("saved_at"));
+    }
+
+    #[test]
+    fn test_skip_and_unknown_keywords() {
+        let file = ConfigFile::from_json(
+            r#"{ "pedals": { "left": "skip", "2": { "type": "media", "value": "play" } } }"#,
+        )
+        .unwrap();
+        assert_eq!(file.pedals["left"], PedalEntry::Keyword(SKIP.to_string()));
+        assert!(matches!(&file.pedals["2"], PedalEntry::Config(spec) if spec.config_type == "media"));
+
+        let bad = PedalSpec { config_type: "keyboard".to_string(), ..Default::default() };
+        assert!(bad.to_configuration().is_err());
+    }
+}
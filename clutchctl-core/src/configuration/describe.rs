@@ -0,0 +1,153 @@
+//! Structured descriptions of configurations
+//!
+//! [`Configuration::describe`] breaks a configuration into its parts, so GUIs
+//! and translations can render it without parsing the `Display` output. The
+//! `Display` impls here are the English rendering, which `Configuration` and
+//! the individual configuration types use for their own `to_string`.
+
+use super::keyboard::KeyMode;
+use super::mouse::{MouseButton, MouseMode};
+use super::text::escape_text;
+use super::{
+    Configuration, ConfigurationType, GamepadConfiguration, KeyboardConfiguration,
+    MediaConfiguration, MouseConfiguration, TextConfiguration, Trigger,
+};
+use crate::protocol::{GameKey, MediaButton};
+use std::fmt;
+
+/// Language-neutral description of a configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDescription {
+    /// Configuration type; `None` for raw and unconfigured pedals
+    pub kind: Option<ConfigurationType>,
+    /// Trigger; `None` for raw and unconfigured pedals
+    pub trigger: Option<Trigger>,
+    /// Type-specific contents
+    pub details: ConfigDetails,
+}
+
+/// Type-specific contents of a [`ConfigDescription`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigDetails {
+    Keyboard {
+        one_shot: bool,
+        /// Modifier key labels such as "LCtrl", in a fixed order
+        modifiers: Vec<&'static str>,
+        keys: Vec<String>,
+    },
+    /// Buttons sorted by name
+    MouseButtons(Vec<MouseButton>),
+    MouseAxis {
+        x: i8,
+        y: i8,
+        wheel: i8,
+    },
+    Text(String),
+    Media(MediaButton),
+    Gamepad(GameKey),
+    Raw {
+        config_type: u8,
+    },
+    Unconfigured,
+}
+
+impl Configuration {
+    /// Describe the configuration without committing to a language
+    pub fn describe(&self) -> ConfigDescription {
+        let details = match self {
+            Configuration::Keyboard(c) => ConfigDetails::from(c),
+            Configuration::Mouse(c) => ConfigDetails::from(c),
+            Configuration::Text(c) => ConfigDetails::from(c),
+            Configuration::Media(c) => ConfigDetails::from(c),
+            Configuration::Gamepad(c) => ConfigDetails::from(c),
+            Configuration::Raw { config_type, .. } => ConfigDetails::Raw { config_type: *config_type },
+            Configuration::Unconfigured => ConfigDetails::Unconfigured,
+        };
+
+        ConfigDescription {
+            kind: self.configuration_type(),
+            trigger: self.trigger(),
+            details,
+        }
+    }
+}
+
+impl From<&KeyboardConfiguration> for ConfigDetails {
+    fn from(config: &KeyboardConfiguration) -> Self {
+        ConfigDetails::Keyboard {
+            one_shot: config.mode == KeyMode::OneShot,
+            modifiers: config.modifiers_display(),
+            keys: config.main_keys().to_vec(),
+        }
+    }
+}
+
+impl From<&MouseConfiguration> for ConfigDetails {
+    fn from(config: &MouseConfiguration) -> Self {
+        match &config.mode {
+            MouseMode::Buttons(buttons) => {
+                let mut buttons: Vec<MouseButton> = buttons.iter().copied().collect();
+                buttons.sort_by_key(|button| button.as_str());
+                ConfigDetails::MouseButtons(buttons)
+            }
+            MouseMode::Axis { x, y, wheel } => ConfigDetails::MouseAxis { x: *x, y: *y, wheel: *wheel },
+        }
+    }
+}
+
+impl From<&TextConfiguration> for ConfigDetails {
+    fn from(config: &TextConfiguration) -> Self {
+        ConfigDetails::Text(config.text.clone())
+    }
+}
+
+impl From<&MediaConfiguration> for ConfigDetails {
+    fn from(config: &MediaConfiguration) -> Self {
+        ConfigDetails::Media(config.button)
+    }
+}
+
+impl From<&GamepadConfiguration> for ConfigDetails {
+    fn from(config: &GamepadConfiguration) -> Self {
+        ConfigDetails::Gamepad(config.button)
+    }
+}
+
+impl fmt::Display for ConfigDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigDetails::Keyboard { one_shot, modifiers, keys } => {
+                let mode_str = if *one_shot { "Keyboard (One-shot)" } else { "Keyboard" };
+                let mut parts = modifiers.clone();
+                parts.extend(keys.iter().map(String::as_str));
+                write!(f, "{}: {}", mode_str, parts.join("+"))?;
+                if keys.is_empty() && !modifiers.is_empty() {
+                    write!(f, " (held)")?;
+                }
+                Ok(())
+            }
+            ConfigDetails::MouseButtons(buttons) => {
+                let names: Vec<&str> = buttons.iter().map(|button| button.as_str()).collect();
+                write!(f, "Mouse: {}", names.join("+"))
+            }
+            ConfigDetails::MouseAxis { x, y, wheel } => {
+                if *wheel != 0 {
+                    write!(f, "Mouse: axis({}, {}, {})", x, y, wheel)
+                } else {
+                    write!(f, "Mouse: axis({}, {})", x, y)
+                }
+            }
+            ConfigDetails::Text(text) => write!(f, "Text: \"{}\"", escape_text(text)),
+            ConfigDetails::Media(button) => write!(f, "Media: {}", MediaConfiguration::new(*button).button_name()),
+            ConfigDetails::Gamepad(button) => write!(f, "Gamepad: {}", GamepadConfiguration::new(*button).button_name()),
+            ConfigDetails::Raw { config_type } => write!(f, "Raw(type=0x{:02x})", config_type),
+            ConfigDetails::Unconfigured => write!(f, "Unconfigured"),
+        }
+    }
+}
+
+impl fmt::Display for ConfigDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.details.fmt(f)
+    }
+}
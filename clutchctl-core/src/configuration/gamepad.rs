@@ -1,10 +1,46 @@
 //! Gamepad configuration type
 
-use super::{BaseConfiguration, ConfigurationType, Trigger};
+use super::{BaseConfiguration, ConfigDetails, ConfigurationType, Trigger};
 use crate::protocol::GameKey;
 
+/// Accepted game button names, canonical name first for each button
+const BUTTON_NAMES: &[(&str, GameKey)] = &[
+    ("left", GameKey::Left),
+    ("dpad-left", GameKey::Left),
+    ("right", GameKey::Right),
+    ("dpad-right", GameKey::Right),
+    ("up", GameKey::Up),
+    ("dpad-up", GameKey::Up),
+    ("down", GameKey::Down),
+    ("dpad-down", GameKey::Down),
+    ("button1", GameKey::Button1),
+    ("button-1", GameKey::Button1),
+    ("1", GameKey::Button1),
+    ("button2", GameKey::Button2),
+    ("button-2", GameKey::Button2),
+    ("2", GameKey::Button2),
+    ("button3", GameKey::Button3),
+    ("button-3", GameKey::Button3),
+    ("3", GameKey::Button3),
+    ("button4", GameKey::Button4),
+    ("button-4", GameKey::Button4),
+    ("4", GameKey::Button4),
+    ("button5", GameKey::Button5),
+    ("button-5", GameKey::Button5),
+    ("5", GameKey::Button5),
+    ("button6", GameKey::Button6),
+    ("button-6", GameKey::Button6),
+    ("6", GameKey::Button6),
+    ("button7", GameKey::Button7),
+    ("button-7", GameKey::Button7),
+    ("7", GameKey::Button7),
+    ("button8", GameKey::Button8),
+    ("button-8", GameKey::Button8),
+    ("8", GameKey::Button8),
+];
+
 /// Gamepad configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GamepadConfiguration {
     /// Game button
     pub button: GameKey,
@@ -23,21 +59,24 @@ impl GamepadConfiguration {
 
     /// Parse game button from string
     pub fn parse_button(s: &str) -> Option<GameKey> {
-        match s.to_lowercase().as_str() {
-            "left" | "dpad-left" => Some(GameKey::Left),
-            "right" | "dpad-right" => Some(GameKey::Right),
-            "up" | "dpad-up" => Some(GameKey::Up),
-            "down" | "dpad-down" => Some(GameKey::Down),
-            "button1" | "button-1" | "1" => Some(GameKey::Button1),
-            "button2" | "button-2" | "2" => Some(GameKey::Button2),
-            "button3" | "button-3" | "3" => Some(GameKey::Button3),
-            "button4" | "button-4" | "4" => Some(GameKey::Button4),
-            "button5" | "button-5" | "5" => Some(GameKey::Button5),
-            "button6" | "button-6" | "6" => Some(GameKey::Button6),
-            "button7" | "button-7" | "7" => Some(GameKey::Button7),
-            "button8" | "button-8" | "8" => Some(GameKey::Button8),
-            _ => None,
-        }
+        let name = s.to_lowercase();
+        BUTTON_NAMES
+            .iter()
+            .find(|&&(candidate, _)| candidate == name)
+            .map(|&(_, button)| button)
+    }
+
+    /// Every name [`parse_button`](Self::parse_button) accepts, aliases included
+    pub fn all_names() -> Vec<&'static str> {
+        BUTTON_NAMES.iter().map(|&(name, _)| name).collect()
+    }
+
+    /// Canonical name of the button, as accepted by [`parse_button`](Self::parse_button)
+    pub fn canonical_name(&self) -> &'static str {
+        BUTTON_NAMES
+            .iter()
+            .find(|&&(_, button)| button == self.button)
+            .map_or("", |&(name, _)| name)
     }
 
     /// Get display name for game button
@@ -73,6 +112,18 @@ impl BaseConfiguration for GamepadConfiguration {
     }
 
     fn to_string(&self) -> String {
-        format!("Gamepad: {}", self.button_name())
+        ConfigDetails::from(self).to_string()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_names_parse() {
+        let gamepad = GamepadConfiguration::all_names();
+        assert!(gamepad.contains(&"button1"));
+        assert!(gamepad.iter().all(|name| GamepadConfiguration::parse_button(name).is_some()));
+    }
+}
@@ -1,7 +1,8 @@
 //! Keyboard configuration type
 
-use super::{BaseConfiguration, ConfigurationType, Trigger};
-use crate::protocol::ModifierKeys;
+use super::{BaseConfiguration, ConfigDetails, ConfigurationType, Trigger};
+use crate::error::{PedalError, Result};
+use crate::protocol::{parse_hex_scan_code, parse_raw_key, ModifierKeys, HID_KEYMAP};
 
 /// Keyboard activation mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,8 +13,45 @@ pub enum KeyMode {
     OneShot,
 }
 
+/// Display names of the modifier keys, in display order
+const MODIFIER_NAMES: &[(ModifierKeys, &str)] = &[
+    (ModifierKeys::LEFT_CONTROL, "LCtrl"),
+    (ModifierKeys::RIGHT_CONTROL, "RCtrl"),
+    (ModifierKeys::LEFT_SHIFT, "LShift"),
+    (ModifierKeys::RIGHT_SHIFT, "RShift"),
+    (ModifierKeys::LEFT_ALT, "LAlt"),
+    (ModifierKeys::RIGHT_ALT, "RAlt"),
+    (ModifierKeys::LEFT_SUPER, "LSuper"),
+    (ModifierKeys::RIGHT_SUPER, "RSuper"),
+];
+
+/// Names accepted for each modifier key, lowercase
+const MODIFIER_ALIASES: &[(&str, ModifierKeys)] = &[
+    ("ctrl", ModifierKeys::LEFT_CONTROL),
+    ("control", ModifierKeys::LEFT_CONTROL),
+    ("lctrl", ModifierKeys::LEFT_CONTROL),
+    ("lcontrol", ModifierKeys::LEFT_CONTROL),
+    ("rctrl", ModifierKeys::RIGHT_CONTROL),
+    ("rcontrol", ModifierKeys::RIGHT_CONTROL),
+    ("shift", ModifierKeys::LEFT_SHIFT),
+    ("lshift", ModifierKeys::LEFT_SHIFT),
+    ("rshift", ModifierKeys::RIGHT_SHIFT),
+    ("alt", ModifierKeys::LEFT_ALT),
+    ("lalt", ModifierKeys::LEFT_ALT),
+    ("ralt", ModifierKeys::RIGHT_ALT),
+    ("super", ModifierKeys::LEFT_SUPER),
+    ("win", ModifierKeys::LEFT_SUPER),
+    ("cmd", ModifierKeys::LEFT_SUPER),
+    ("lsuper", ModifierKeys::LEFT_SUPER),
+    ("lwin", ModifierKeys::LEFT_SUPER),
+    ("lcmd", ModifierKeys::LEFT_SUPER),
+    ("rsuper", ModifierKeys::RIGHT_SUPER),
+    ("rwin", ModifierKeys::RIGHT_SUPER),
+    ("rcmd", ModifierKeys::RIGHT_SUPER),
+];
+
 /// Keyboard configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeyboardConfiguration {
     /// Activation mode
     pub mode: KeyMode,
@@ -46,74 +84,121 @@ impl KeyboardConfiguration {
         }
     }
 
-    /// Parse modifier keys from a key string
-    pub fn parse_modifiers(key: &str) -> (ModifierKeys, Option<String>) {
+    /// Parse a key string (e.g. "lctrl+shift+a+b") into modifiers and every main key
+    ///
+    /// Parts naming a modifier set its bit; all other parts, hex scan codes
+    /// included, are main keys in the order given. The main keys are empty when
+    /// the string only names modifiers. Also accepts the output of
+    /// [`format_keys`](Self::format_keys).
+    pub fn parse_modifiers(keys: &str) -> (ModifierKeys, Vec<String>) {
         let mut modifiers = ModifierKeys::empty();
-        let parts: Vec<&str> = key.split('+').collect();
-
-        if parts.len() == 1 {
-            return (modifiers, Some(key.to_string()));
+        let mut main_keys = Vec::new();
+        for part in keys.split('+').filter(|part| !part.is_empty()) {
+            match Self::modifier_from_name(part) {
+                Some(modifier) => modifiers |= modifier,
+                None => main_keys.push(part.to_string()),
+            }
         }
+        (modifiers, main_keys)
+    }
 
-        let mut main_key = None;
-        for part in parts {
-            match part.to_lowercase().as_str() {
-                "lcontrol" | "lctrl" => modifiers |= ModifierKeys::LEFT_CONTROL,
-                "rcontrol" | "rctrl" => modifiers |= ModifierKeys::RIGHT_CONTROL,
-                "control" | "ctrl" => modifiers |= ModifierKeys::LEFT_CONTROL,
-                "lshift" => modifiers |= ModifierKeys::LEFT_SHIFT,
-                "rshift" => modifiers |= ModifierKeys::RIGHT_SHIFT,
-                "shift" => modifiers |= ModifierKeys::LEFT_SHIFT,
-                "lalt" => modifiers |= ModifierKeys::LEFT_ALT,
-                "ralt" => modifiers |= ModifierKeys::RIGHT_ALT,
-                "alt" => modifiers |= ModifierKeys::LEFT_ALT,
-                "lsuper" | "lwin" | "lcmd" => modifiers |= ModifierKeys::LEFT_SUPER,
-                "rsuper" | "rwin" | "rcmd" => modifiers |= ModifierKeys::RIGHT_SUPER,
-                "super" | "win" | "cmd" => modifiers |= ModifierKeys::LEFT_SUPER,
-                _ => main_key = Some(part.to_string()),
+    /// [`parse_modifiers`](Self::parse_modifiers) for user input: rejects what it would let through
+    ///
+    /// A modifier given twice (`ctrl+lctrl`) and a main key that is neither a
+    /// key name nor a hex scan code are `PedalError::InvalidConfiguration`;
+    /// for a likely typo the error suggests the closest name ("unknown key
+    /// 'ctlr'; did you mean ctrl?").
+    pub fn parse_keys(keys: &str) -> Result<(ModifierKeys, Vec<String>)> {
+        let mut modifiers = ModifierKeys::empty();
+        let mut main_keys = Vec::new();
+        for part in keys.split('+').filter(|part| !part.is_empty()) {
+            match Self::modifier_from_name(part) {
+                Some(modifier) if modifiers.contains(modifier) => {
+                    return Err(PedalError::InvalidConfiguration(format!(
+                        "modifier '{}' is given twice in '{}'", part, keys
+                    )));
+                }
+                Some(modifier) => modifiers |= modifier,
+                None => {
+                    check_key_name(part)?;
+                    main_keys.push(part.to_string());
+                }
             }
         }
+        Ok((modifiers, main_keys))
+    }
+
+    /// Modifier named by a key string part, if it is one
+    fn modifier_from_name(name: &str) -> Option<ModifierKeys> {
+        let name = name.to_lowercase();
+        MODIFIER_ALIASES.iter()
+            .find(|&&(alias, _)| alias == name)
+            .map(|&(_, modifier)| modifier)
+    }
+
+    /// Display names of the active modifiers, in a fixed order
+    pub fn modifiers_display(&self) -> Vec<&'static str> {
+        MODIFIER_NAMES
+            .iter()
+            .filter(|&&(modifier, _)| self.modifiers.contains(modifier))
+            .map(|&(_, name)| name)
+            .collect()
+    }
+
+    /// Non-modifier keys
+    pub fn main_keys(&self) -> &[String] {
+        &self.keys
+    }
 
-        (modifiers, main_key)
+    /// Check whether only modifiers are set, so the pedal acts as a held modifier
+    pub fn is_modifier_only(&self) -> bool {
+        self.keys.is_empty() && !self.modifiers.is_empty()
     }
 
     /// Format modifiers and keys for display
     pub fn format_keys(&self) -> String {
-        let mut parts = Vec::new();
+        let mut parts = self.modifiers_display();
+        parts.extend(self.keys.iter().map(String::as_str));
+        parts.join("+")
+    }
+}
 
-        // Add modifiers
-        if self.modifiers.contains(ModifierKeys::LEFT_CONTROL) {
-            parts.push("LCtrl");
-        }
-        if self.modifiers.contains(ModifierKeys::RIGHT_CONTROL) {
-            parts.push("RCtrl");
-        }
-        if self.modifiers.contains(ModifierKeys::LEFT_SHIFT) {
-            parts.push("LShift");
-        }
-        if self.modifiers.contains(ModifierKeys::RIGHT_SHIFT) {
-            parts.push("RShift");
-        }
-        if self.modifiers.contains(ModifierKeys::LEFT_ALT) {
-            parts.push("LAlt");
-        }
-        if self.modifiers.contains(ModifierKeys::RIGHT_ALT) {
-            parts.push("RAlt");
-        }
-        if self.modifiers.contains(ModifierKeys::LEFT_SUPER) {
-            parts.push("LSuper");
-        }
-        if self.modifiers.contains(ModifierKeys::RIGHT_SUPER) {
-            parts.push("RSuper");
-        }
+/// Check that a main key is a key name, a valid raw usage code or a valid hex scan code
+fn check_key_name(key: &str) -> Result<()> {
+    if let Some(code) = parse_raw_key(key).or_else(|| parse_hex_scan_code(key)) {
+        return code.map(|_| ());
+    }
+    if HID_KEYMAP.encode_key(key).is_some() {
+        return Ok(());
+    }
 
-        // Add main keys
-        for key in &self.keys {
-            parts.push(key);
-        }
+    let lowercase = key.to_lowercase();
+    let suggestion = MODIFIER_ALIASES.iter()
+        .map(|&(alias, _)| alias)
+        .chain(HID_KEYMAP.all_key_names())
+        .map(|name| (edit_distance(&lowercase, &name.to_lowercase()), name))
+        .filter(|&(distance, _)| distance <= 2 && distance < key.chars().count())
+        .min_by_key(|&(distance, _)| distance);
 
-        parts.join("+")
+    Err(PedalError::InvalidConfiguration(match suggestion {
+        Some((_, name)) => format!("unknown key '{}'; did you mean {}?", key, name),
+        None => format!("unknown key '{}'", key),
+    }))
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
     }
+    previous[b.len()]
 }
 
 impl BaseConfiguration for KeyboardConfiguration {
@@ -130,10 +215,81 @@ impl BaseConfiguration for KeyboardConfiguration {
     }
 
     fn to_string(&self) -> String {
-        let mode_str = match self.mode {
-            KeyMode::Standard => "Keyboard",
-            KeyMode::OneShot => "Keyboard (One-shot)",
-        };
-        format!("{}: {}", mode_str, self.format_keys())
+        ConfigDetails::from(self).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::Configuration;
+
+    #[test]
+    fn test_keyboard_modifiers_and_main_keys() {
+        let (modifiers, keys) = KeyboardConfiguration::parse_modifiers("shift+ctrl+a");
+        let config = KeyboardConfiguration::with_modifiers(
+            KeyMode::Standard,
+            keys,
+            modifiers,
+        );
+
+        assert_eq!(config.modifiers_display(), vec!["LCtrl", "LShift"]);
+        assert_eq!(config.main_keys(), ["a".to_string()]);
+        assert_eq!(config.format_keys(), "LCtrl+LShift+a");
+    }
+
+    #[test]
+    fn test_modifier_only_keyboard() {
+        let (modifiers, keys) = KeyboardConfiguration::parse_modifiers("lshift");
+        assert!(keys.is_empty());
+
+        let config = KeyboardConfiguration::with_modifiers(KeyMode::Standard, Vec::new(), modifiers);
+        assert!(config.is_modifier_only());
+        assert_eq!(config.format_keys(), "LShift");
+        assert_eq!(Configuration::Keyboard(config).to_string(), "Keyboard: LShift (held)");
+
+        assert_eq!(KeyboardConfiguration::parse_modifiers("a"), (ModifierKeys::empty(), vec!["a".to_string()]));
+    }
+
+    #[test]
+    fn test_parse_keys_rejects_typos_and_repeats() {
+        let error = KeyboardConfiguration::parse_keys("ctlr+a").unwrap_err().to_string();
+        assert!(error.contains("unknown key 'ctlr'; did you mean ctrl?"), "{}", error);
+        let error = KeyboardConfiguration::parse_keys("shfit+a").unwrap_err().to_string();
+        assert!(error.contains("did you mean shift?"), "{}", error);
+        let error = KeyboardConfiguration::parse_keys("ctrl+qqqqqq").unwrap_err().to_string();
+        assert!(error.ends_with("unknown key 'qqqqqq'"), "{}", error);
+
+        assert!(matches!(
+            KeyboardConfiguration::parse_keys("ctrl+lctrl+a"),
+            Err(PedalError::InvalidConfiguration(_))
+        ));
+        assert!(KeyboardConfiguration::parse_keys("ctrl+0xzz").is_err());
+        assert!(KeyboardConfiguration::parse_keys("ctrl+raw:0xe0").is_err());
+        assert_eq!(
+            KeyboardConfiguration::parse_keys("ctrl+raw:0x68").unwrap(),
+            (ModifierKeys::LEFT_CONTROL, vec!["raw:0x68".to_string()])
+        );
+
+        assert_eq!(
+            KeyboardConfiguration::parse_keys("LCtrl+rctrl+F5+0x04").unwrap(),
+            KeyboardConfiguration::parse_modifiers("LCtrl+rctrl+F5+0x04")
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_modifiers_keeps_every_main_key() {
+        assert_eq!(
+            KeyboardConfiguration::parse_modifiers("ctrl+a+b"),
+            (ModifierKeys::LEFT_CONTROL, vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            KeyboardConfiguration::parse_modifiers("a+b"),
+            (ModifierKeys::empty(), vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            KeyboardConfiguration::parse_modifiers("ctrl+0x04"),
+            (ModifierKeys::LEFT_CONTROL, vec!["0x04".to_string()])
+        );
+    }
+}
@@ -1,13 +1,70 @@
 //! Media control configuration type
+//!
+//! A media configuration can record a keyboard key to fall back on in apps
+//! that ignore consumer-control codes. No supported firmware sends both codes
+//! from one pedal, so the fallback is never written: encoders ignore it and
+//! configurations read back from a device have none. It exists so host-side
+//! tools that inject the key themselves have somewhere to keep it.
 
-use super::{BaseConfiguration, ConfigurationType, Trigger};
-use crate::protocol::MediaButton;
+use super::{BaseConfiguration, ConfigDetails, ConfigurationType, Trigger};
+use crate::error::{PedalError, Result};
+use crate::protocol::{MediaButton, HID_KEYMAP};
+
+/// Every media button with its accepted names (canonical name first) and display name
+///
+/// Parsing, display and name enumeration are all derived from this table.
+const BUTTONS: &[(MediaButton, &[&str], &str)] = &[
+    (MediaButton::VolumeMinus, &["volume-down", "volume-minus"], "Volume Down"),
+    (MediaButton::VolumePlus, &["volume-up", "volume-plus"], "Volume Up"),
+    (MediaButton::Mute, &["mute"], "Mute"),
+    (MediaButton::Play, &["play", "play-pause"], "Play/Pause"),
+    (MediaButton::Forward, &["forward", "fast-forward"], "Fast Forward"),
+    (MediaButton::Next, &["next", "skip"], "Next Track"),
+    (MediaButton::Stop, &["stop"], "Stop"),
+    (MediaButton::OpenPlayer, &["open-player", "player"], "Open Player"),
+    (MediaButton::OpenHomepage, &["open-homepage", "homepage", "home"], "Open Homepage"),
+    (MediaButton::StopWebpage, &["stop-webpage", "stop-page"], "Stop Webpage"),
+    (MediaButton::BackBrowse, &["back-browse", "browser-back"], "Browser Back"),
+    (MediaButton::ForwardBrowse, &["forward-browse", "browser-forward"], "Browser Forward"),
+    (MediaButton::Refresh, &["refresh", "reload"], "Refresh"),
+    (MediaButton::OpenMyComputer, &["open-my-computer", "my-computer", "computer"], "Open My Computer"),
+    (MediaButton::OpenMail, &["open-mail", "mail", "email"], "Open Mail"),
+    (MediaButton::OpenCalc, &["open-calc", "calculator", "calc"], "Open Calculator"),
+    (MediaButton::OpenSearch, &["open-search", "search"], "Open Search"),
+    (MediaButton::Shutdown, &["shutdown", "power-off"], "Shutdown"),
+    (MediaButton::Sleep, &["sleep", "suspend"], "Sleep"),
+];
+
+/// Buttons with unconfirmed codes, see [`MediaButton::is_experimental`]
+#[cfg(feature = "experimental")]
+const EXPERIMENTAL_BUTTONS: &[(MediaButton, &[&str], &str)] = &[
+    (MediaButton::BrightnessUp, &["brightness-up"], "Brightness Up"),
+    (MediaButton::BrightnessDown, &["brightness-down"], "Brightness Down"),
+    (MediaButton::MicMute, &["mic-mute", "microphone-mute"], "Mic Mute"),
+];
+
+/// Every table entry, experimental buttons last
+fn buttons() -> impl Iterator<Item = &'static (MediaButton, &'static [&'static str], &'static str)> {
+    let buttons = BUTTONS.iter();
+    #[cfg(feature = "experimental")]
+    let buttons = buttons.chain(EXPERIMENTAL_BUTTONS);
+    buttons
+}
+
+/// Table entry for a button
+fn entry(button: MediaButton) -> &'static (MediaButton, &'static [&'static str], &'static str) {
+    buttons()
+        .find(|&&(candidate, _, _)| candidate == button)
+        .expect("every media button is in the table")
+}
 
 /// Media configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MediaConfiguration {
     /// Media button
     pub button: MediaButton,
+    /// Keyboard key for apps that ignore the media code; host-side only
+    pub keyboard_fallback: Option<String>,
     /// Trigger mode
     trigger: Trigger,
 }
@@ -17,59 +74,50 @@ impl MediaConfiguration {
     pub fn new(button: MediaButton) -> Self {
         Self {
             button,
+            keyboard_fallback: None,
             trigger: Trigger::OnPress,
         }
     }
 
+    /// Record a keyboard key, by keymap name, to stand in for the media code
+    ///
+    /// The key is not sent by the pedal; see the module documentation.
+    pub fn with_keyboard_fallback(mut self, key: &str) -> Result<Self> {
+        if HID_KEYMAP.encode_key(key).is_none() {
+            return Err(PedalError::InvalidConfiguration(format!("Unknown fallback key: {}", key)));
+        }
+        self.keyboard_fallback = Some(key.to_string());
+        Ok(self)
+    }
+
     /// Parse media button from string
+    ///
+    /// Accepts every alias as well as the display name, ignoring case.
     pub fn parse_button(s: &str) -> Option<MediaButton> {
-        match s.to_lowercase().replace('_', "-").as_str() {
-            "volume-down" | "volume-minus" => Some(MediaButton::VolumeMinus),
-            "volume-up" | "volume-plus" => Some(MediaButton::VolumePlus),
-            "mute" => Some(MediaButton::Mute),
-            "play" | "play-pause" => Some(MediaButton::Play),
-            "forward" | "fast-forward" => Some(MediaButton::Forward),
-            "next" | "skip" => Some(MediaButton::Next),
-            "stop" => Some(MediaButton::Stop),
-            "open-player" | "player" => Some(MediaButton::OpenPlayer),
-            "open-homepage" | "homepage" | "home" => Some(MediaButton::OpenHomepage),
-            "stop-webpage" | "stop-page" => Some(MediaButton::StopWebpage),
-            "back-browse" | "browser-back" => Some(MediaButton::BackBrowse),
-            "forward-browse" | "browser-forward" => Some(MediaButton::ForwardBrowse),
-            "refresh" | "reload" => Some(MediaButton::Refresh),
-            "open-my-computer" | "my-computer" | "computer" => Some(MediaButton::OpenMyComputer),
-            "open-mail" | "mail" | "email" => Some(MediaButton::OpenMail),
-            "open-calc" | "calculator" | "calc" => Some(MediaButton::OpenCalc),
-            "open-search" | "search" => Some(MediaButton::OpenSearch),
-            "shutdown" | "power-off" => Some(MediaButton::Shutdown),
-            "sleep" | "suspend" => Some(MediaButton::Sleep),
-            _ => None,
-        }
+        let name = s.to_lowercase().replace('_', "-");
+        buttons()
+            .find(|&&(_, aliases, display)| aliases.contains(&name.as_str()) || display.eq_ignore_ascii_case(s))
+            .map(|&(button, _, _)| button)
+    }
+
+    /// Every media button, in table order
+    pub fn all_buttons() -> Vec<MediaButton> {
+        buttons().map(|&(button, _, _)| button).collect()
+    }
+
+    /// Every name [`parse_button`](Self::parse_button) accepts, aliases included
+    pub fn all_names() -> Vec<&'static str> {
+        buttons().flat_map(|&(_, aliases, _)| aliases.iter().copied()).collect()
+    }
+
+    /// Canonical name of the button, as accepted by [`parse_button`](Self::parse_button)
+    pub fn canonical_name(&self) -> &'static str {
+        entry(self.button).1[0]
     }
 
     /// Get display name for media button
     pub fn button_name(&self) -> &'static str {
-        match self.button {
-            MediaButton::VolumeMinus => "Volume Down",
-            MediaButton::VolumePlus => "Volume Up",
-            MediaButton::Mute => "Mute",
-            MediaButton::Play => "Play/Pause",
-            MediaButton::Forward => "Fast Forward",
-            MediaButton::Next => "Next Track",
-            MediaButton::Stop => "Stop",
-            MediaButton::OpenPlayer => "Open Player",
-            MediaButton::OpenHomepage => "Open Homepage",
-            MediaButton::StopWebpage => "Stop Webpage",
-            MediaButton::BackBrowse => "Browser Back",
-            MediaButton::ForwardBrowse => "Browser Forward",
-            MediaButton::Refresh => "Refresh",
-            MediaButton::OpenMyComputer => "Open My Computer",
-            MediaButton::OpenMail => "Open Mail",
-            MediaButton::OpenCalc => "Open Calculator",
-            MediaButton::OpenSearch => "Open Search",
-            MediaButton::Shutdown => "Shutdown",
-            MediaButton::Sleep => "Sleep",
-        }
+        entry(self.button).2
     }
 }
 
@@ -87,6 +135,53 @@ impl BaseConfiguration for MediaConfiguration {
     }
 
     fn to_string(&self) -> String {
-        format!("Media: {}", self.button_name())
+        ConfigDetails::from(self).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_names_parse() {
+        let media = MediaConfiguration::all_names();
+        assert!(media.contains(&"play"));
+        assert!(media.iter().all(|name| MediaConfiguration::parse_button(name).is_some()));
+    }
+
+    #[test]
+    fn test_media_button_table() {
+        // Every protocol variant must be in the table
+        let variants: Vec<MediaButton> = (0..=u8::MAX).filter_map(MediaButton::from_u8).collect();
+        assert_eq!(MediaConfiguration::all_buttons(), variants);
+        for button in variants {
+            let config = MediaConfiguration::new(button);
+            assert!(!config.canonical_name().is_empty());
+            assert_eq!(MediaConfiguration::parse_button(config.canonical_name()), Some(button));
+            assert_eq!(MediaConfiguration::parse_button(config.button_name()), Some(button));
+        }
+        assert_eq!(MediaConfiguration::parse_button("Volume_Up"), Some(MediaButton::VolumePlus));
+        assert_eq!(MediaConfiguration::parse_button("rewind"), None);
+    }
+
+    #[test]
+    fn test_media_keyboard_fallback() {
+        let config = MediaConfiguration::new(MediaButton::Play).with_keyboard_fallback("space").unwrap();
+        assert_eq!(config.keyboard_fallback.as_deref(), Some("space"));
+        assert!(MediaConfiguration::new(MediaButton::Play).keyboard_fallback.is_none());
+        assert!(matches!(
+            MediaConfiguration::new(MediaButton::Play).with_keyboard_fallback("nokey"),
+            Err(PedalError::InvalidConfiguration(_))
+        ));
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn test_experimental_media_buttons() {
+        let button = MediaConfiguration::parse_button("brightness-up").unwrap();
+        assert_eq!(button as u8, 20);
+        assert!(button.is_experimental());
+        assert!(!MediaButton::Sleep.is_experimental());
+    }
+}
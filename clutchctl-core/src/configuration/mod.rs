@@ -5,17 +5,21 @@ pub mod mouse;
 pub mod text;
 pub mod media;
 pub mod gamepad;
+pub mod describe;
 
 pub use keyboard::KeyboardConfiguration;
 pub use mouse::MouseConfiguration;
 pub use text::TextConfiguration;
 pub use media::MediaConfiguration;
 pub use gamepad::GamepadConfiguration;
+pub use describe::{ConfigDescription, ConfigDetails};
 
+use crate::error::PedalError;
 use crate::protocol::TriggerMode;
+use std::str::FromStr;
 
 /// Configuration type enumeration
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConfigurationType {
     Keyboard,
     Mouse,
@@ -24,6 +28,48 @@ pub enum ConfigurationType {
     Gamepad,
 }
 
+impl ConfigurationType {
+    /// Every configuration type, in display order
+    pub fn all() -> &'static [ConfigurationType] {
+        &[
+            ConfigurationType::Keyboard,
+            ConfigurationType::Mouse,
+            ConfigurationType::Text,
+            ConfigurationType::Media,
+            ConfigurationType::Gamepad,
+        ]
+    }
+
+    /// Lowercase name, as used on the command line
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigurationType::Keyboard => "keyboard",
+            ConfigurationType::Mouse => "mouse",
+            ConfigurationType::Text => "text",
+            ConfigurationType::Media => "media",
+            ConfigurationType::Gamepad => "gamepad",
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigurationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ConfigurationType {
+    type Err = PedalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ConfigurationType::all()
+            .iter()
+            .find(|t| t.as_str().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| PedalError::ParseError(format!("Unknown configuration type '{}'", s)))
+    }
+}
+
 /// Trigger type for pedal activation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Trigger {
@@ -65,13 +111,23 @@ pub trait BaseConfiguration {
 }
 
 /// Main configuration enum that holds all possible configurations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Configuration {
     Keyboard(KeyboardConfiguration),
     Mouse(MouseConfiguration),
     Text(TextConfiguration),
     Media(MediaConfiguration),
     Gamepad(GamepadConfiguration),
+    /// Configuration of a type clutchctl doesn't model, kept verbatim so it
+    /// survives a read/save cycle
+    ///
+    /// The trigger is kept alongside, since iKKEGOL devices store it outside
+    /// the config packet.
+    Raw {
+        config_type: u8,
+        data: [u8; 38],
+        trigger: Trigger,
+    },
     Unconfigured,
 }
 
@@ -89,7 +145,7 @@ impl Configuration {
             Configuration::Text(_) => Some(ConfigurationType::Text),
             Configuration::Media(_) => Some(ConfigurationType::Media),
             Configuration::Gamepad(_) => Some(ConfigurationType::Gamepad),
-            Configuration::Unconfigured => None,
+            Configuration::Raw { .. } | Configuration::Unconfigured => None,
         }
     }
 
@@ -101,6 +157,7 @@ impl Configuration {
             Configuration::Text(c) => Some(c.trigger()),
             Configuration::Media(c) => Some(c.trigger()),
             Configuration::Gamepad(c) => Some(c.trigger()),
+            Configuration::Raw { trigger, .. } => Some(*trigger),
             Configuration::Unconfigured => None,
         }
     }
@@ -113,6 +170,7 @@ impl Configuration {
             Configuration::Text(c) => c.set_trigger(trigger),
             Configuration::Media(c) => c.set_trigger(trigger),
             Configuration::Gamepad(c) => c.set_trigger(trigger),
+            Configuration::Raw { trigger: raw_trigger, .. } => *raw_trigger = trigger,
             Configuration::Unconfigured => {}
         }
     }
@@ -120,13 +178,74 @@ impl Configuration {
 
 impl std::fmt::Display for Configuration {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Configuration::Keyboard(c) => write!(f, "{}", c.to_string()),
-            Configuration::Mouse(c) => write!(f, "{}", c.to_string()),
-            Configuration::Text(c) => write!(f, "{}", c.to_string()),
-            Configuration::Media(c) => write!(f, "{}", c.to_string()),
-            Configuration::Gamepad(c) => write!(f, "{}", c.to_string()),
-            Configuration::Unconfigured => write!(f, "Unconfigured"),
+        // English rendering of the structured description
+        self.describe().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configuration_type_round_trip() {
+        for name in ["keyboard", "mouse", "text", "media", "gamepad"] {
+            let config_type: ConfigurationType = name.parse().unwrap();
+            assert_eq!(config_type.to_string(), name);
         }
+        assert_eq!(ConfigurationType::all().len(), 5);
+        assert_eq!("Keyboard".parse::<ConfigurationType>().unwrap(), ConfigurationType::Keyboard);
+        assert!("joystick".parse::<ConfigurationType>().is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_configuration_equality_includes_trigger() {
+        let press = Configuration::Text(TextConfiguration::new("hi".to_string()));
+        let mut release = press.clone();
+        assert_eq!(press, release);
+
+        release.set_trigger(Trigger::OnRelease);
+        assert_ne!(press, release);
+        assert_ne!(press, Configuration::Unconfigured);
+    }
+
+    #[test]
+    fn test_describe() {
+        let (modifiers, keys) = KeyboardConfiguration::parse_modifiers("ctrl+c");
+        let config = Configuration::Keyboard(KeyboardConfiguration::with_modifiers(
+            keyboard::KeyMode::OneShot,
+            keys,
+            modifiers,
+        ));
+        let description = config.describe();
+        assert_eq!(description.kind, Some(ConfigurationType::Keyboard));
+        assert_eq!(description.trigger, Some(Trigger::OnPress));
+        assert_eq!(description.details, ConfigDetails::Keyboard {
+            one_shot: true,
+            modifiers: vec!["LCtrl"],
+            keys: vec!["c".to_string()],
+        });
+        assert_eq!(description.to_string(), config.to_string());
+        assert_eq!(config.to_string(), "Keyboard (One-shot): LCtrl+c");
+
+        let buttons = mouse::MouseConfiguration::parse_buttons("right+left").unwrap();
+        let config = Configuration::Mouse(MouseConfiguration::buttons(buttons));
+        assert_eq!(
+            config.describe().details,
+            ConfigDetails::MouseButtons(vec![mouse::MouseButton::Left, mouse::MouseButton::Right])
+        );
+        assert_eq!(config.to_string(), "Mouse: left+right");
+
+        let description = Configuration::Unconfigured.describe();
+        assert_eq!((description.kind, description.trigger), (None, None));
+        assert_eq!(description.to_string(), "Unconfigured");
+    }
+
+    #[test]
+    fn test_raw_display() {
+        let mut raw = Configuration::Raw { config_type: 0x0b, data: [0; 38], trigger: Trigger::OnPress };
+        assert_eq!(raw.to_string(), "Raw(type=0x0b)");
+        raw.set_trigger(Trigger::OnRelease);
+        assert_eq!(raw.trigger(), Some(Trigger::OnRelease));
+    }
+}
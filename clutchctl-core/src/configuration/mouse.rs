@@ -1,6 +1,7 @@
 //! Mouse configuration type
 
-use super::{BaseConfiguration, ConfigurationType, Trigger};
+use super::{BaseConfiguration, ConfigDetails, ConfigurationType, Trigger};
+use crate::error::{PedalError, Result};
 use std::collections::HashSet;
 
 /// Mouse button types
@@ -14,16 +15,25 @@ pub enum MouseButton {
 }
 
 impl MouseButton {
+    /// Every mouse button
+    pub const ALL: [MouseButton; 5] = [
+        Self::Left,
+        Self::Right,
+        Self::Middle,
+        Self::Forward,
+        Self::Back,
+    ];
+
     /// Parse from string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
-        match s.to_lowercase().as_str() {
-            "left" => Some(Self::Left),
-            "right" => Some(Self::Right),
-            "middle" => Some(Self::Middle),
-            "forward" => Some(Self::Forward),
-            "back" => Some(Self::Back),
-            _ => None,
-        }
+        let name = s.to_lowercase();
+        Self::ALL.into_iter().find(|button| button.as_str() == name)
+    }
+
+    /// Names of every mouse button, as accepted by [`from_str`](Self::from_str)
+    pub fn all_names() -> Vec<&'static str> {
+        Self::ALL.iter().map(|button| button.as_str()).collect()
     }
 
     /// Convert to display string
@@ -39,7 +49,7 @@ impl MouseButton {
 }
 
 /// Mouse configuration mode
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MouseMode {
     /// Mouse button clicks
     Buttons(HashSet<MouseButton>),
@@ -52,7 +62,7 @@ pub enum MouseMode {
 }
 
 /// Mouse configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MouseConfiguration {
     /// Mouse mode
     pub mode: MouseMode,
@@ -69,6 +79,21 @@ impl MouseConfiguration {
         }
     }
 
+    /// Create a button configuration that clicks `clicks` times per press
+    ///
+    /// Neither protocol has a known click count field (the two unknown bytes
+    /// of the iKKEGOL mouse data aren't one), so anything but a single click
+    /// is `PedalError::InvalidConfiguration`.
+    pub fn buttons_with_clicks(buttons: HashSet<MouseButton>, clicks: u8) -> Result<Self> {
+        if clicks != 1 {
+            return Err(PedalError::InvalidConfiguration(format!(
+                "{} clicks per press: supported devices can only click once per press",
+                clicks
+            )));
+        }
+        Ok(Self::buttons(buttons))
+    }
+
     /// Create a new axis configuration
     pub fn axis(x: i8, y: i8, wheel: i8) -> Self {
         Self {
@@ -121,6 +146,32 @@ impl BaseConfiguration for MouseConfiguration {
     }
 
     fn to_string(&self) -> String {
-        format!("Mouse: {}", self.format())
+        ConfigDetails::from(self).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mouse_click_count() {
+        let buttons = MouseConfiguration::parse_buttons("left").unwrap();
+        let single = MouseConfiguration::buttons_with_clicks(buttons.clone(), 1).unwrap();
+        assert_eq!(single, MouseConfiguration::buttons(buttons.clone()));
+
+        for clicks in [0, 2] {
+            assert!(matches!(
+                MouseConfiguration::buttons_with_clicks(buttons.clone(), clicks),
+                Err(PedalError::InvalidConfiguration(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_all_names() {
+        let mouse = MouseButton::all_names();
+        assert!(mouse.contains(&"left"));
+        assert_eq!(mouse.len(), MouseButton::ALL.len());
     }
-}
\ No newline at end of file
+}
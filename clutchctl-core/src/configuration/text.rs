@@ -1,10 +1,42 @@
 //! Text configuration type
 
-use super::{BaseConfiguration, ConfigurationType, Trigger};
-use crate::protocol::HID_KEYMAP;
+use super::{BaseConfiguration, ConfigDetails, ConfigurationType, Trigger};
+use crate::error::{PedalError, Result};
+use crate::protocol::{HID_KEYMAP, SHIFT_FLAG};
+
+/// Bytes of scan codes a text configuration can hold in one iKKEGOL packet
+///
+/// Other models may allow more; see `DeviceCapabilities::max_text_length`.
+pub const MAX_TEXT_BYTES: usize = 38;
+
+/// Scan code the firmware types for a character, with [`SHIFT_FLAG`] for shifted ones
+fn encode_text_char(ch: char) -> Option<u8> {
+    match ch {
+        ' ' => Some(0x2c),
+        '\n' => Some(0x28),
+        '\t' => Some(0x2b),
+        _ => HID_KEYMAP
+            .encode_char_with_shift(ch)
+            .map(|(code, shift)| if shift { code | SHIFT_FLAG } else { code }),
+    }
+}
+
+/// Write newlines, tabs and backslashes as the escapes [`TextConfiguration::from_escaped`] reads
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\\' => escaped.push_str("\\\\"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
 
 /// Text configuration - types a string when pedal is activated
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TextConfiguration {
     /// Text to type
     pub text: String,
@@ -21,29 +53,105 @@ impl TextConfiguration {
         }
     }
 
-    /// Get the text with characters encoded as USB HID scan codes
-    pub fn encode_for_protocol(&self) -> Vec<u8> {
-        let mut encoded = Vec::new();
-
-        for ch in self.text.chars() {
-            // Convert character to HID scan code
-            if let Some(code) = HID_KEYMAP.encode_char(ch) {
-                encoded.push(code);
-            } else if ch == ' ' {
-                // Space character
-                encoded.push(0x2c);
-            } else {
-                // Skip unsupported characters
+    /// Create a text configuration from text with `\n` (enter), `\t` (tab)
+    /// and `\\` (backslash) escapes, as typed on a command line
+    ///
+    /// Any other backslash sequence is `PedalError::InvalidConfiguration`.
+    pub fn from_escaped(text: &str) -> Result<Self> {
+        let mut unescaped = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '\\' {
+                unescaped.push(ch);
                 continue;
             }
-
-            if encoded.len() >= 38 {
-                break; // Maximum text length
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some('\\') => unescaped.push('\\'),
+                other => {
+                    let sequence: String = std::iter::once('\\').chain(other).collect();
+                    return Err(PedalError::InvalidConfiguration(format!(
+                        "Unknown escape '{}' in text; use \\n, \\t or \\\\",
+                        sequence
+                    )));
+                }
             }
         }
+        Ok(Self::new(unescaped))
+    }
+
+    /// Check that every character can be typed by the firmware and that the
+    /// encoded text fits in [`MAX_TEXT_BYTES`]
+    ///
+    /// The firmware has no modifier byte per character, only a shift bit on the
+    /// scan code, so characters outside the keymap (non-ASCII, or symbols that
+    /// need AltGr) can't be encoded.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_with_limit(MAX_TEXT_BYTES)
+    }
+
+    /// [`Self::validate`] against a device's own limit of `max_bytes` encoded bytes
+    pub fn validate_with_limit(&self, max_bytes: usize) -> Result<()> {
+        self.validate_characters()?;
+
+        let encoded_len = self.encoded_len();
+        if encoded_len > max_bytes {
+            return Err(PedalError::InvalidConfiguration(format!(
+                "Text too long: {} characters encode to {} bytes (max {})",
+                self.text.chars().count(),
+                encoded_len,
+                max_bytes
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check only that every character can be typed, for text not yet bound to a device
+    pub fn validate_characters(&self) -> Result<()> {
+        let unsupported: Vec<char> = self.text.chars()
+            .filter(|&ch| encode_text_char(ch).is_none())
+            .collect();
+
+        if !unsupported.is_empty() {
+            let chars: String = unsupported.into_iter().collect();
+            return Err(PedalError::InvalidConfiguration(format!(
+                "Text contains characters that can't be typed: \"{}\"",
+                chars
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Number of scan code bytes the text encodes to, before truncation
+    ///
+    /// Every typeable character takes one byte (shift is a flag on the code),
+    /// so this is the character count minus characters that can't be typed.
+    pub fn encoded_len(&self) -> usize {
+        self.text.chars()
+            .filter(|&ch| encode_text_char(ch).is_some())
+            .count()
+    }
+
+    /// Get the text as USB HID scan codes, without truncation or padding
+    ///
+    /// Shifted characters are sent as the base scan code with `SHIFT_FLAG` set.
+    /// Characters that can't be encoded are skipped; see [`Self::validate`].
+    pub fn scan_codes(&self) -> Vec<u8> {
+        self.text.chars().filter_map(encode_text_char).collect()
+    }
+
+    /// Get the scan codes as the 38 data bytes of an iKKEGOL packet
+    ///
+    /// Text past [`MAX_TEXT_BYTES`] is cut off.
+    pub fn encode_for_protocol(&self) -> Vec<u8> {
+        let mut encoded = self.scan_codes();
+        encoded.truncate(MAX_TEXT_BYTES);
 
         // Pad with zeros
-        while encoded.len() < 38 {
+        while encoded.len() < MAX_TEXT_BYTES {
             encoded.push(0);
         }
 
@@ -52,6 +160,11 @@ impl TextConfiguration {
 
     /// Decode text from HID scan code format
     pub fn decode_from_protocol(data: &[u8; 38]) -> String {
+        Self::decode_scan_codes(data)
+    }
+
+    /// Decode scan codes of any length, stopping at the first zero byte
+    pub fn decode_scan_codes(data: &[u8]) -> String {
         let mut text = String::new();
 
         for &byte in data {
@@ -64,6 +177,10 @@ impl TextConfiguration {
                 // Handle special cases
                 if key_name == "space" {
                     text.push(' ');
+                } else if key_name == "enter" {
+                    text.push('\n');
+                } else if key_name == "tab" {
+                    text.push('\t');
                 } else if key_name.len() == 1 {
                     // Single character key
                     text.push_str(key_name);
@@ -97,6 +214,70 @@ impl BaseConfiguration for TextConfiguration {
     }
 
     fn to_string(&self) -> String {
-        format!("Text: \"{}\"", self.text)
+        ConfigDetails::from(self).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shifted_symbols_round_trip() {
+        let config = TextConfiguration::new("Hi! a_b?".to_string());
+        assert!(config.validate().is_ok());
+
+        let encoded = config.encode_for_protocol();
+        assert_eq!(&encoded[..3], &[0x0b | SHIFT_FLAG, 0x0c, 0x1e | SHIFT_FLAG]);
+
+        let mut data = [0u8; 38];
+        data.copy_from_slice(&encoded);
+        assert_eq!(TextConfiguration::decode_from_protocol(&data), "Hi! a_b?");
+    }
+
+    #[test]
+    fn test_validate_checks_encoded_length() {
+        // Shifted symbols still take one byte each, so 38 of them fit
+        let symbols = "!@#$%^&*()_+{}|:\"<>?~".repeat(2)[..38].to_string();
+        let config = TextConfiguration::new(symbols.clone());
+        assert_eq!(config.encoded_len(), MAX_TEXT_BYTES);
+        assert!(config.validate().is_ok());
+
+        let config = TextConfiguration::new(format!("{}!", symbols));
+        match config.validate() {
+            Err(PedalError::InvalidConfiguration(message)) => {
+                assert!(message.contains("39 characters encode to 39 bytes"), "{}", message);
+            }
+            other => panic!("expected a length error, got {:?}", other),
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_escape_sequences() {
+        let config = TextConfiguration::from_escaped("a\\nb").unwrap();
+        assert_eq!(config.text, "a\nb");
+        assert_eq!(&config.encode_for_protocol()[..4], &[0x04, 0x28, 0x05, 0]);
+
+        let config = TextConfiguration::from_escaped("x\\ty\\\\").unwrap();
+        assert_eq!(config.text, "x\ty\\");
+        assert_eq!(&config.encode_for_protocol()[..4], &[0x1b, 0x2b, 0x1c, 0x31]);
+        assert_eq!(escape_text(&config.text), "x\\ty\\\\");
+
+        let mut data = [0u8; 38];
+        data.copy_from_slice(&config.encode_for_protocol());
+        assert_eq!(TextConfiguration::decode_from_protocol(&data), config.text);
+
+        for bad in ["\\q", "trailing\\"] {
+            assert!(matches!(
+                TextConfiguration::from_escaped(bad),
+                Err(PedalError::InvalidConfiguration(_))
+            ), "{} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_unsupported_characters() {
+        let config = TextConfiguration::new("caf\u{e9}".to_string());
+        assert!(matches!(config.validate(), Err(PedalError::InvalidConfiguration(_))));
+    }
+}
@@ -1,130 +1,265 @@
 //! Device discovery functionality
 
-use crate::device::{IkkegolDevice, PCsensorDevice, PedalDevice};
-use crate::error::Result;
-use crate::usb::{get_hid_api, HidDeviceInfo};
+use crate::device::{DeviceOptions, IkkegolDevice, PCsensorDevice, PedalDevice};
+use crate::error::{PedalError, Result};
+use crate::usb::{get_refreshed_hid_api, HidDeviceInfo};
+use crate::user_config::{load_extra_device_ids, load_pedal_names, PedalOverride};
 use crate::SUPPORTED_DEVICES;
 use log::{debug, info};
+use std::ffi::CStr;
 use std::sync::Arc;
 
-/// Device info collected during enumeration (before opening devices)
-struct DiscoveredDeviceInfo {
-    vendor_id: u16,
-    product_id: u16,
-    device_type: &'static str,
-    hid_info: HidDeviceInfo,
+/// Built-in supported devices merged with any extra IDs from the user's config file
+pub fn supported_device_ids() -> Vec<(u16, u16, String)> {
+    let mut ids: Vec<(u16, u16, String)> = SUPPORTED_DEVICES
+        .iter()
+        .map(|&(vid, pid, device_type)| (vid, pid, device_type.to_string()))
+        .collect();
+
+    for (vid, pid, device_type) in load_extra_device_ids() {
+        if ids.iter().any(|&(v, p, _)| v == vid && p == pid) {
+            debug!("Extra device ID {:04x}:{:04x} is already supported", vid, pid);
+            continue;
+        }
+        ids.push((vid, pid, device_type));
+    }
+
+    ids
 }
 
-/// Discover all connected pedal devices
-pub fn discover_devices() -> Result<Vec<Arc<dyn crate::device::PedalDevice + Send + Sync>>> {
-    let mut devices: Vec<Arc<dyn crate::device::PedalDevice + Send + Sync>> = Vec::new();
-    let mut device_id = 0;
+/// A supported device found during enumeration, not yet opened
+///
+/// Opening a device reads its model and, for some protocols, its whole
+/// configuration with long timeouts. A handle defers all of that until
+/// [`DeviceHandle::open`] is called.
+#[derive(Debug, Clone)]
+pub struct DeviceHandle {
+    id: usize,
+    device_type: String,
+    info: HidDeviceInfo,
+    /// Names and order from `pedal-names.toml`; empty fields keep the model defaults
+    pedal_override: Option<PedalOverride>,
+}
+
+impl DeviceHandle {
+    /// Handle of the in-memory device that simulation discovers as ID 0
+    #[cfg(any(test, feature = "simulate"))]
+    pub(crate) fn simulated() -> Self {
+        Self {
+            id: 0,
+            device_type: crate::device::mock::SIMULATED_DEVICE_TYPE.to_string(),
+            info: crate::device::mock::simulated_info(),
+            pedal_override: None,
+        }
+    }
+
+    /// Device ID, the same one [`discover_devices`] assigns
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Protocol type from the supported device table (e.g. "iKKEGOL")
+    pub fn device_type(&self) -> &str {
+        &self.device_type
+    }
+
+    /// HID enumeration info
+    pub fn info(&self) -> &HidDeviceInfo {
+        &self.info
+    }
+
+    /// Open and initialize the device
+    pub fn open(&self) -> Result<Arc<dyn PedalDevice + Send + Sync>> {
+        self.open_with_options(&DeviceOptions::default())
+    }
+
+    /// Open and initialize the device with custom options
+    ///
+    /// Names and order in `options` take precedence over the configured ones.
+    pub fn open_with_options(&self, options: &DeviceOptions) -> Result<Arc<dyn PedalDevice + Send + Sync>> {
+        debug!("Opening {} device: VID={:04x} PID={:04x}",
+               self.device_type, self.info.vendor_id, self.info.product_id);
+
+        let mut options = options.clone();
+        if let Some(pedal_override) = &self.pedal_override {
+            if options.pedal_names.is_empty() {
+                options.pedal_names = pedal_override.names.clone();
+            }
+            if options.physical_order.is_empty() {
+                options.physical_order = pedal_override.order.clone();
+            }
+        }
+        let options = &options;
+
+        match self.device_type.as_str() {
+            #[cfg(any(test, feature = "simulate"))]
+            crate::device::mock::SIMULATED_DEVICE_TYPE => {
+                let pedal_count = crate::device::mock::simulated_pedal_count().unwrap_or(3);
+                Ok(Arc::new(crate::device::MockDevice::with_options(self.id, pedal_count, options)?))
+            },
+            // PCsensor devices use HID protocol
+            "PCsensor" | "FootSwitch1P" => {
+                PCsensorDevice::with_options(self.info.clone(), self.id, options)
+                    .map(|d| Arc::new(d) as Arc<dyn PedalDevice + Send + Sync>)
+            },
+            // iKKEGOL and Scythe devices use the iKKEGOL protocol
+            _ => {
+                IkkegolDevice::with_options(self.info.clone(), self.id, options)
+                    .map(|d| Arc::new(d) as Arc<dyn PedalDevice + Send + Sync>)
+            }
+        }
+    }
+}
+
+/// Enumerate supported devices without opening them
+///
+/// IDs are assigned in enumeration order, so they match the IDs of
+/// [`discover_devices`] (which skips devices that fail to open). With the
+/// `simulate` feature, [`enable_simulation`](crate::device::enable_simulation)
+/// replaces the HID devices with one in-memory device.
+pub fn discover_devices_lazy() -> Result<Vec<DeviceHandle>> {
+    #[cfg(feature = "simulate")]
+    if crate::device::mock::simulated_pedal_count().is_some() {
+        return Ok(vec![DeviceHandle::simulated()]);
+    }
 
     // Track which device paths we've already processed (to avoid duplicates from multiple interfaces)
     let mut processed_devices: std::collections::HashSet<(u16, u16, String)> = std::collections::HashSet::new();
 
-    // Collect device info while holding the HID API lock, then release it
-    // This avoids deadlock when device constructors try to open devices
-    let discovered_devices: Vec<DiscoveredDeviceInfo> = {
-        let api = get_hid_api()?;
-
-        let mut found = Vec::new();
-
-        debug!("Enumerating HID devices...");
-
-        // Iterate through all HID devices
-        for device_info in api.device_list() {
-            let vendor_id = device_info.vendor_id();
-            let product_id = device_info.product_id();
-
-            debug!("Checking HID device: VID={:04x} PID={:04x}", vendor_id, product_id);
-
-            // Check if this is a supported device
-            for &(supported_vid, supported_pid, device_type) in SUPPORTED_DEVICES {
-                if vendor_id == supported_vid && product_id == supported_pid {
-                    // Create a unique key for this physical device
-                    // Use serial number if available, otherwise use path
-                    let device_key = (
-                        vendor_id,
-                        product_id,
-                        device_info.serial_number()
-                            .map(|s| s.to_string())
-                            .unwrap_or_else(|| device_info.path().to_string_lossy().to_string()),
-                    );
-
-                    // Skip if we've already processed this device
-                    if processed_devices.contains(&device_key) {
-                        continue;
-                    }
-
-                    debug!("Found {} device: VID={:04x} PID={:04x} interface={}",
-                           device_type, vendor_id, product_id, device_info.interface_number());
-
-                    // Collect device info
-                    let info = HidDeviceInfo::from_hidapi(device_info);
-                    found.push(DiscoveredDeviceInfo {
-                        vendor_id,
-                        product_id,
-                        device_type,
-                        hid_info: info,
-                    });
-
-                    processed_devices.insert(device_key);
-                    break; // Found a match, no need to check other device types
-                }
-            }
+    let supported_ids = supported_device_ids();
+    let pedal_overrides = load_pedal_names();
+
+    let api = get_refreshed_hid_api()?;
+
+    let mut found = Vec::new();
+
+    debug!("Enumerating HID devices...");
+
+    // Iterate through all HID devices
+    for device_info in api.device_list() {
+        let vendor_id = device_info.vendor_id();
+        let product_id = device_info.product_id();
+
+        debug!("Checking HID device: VID={:04x} PID={:04x}", vendor_id, product_id);
+
+        // Check if this is a supported device
+        let Some((_, _, device_type)) = supported_ids
+            .iter()
+            .find(|&&(vid, pid, _)| vid == vendor_id && pid == product_id)
+        else {
+            continue;
+        };
+
+        // Create a unique key for this physical device
+        // Use serial number if available, otherwise use path
+        let device_key = (
+            vendor_id,
+            product_id,
+            device_info.serial_number()
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| device_info.path().to_string_lossy().to_string()),
+        );
+
+        // Skip if we've already processed this device
+        if !processed_devices.insert(device_key) {
+            continue;
         }
 
-        found
-    }; // HID API lock is released here
+        debug!("Found {} device: VID={:04x} PID={:04x} interface={} path={}",
+               device_type, vendor_id, product_id, device_info.interface_number(),
+               device_info.path().to_string_lossy());
 
-    // Now open devices without holding the HID API lock
-    for discovered in discovered_devices {
-        debug!("Opening {} device: VID={:04x} PID={:04x}",
-               discovered.device_type, discovered.vendor_id, discovered.product_id);
-
-        let device_result: Result<Arc<dyn PedalDevice + Send + Sync>> =
-            match (discovered.vendor_id, discovered.product_id) {
-                // PCsensor devices use HID protocol
-                (0x3553, 0xb001) | (0x0c45, 0x7403) | (0x0c45, 0x7404) |
-                (0x413d, 0x2107) | (0x5131, 0x2019) => {
-                    PCsensorDevice::new(discovered.hid_info, device_id)
-                        .map(|d| Arc::new(d) as Arc<dyn PedalDevice + Send + Sync>)
-                },
-                // iKKEGOL devices
-                (0x1a86, 0xe026) => {
-                    IkkegolDevice::new(discovered.hid_info, device_id)
-                        .map(|d| Arc::new(d) as Arc<dyn PedalDevice + Send + Sync>)
-                },
-                // Scythe devices - try iKKEGOL protocol
-                (0x0426, 0x3011) | (0x055a, 0x0998) => {
-                    IkkegolDevice::new(discovered.hid_info, device_id)
-                        .map(|d| Arc::new(d) as Arc<dyn PedalDevice + Send + Sync>)
-                },
-                _ => {
-                    IkkegolDevice::new(discovered.hid_info, device_id)
-                        .map(|d| Arc::new(d) as Arc<dyn PedalDevice + Send + Sync>)
-                }
-            };
-
-        match device_result {
+        let info = HidDeviceInfo::from_hidapi(device_info);
+        let pedal_override = info.serial_number.as_ref()
+            .and_then(|serial| pedal_overrides.iter().find(|o| &o.serial == serial))
+            .cloned();
+
+        found.push(DeviceHandle {
+            id: found.len(),
+            device_type: device_type.clone(),
+            info,
+            pedal_override,
+        });
+    }
+
+    Ok(found)
+}
+
+/// Outcome of opening every enumerated device
+pub struct DiscoveryReport {
+    /// Devices that opened, in ID order
+    pub devices: Vec<Arc<dyn PedalDevice + Send + Sync>>,
+    /// Devices that were enumerated but failed to open, with the reason
+    pub failed: Vec<(HidDeviceInfo, PedalError)>,
+}
+
+/// Discover all connected pedal devices
+///
+/// Devices that fail to open (busy, no permission, unresponsive) are
+/// skipped; use [`discover_devices_detailed`] to find out about them.
+pub fn discover_devices() -> Result<Vec<Arc<dyn PedalDevice + Send + Sync>>> {
+    Ok(discover_devices_detailed()?.devices)
+}
+
+/// Discover all connected pedal devices, keeping the ones that failed to open
+pub fn discover_devices_detailed() -> Result<DiscoveryReport> {
+    // Collect handles first; this releases the HID API lock, which avoids a
+    // deadlock when device constructors open devices
+    let handles = discover_devices_lazy()?;
+
+    let mut report = DiscoveryReport { devices: Vec::new(), failed: Vec::new() };
+    for handle in handles {
+        match handle.open() {
             Ok(pedal_device) => {
                 info!("Discovered {} device (ID: {})",
-                      pedal_device.model(), device_id);
-                devices.push(pedal_device);
-                device_id += 1;
+                      pedal_device.model(), handle.id());
+                report.devices.push(pedal_device);
             }
             Err(e) => {
                 debug!("Failed to initialize device: {}", e);
+                report.failed.push((handle.info, e));
             }
         }
     }
 
-    Ok(devices)
+    Ok(report)
+}
+
+/// Find a specific device by ID, opening only that device
+pub fn find_device_by_id(id: usize) -> Result<Option<Arc<dyn PedalDevice + Send + Sync>>> {
+    let handles = discover_devices_lazy()?;
+    handles
+        .into_iter()
+        .find(|handle| handle.id() == id)
+        .map(|handle| handle.open())
+        .transpose()
 }
 
-/// Find a specific device by ID
-pub fn find_device_by_id(id: usize) -> Result<Option<Arc<dyn crate::device::PedalDevice + Send + Sync>>> {
-    let devices = discover_devices()?;
-    Ok(devices.into_iter().find(|d| d.id() == id))
+/// Open the device at an explicit HID path, skipping discovery and deduplication
+///
+/// The device type is probed from the VID/PID the HID layer reports for the
+/// path. The device gets ID 0.
+pub fn open_by_path(path: &CStr) -> Result<Arc<dyn PedalDevice + Send + Sync>> {
+    // Release the HID API lock before opening, as in discover_devices
+    let info = {
+        let api = get_refreshed_hid_api()?;
+        let info = api
+            .device_list()
+            .find(|d| d.path() == path)
+            .map(HidDeviceInfo::from_hidapi);
+        info.ok_or_else(|| PedalError::Hid(format!("No HID device at path {}", path.to_string_lossy())))?
+    };
+
+    let device_type = supported_device_ids()
+        .into_iter()
+        .find(|&(vid, pid, _)| vid == info.vendor_id && pid == info.product_id)
+        .map(|(_, _, device_type)| device_type)
+        .ok_or_else(|| PedalError::UnsupportedDevice(format!(
+            "{:04x}:{:04x} at {}", info.vendor_id, info.product_id, path.to_string_lossy()
+        )))?;
+
+    let pedal_override = info.serial_number.as_ref()
+        .and_then(|serial| load_pedal_names().into_iter().find(|o| &o.serial == serial));
+
+    DeviceHandle { id: 0, device_type, info, pedal_override }.open()
 }
@@ -0,0 +1,152 @@
+//! Pedal press events and press-duration measurement
+//!
+//! None of the supported firmwares can bind two actions to one pedal, so a
+//! short/long press distinction has to be made on the host. Devices that can
+//! report pedal activity return [`PedalEvent`]s from
+//! [`PedalDevice::poll_event`](crate::device::PedalDevice::poll_event); a
+//! [`PressTracker`] pairs them into [`PedalPress`]es with their hold duration.
+//!
+//! This is scaffolding: no supported device reports events yet, since pedal
+//! presses arrive as key reports on the keyboard interface the OS holds. The
+//! types are therefore only reachable through this module, not re-exported
+//! from [`crate::device`] or the prelude.
+
+use std::time::{Duration, Instant};
+
+/// Pedal state change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PedalEventKind {
+    Pressed,
+    Released,
+}
+
+/// A pedal state change observed on the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PedalEvent {
+    /// 0-based pedal index
+    pub pedal_index: usize,
+    /// What happened
+    pub kind: PedalEventKind,
+    /// When the host received the event
+    pub timestamp: Instant,
+}
+
+impl PedalEvent {
+    /// Create an event timestamped now
+    pub fn now(pedal_index: usize, kind: PedalEventKind) -> Self {
+        Self {
+            pedal_index,
+            kind,
+            timestamp: Instant::now(),
+        }
+    }
+}
+
+/// Short or long press, relative to a hold threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressKind {
+    Short,
+    Long,
+}
+
+/// A completed press of one pedal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PedalPress {
+    /// 0-based pedal index
+    pub pedal_index: usize,
+    /// When the pedal went down
+    pub pressed_at: Instant,
+    /// How long the pedal was held
+    pub duration: Duration,
+}
+
+impl PedalPress {
+    /// Classify the press against a long-press threshold
+    pub fn kind(&self, long_press: Duration) -> PressKind {
+        if self.duration >= long_press {
+            PressKind::Long
+        } else {
+            PressKind::Short
+        }
+    }
+}
+
+/// Pairs press and release events into completed presses
+#[derive(Debug, Default)]
+pub struct PressTracker {
+    /// Press time of each pedal currently held down
+    held: Vec<Option<Instant>>,
+}
+
+impl PressTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed an event; returns the completed press when a held pedal is released
+    ///
+    /// A release without a matching press is ignored, as is a repeated press
+    /// (the first press time is kept).
+    pub fn record(&mut self, event: PedalEvent) -> Option<PedalPress> {
+        if self.held.len() <= event.pedal_index {
+            self.held.resize(event.pedal_index + 1, None);
+        }
+        let slot = &mut self.held[event.pedal_index];
+
+        match event.kind {
+            PedalEventKind::Pressed => {
+                slot.get_or_insert(event.timestamp);
+                None
+            }
+            PedalEventKind::Released => slot.take().map(|pressed_at| PedalPress {
+                pedal_index: event.pedal_index,
+                pressed_at,
+                duration: event.timestamp.saturating_duration_since(pressed_at),
+            }),
+        }
+    }
+
+    /// Check whether a pedal is currently held down
+    pub fn is_held(&self, pedal_index: usize) -> bool {
+        self.held.get(pedal_index).is_some_and(|slot| slot.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(pedal_index: usize, kind: PedalEventKind, at: Instant) -> PedalEvent {
+        PedalEvent { pedal_index, kind, timestamp: at }
+    }
+
+    #[test]
+    fn test_press_duration() {
+        let start = Instant::now();
+        let mut tracker = PressTracker::new();
+
+        assert_eq!(tracker.record(event(1, PedalEventKind::Pressed, start)), None);
+        assert!(tracker.is_held(1));
+
+        // Repeated press keeps the first press time
+        let later = start + Duration::from_millis(100);
+        assert_eq!(tracker.record(event(1, PedalEventKind::Pressed, later)), None);
+
+        let released = start + Duration::from_millis(800);
+        let press = tracker.record(event(1, PedalEventKind::Released, released)).unwrap();
+        assert_eq!(press.pedal_index, 1);
+        assert_eq!(press.pressed_at, start);
+        assert_eq!(press.duration, Duration::from_millis(800));
+        assert_eq!(press.kind(Duration::from_millis(500)), PressKind::Long);
+        assert_eq!(press.kind(Duration::from_secs(1)), PressKind::Short);
+        assert!(!tracker.is_held(1));
+    }
+
+    #[test]
+    fn test_unmatched_release_is_ignored() {
+        let mut tracker = PressTracker::new();
+        assert_eq!(tracker.record(PedalEvent::now(0, PedalEventKind::Released)), None);
+        assert!(!tracker.is_held(0));
+    }
+}
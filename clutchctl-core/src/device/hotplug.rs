@@ -0,0 +1,164 @@
+//! Notifications when supported devices are plugged in or removed
+//!
+//! hidapi has no hotplug callbacks, so [`watch_hotplug`] polls: a background
+//! thread re-enumerates the HID bus every [`POLL_INTERVAL`] and reports the
+//! difference. This works on every platform hidapi supports, at the cost of
+//! events arriving up to one interval late. A device that is unplugged and
+//! plugged back in within one interval produces no events.
+//!
+//! Only devices that discovery would return are reported, i.e. the built-in
+//! `SUPPORTED_DEVICES` plus any extra IDs in `devices.toml`.
+
+use crate::device::discover_devices_lazy;
+use crate::error::{PedalError, Result};
+use crate::usb::HidDeviceInfo;
+use log::{debug, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Time between two enumerations of the HID bus
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A supported device appeared or disappeared
+#[derive(Debug, Clone)]
+pub enum HotplugEvent {
+    /// Device was plugged in
+    Added(HidDeviceInfo),
+    /// Device was removed; the info is from when it was last seen
+    Removed(HidDeviceInfo),
+}
+
+/// Running hotplug watcher; dropping it stops the background thread
+pub struct HotplugWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HotplugWatcher {
+    /// Stop watching and wait for the background thread to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for HotplugWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Call `callback` from a background thread whenever a supported device is plugged in or removed
+///
+/// Devices present when watching starts are not reported. Fails if the
+/// initial enumeration fails; later enumeration errors are logged and the
+/// poll is retried.
+pub fn watch_hotplug(callback: impl Fn(HotplugEvent) + Send + 'static) -> Result<HotplugWatcher> {
+    let mut known = enumerate()?;
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let thread = {
+        let stop = Arc::clone(&stop);
+        std::thread::Builder::new()
+            .name("clutchctl-hotplug".to_string())
+            .spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(POLL_INTERVAL);
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    let current = match enumerate() {
+                        Ok(current) => current,
+                        Err(e) => {
+                            warn!("Hotplug enumeration failed: {}", e);
+                            continue;
+                        }
+                    };
+
+                    for event in diff(&known, &current) {
+                        debug!("Hotplug event: {:?}", event);
+                        callback(event);
+                    }
+                    known = current;
+                }
+            })
+            .map_err(|e| PedalError::Hid(format!("Failed to start hotplug thread: {}", e)))?
+    };
+
+    Ok(HotplugWatcher {
+        stop,
+        thread: Some(thread),
+    })
+}
+
+fn enumerate() -> Result<Vec<HidDeviceInfo>> {
+    Ok(discover_devices_lazy()?
+        .into_iter()
+        .map(|handle| handle.info().clone())
+        .collect())
+}
+
+/// Identity of a physical device, as used by discovery to skip duplicate interfaces
+fn device_key(info: &HidDeviceInfo) -> (u16, u16, String) {
+    let id = info
+        .serial_number
+        .clone()
+        .unwrap_or_else(|| info.path.to_string_lossy().into_owned());
+    (info.vendor_id, info.product_id, id)
+}
+
+/// Events turning `previous` into `current`: removals first, then additions
+fn diff(previous: &[HidDeviceInfo], current: &[HidDeviceInfo]) -> Vec<HotplugEvent> {
+    let removed = previous
+        .iter()
+        .filter(|info| !current.iter().any(|other| device_key(other) == device_key(info)))
+        .map(|info| HotplugEvent::Removed(info.clone()));
+    let added = current
+        .iter()
+        .filter(|info| !previous.iter().any(|other| device_key(other) == device_key(info)))
+        .map(|info| HotplugEvent::Added(info.clone()));
+
+    removed.chain(added).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    fn info(serial: &str) -> HidDeviceInfo {
+        HidDeviceInfo {
+            vendor_id: 0x1a86,
+            product_id: 0xe026,
+            path: CString::new(format!("path-{}", serial)).unwrap(),
+            serial_number: Some(serial.to_string()),
+            manufacturer: None,
+            product: None,
+            interface_number: 1,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let events = diff(&[info("a"), info("b")], &[info("b"), info("c")]);
+        let summary: Vec<(bool, Option<String>)> = events
+            .into_iter()
+            .map(|event| match event {
+                HotplugEvent::Added(info) => (true, info.serial_number),
+                HotplugEvent::Removed(info) => (false, info.serial_number),
+            })
+            .collect();
+        assert_eq!(summary, vec![(false, Some("a".to_string())), (true, Some("c".to_string()))]);
+
+        assert!(diff(&[info("a")], &[info("a")]).is_empty());
+    }
+}
@@ -1,16 +1,18 @@
 //! iKKEGOL USB pedal device implementation
 
-use crate::configuration::{Configuration, Trigger};
-use crate::device::{DeviceCapabilities, PedalDevice};
-use crate::error::{PedalError, Result};
-use crate::protocol::{self, ConfigPacket, TriggerMode};
-use crate::usb::{open_device_path, HidDeviceInfo};
+use crate::configuration::text::MAX_TEXT_BYTES;
+use crate::configuration::{Configuration, ConfigurationType, Trigger};
+use crate::device::{DeviceCapabilities, DeviceOptions, PedalDevice};
+use crate::error::{PedalError, PedalOperationKind, Result};
+use crate::protocol::{self, Command, ConfigPacket, TriggerMode};
+use crate::usb::{check_read, open_device_path, DeviceLock, HidDeviceInfo};
 use hidapi::HidDevice;
-use log::debug;
+use log::{debug, info, warn};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 /// USB pedal device models
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum IkkegolModel {
     FS2020U1IR,     // iKKEGOL 3 pedals
     FS2017U1IR,     // iKKEGOL 1 pedal
@@ -34,6 +36,30 @@ impl IkkegolModel {
         }
     }
 
+    /// Parse the model string reported by a Scythe foot switch
+    ///
+    /// Scythe units report names like "USB Foot Switch" or "USB Foot Switch II";
+    /// some rebadged iKKEGOL boards report an iKKEGOL model instead. Anything
+    /// unrecognized keeps the model implied by the USB ID.
+    fn from_scythe_str(s: &str, default: Self) -> Self {
+        let upper = s.trim().to_uppercase();
+
+        if upper.contains("FS2020U1IR") || upper.contains("FS2017U1IR") {
+            return Self::from_str(&upper);
+        }
+
+        let compact: String = upper.chars().filter(|c| !c.is_whitespace()).collect();
+        if !(compact.contains("SCYTHE") || compact.contains("FOOTSWITCH")) {
+            return default;
+        }
+
+        if compact.contains("SWITCHII") || compact.ends_with("SWITCH2") {
+            Self::Scythe2
+        } else {
+            Self::Scythe
+        }
+    }
+
     /// Get device capabilities
     fn capabilities(&self) -> DeviceCapabilities {
         match self {
@@ -45,11 +71,21 @@ impl IkkegolModel {
                     "middle".to_string(),
                     "right".to_string(),
                 ],
+                supported_triggers: vec![Trigger::OnPress, Trigger::OnRelease],
+                supported_config_types: ConfigurationType::all().to_vec(),
+                supports_mouse_axis: true,
+                max_text_length: MAX_TEXT_BYTES,
+                physical_order: vec![0, 1, 2],
             },
             Self::FS2017U1IR | Self::FootSwitch1P => DeviceCapabilities {
                 pedal_count: 1,
                 first_pedal_index: 1, // Note: This model uses index 1, not 0
                 pedal_names: vec!["pedal".to_string()],
+                supported_triggers: vec![Trigger::OnPress, Trigger::OnRelease],
+                supported_config_types: ConfigurationType::all().to_vec(),
+                supports_mouse_axis: true,
+                max_text_length: MAX_TEXT_BYTES,
+                physical_order: vec![0],
             },
             Self::Unknown(_) => DeviceCapabilities {
                 // Default to 3 pedals for unknown models (likely compatible devices)
@@ -60,63 +96,303 @@ impl IkkegolModel {
                     "middle".to_string(),
                     "right".to_string(),
                 ],
+                supported_triggers: vec![Trigger::OnPress, Trigger::OnRelease],
+                supported_config_types: ConfigurationType::all().to_vec(),
+                supports_mouse_axis: true,
+                max_text_length: MAX_TEXT_BYTES,
+                physical_order: vec![0, 1, 2],
             },
         }
     }
 }
 
+/// Command that begins every write session
+const BEGIN_WRITE: [u8; 8] = Command::BeginWrite { arg: 1 }.to_bytes();
+
+/// Longest model string reply, in bytes
+const MODEL_RESPONSE_MAX: usize = 32;
+
+/// Total time allowed for the model string to arrive
+const MODEL_READ_DEADLINE: Duration = Duration::from_secs(1);
+
+/// Timeout of each read while assembling the model string, in milliseconds
+const MODEL_READ_TIMEOUT_MS: i32 = 100;
+
+/// Empty reads in a row that end a model string reply once it has started
+const MODEL_IDLE_READS: usize = 3;
+
+/// Collect the model string reply from 8-byte chunks
+///
+/// Some units pause between chunks, so a single empty read doesn't end the
+/// reply; [`MODEL_IDLE_READS`] in a row after the first chunk do, and so does
+/// `deadline` passing. A chunk that is short or contains the NUL terminator
+/// ends it, as does a read error or [`MODEL_RESPONSE_MAX`] bytes.
+fn assemble_model_response(
+    mut read: impl FnMut(&mut [u8; 8], i32) -> Result<usize>,
+    deadline: Duration,
+) -> Vec<u8> {
+    let start = Instant::now();
+    let mut response = Vec::new();
+    let mut idle_reads = 0;
+
+    while response.len() < MODEL_RESPONSE_MAX && start.elapsed() < deadline {
+        let mut buf = [0u8; 8];
+        match read(&mut buf, MODEL_READ_TIMEOUT_MS) {
+            Ok(0) if response.is_empty() => continue,
+            Ok(0) => {
+                idle_reads += 1;
+                if idle_reads == MODEL_IDLE_READS {
+                    break;
+                }
+            }
+            Ok(n) => {
+                idle_reads = 0;
+                response.extend_from_slice(&buf[..n]);
+                if n < buf.len() || buf[..n].contains(&0) {
+                    break;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+
+    response
+}
+
+/// Split a model string reply such as `FS2020U1IR_V3.4` into model and version
+///
+/// Bytes that aren't printable ASCII (NULs, padding, noise) are dropped and
+/// the reply is capped at [`MODEL_RESPONSE_MAX`]. The text after the last
+/// underscore is the version only if it starts with `V` or a digit, so model
+/// names with underscores of their own stay whole; the version is then
+/// "unknown".
+fn parse_model_response(response: &[u8]) -> (String, String) {
+    let text: String = response.iter()
+        .take(MODEL_RESPONSE_MAX)
+        .filter(|byte| byte.is_ascii_graphic() || **byte == b' ')
+        .map(|&byte| byte as char)
+        .collect();
+    let text = text.trim();
+
+    if let Some((model, version)) = text.rsplit_once('_') {
+        let looks_like_version = version.starts_with(|c: char| c == 'V' || c == 'v' || c.is_ascii_digit());
+        if looks_like_version && !model.is_empty() {
+            return (model.to_string(), version.to_string());
+        }
+    }
+
+    (text.to_string(), "unknown".to_string())
+}
+
+/// Upper bound on the pedal count probed for unknown models
+const MAX_PROBED_PEDALS: usize = 8;
+
+/// Check whether a config reply looks like a real pedal slot
+///
+/// Slots past the last pedal either time out or echo garbage; a size beyond
+/// the packet marks the reply as not a pedal. An unknown config type doesn't:
+/// such pedals are kept as `Configuration::Raw`.
+fn is_plausible_config(packet: &ConfigPacket) -> bool {
+    packet.size as usize <= ConfigPacket::PACKET_SIZE
+}
+
+/// Count pedals by reading slots from 0 upwards with `read_slot`
+///
+/// The first error (usually a timeout) or implausible reply ends the count.
+fn count_pedals(mut read_slot: impl FnMut(usize) -> Result<[u8; 40]>) -> usize {
+    let mut count = 0;
+    while count < MAX_PROBED_PEDALS {
+        match read_slot(count) {
+            Ok(bytes) if is_plausible_config(&ConfigPacket::from_bytes(&bytes)) => count += 1,
+            Ok(bytes) => {
+                debug!("Probe of slot {} (wire 0x{:02x}) returned an invalid config packet: {:02x?}",
+                       count, count + 1, &bytes[..8]);
+                break;
+            }
+            Err(e) => {
+                debug!("Probe of slot {} (wire 0x{:02x}) got no answer: {}", count, count + 1, e);
+                break;
+            }
+        }
+    }
+    count
+}
+
+/// Pad a possibly short config reply to a full packet
+///
+/// At least the size and type bytes must be present.
+fn packet_from_response(response: &[u8]) -> Result<[u8; 40]> {
+    if response.len() < ConfigPacket::DATA_OFFSET {
+        return Err(PedalError::Protocol(format!(
+            "Config response too short: {} byte(s)",
+            response.len()
+        )));
+    }
+
+    let mut packet_bytes = [0u8; ConfigPacket::PACKET_SIZE];
+    let len = response.len().min(ConfigPacket::PACKET_SIZE);
+    packet_bytes[..len].copy_from_slice(&response[..len]);
+    Ok(packet_bytes)
+}
+
+/// Most pedals requested before their replies are read
+///
+/// Each reply is five input reports and hidapi's libusb backend queues at
+/// most 30, so larger batches could drop replies.
+const PIPELINE_BATCH: usize = 4;
+
+/// Split the replies to `count` back-to-back read commands into packets
+///
+/// Replies carry no pedal index, so they are matched to the commands by
+/// order. That is only safe when exactly `count` full packets arrive and each
+/// looks like a config, so anything else is an error and the caller reads the
+/// pedals one at a time instead.
+fn collect_pipelined_responses(
+    mut read: impl FnMut(&mut [u8; 8], i32) -> Result<usize>,
+    count: usize,
+    timeout_ms: i32,
+) -> Result<Vec<[u8; 40]>> {
+    let expected = count * ConfigPacket::PACKET_SIZE;
+    let mut stream = Vec::with_capacity(expected);
+
+    while stream.len() < expected {
+        let mut buf = [0u8; 8];
+        match read(&mut buf, timeout_ms)? {
+            0 => {
+                return Err(PedalError::Protocol(format!(
+                    "Pipelined read got {} of {} bytes",
+                    stream.len(),
+                    expected
+                )))
+            }
+            n => stream.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    if stream.len() != expected {
+        return Err(PedalError::Protocol(format!(
+            "Pipelined read got {} bytes, expected {}",
+            stream.len(),
+            expected
+        )));
+    }
+
+    stream
+        .chunks_exact(ConfigPacket::PACKET_SIZE)
+        .map(|chunk| {
+            let bytes = packet_from_response(chunk)?;
+            // Stricter than probing: a shifted stream rarely starts a chunk with
+            // a known type, and a pedal of an unknown type is just read again alone
+            let packet = ConfigPacket::from_bytes(&bytes);
+            if !is_plausible_config(&packet) || packet.get_config_type().is_none() {
+                return Err(PedalError::Protocol(format!(
+                    "Pipelined reply is not a config packet: {:02x?}",
+                    &bytes[..8]
+                )));
+            }
+            Ok(bytes)
+        })
+        .collect()
+}
+
+/// Display names for a probed pedal count, matching the known models
+fn probed_pedal_names(count: usize) -> Vec<String> {
+    match count {
+        1 => vec!["pedal".to_string()],
+        2 => vec!["left".to_string(), "right".to_string()],
+        3 => vec!["left".to_string(), "middle".to_string(), "right".to_string()],
+        _ => (1..=count).map(|i| format!("pedal{}", i)).collect(),
+    }
+}
+
 /// iKKEGOL pedal device
 pub struct IkkegolDevice {
     device: Mutex<HidDevice>,
+    /// Cross-process lock, held from open until the device is dropped so no
+    /// other process can write between a load and a save
+    _lock: DeviceLock,
     id: usize,
+    /// USB vendor and product ID
+    usb_ids: (u16, u16),
     model: IkkegolModel,
     version: String,
     capabilities: DeviceCapabilities,
     configurations: Mutex<Vec<Configuration>>,
     trigger_modes: Mutex<Vec<TriggerMode>>,
     modified_pedals: Mutex<Vec<bool>>,
+    /// Bytes of each pedal's configuration as last read
+    raw_configs: Mutex<Vec<[u8; 40]>>,
 }
 
 impl IkkegolDevice {
     /// Create a new iKKEGOL device
     pub fn new(info: HidDeviceInfo, id: usize) -> Result<Self> {
+        Self::with_options(info, id, &DeviceOptions::default())
+    }
+
+    /// Create a new iKKEGOL device with custom options
+    pub fn with_options(info: HidDeviceInfo, id: usize, options: &DeviceOptions) -> Result<Self> {
         let vendor_id = info.vendor_id;
         let product_id = info.product_id;
 
         debug!("Opening iKKEGOL device {:04x}:{:04x} at path {:?}",
                vendor_id, product_id, info.path);
 
+        // See `DeviceLock::acquire`
+        let lock = DeviceLock::acquire(&info.device_key())?;
+
         // Open the device by path
         let device = open_device_path(&info.path)?;
 
         // Set non-blocking mode for reads with timeout
         device.set_blocking_mode(false)?;
 
+        // Read the model string once; it names the model and version (may not work for all models)
+        let model_reply = Self::read_model_and_version_static(&device).ok();
+
         // Determine model based on USB ID
         let model = match (vendor_id, product_id) {
             (0x0c45, 0x7403) | (0x0c45, 0x7404) | (0x413d, 0x2107) | (0x3553, 0xb001) => IkkegolModel::PCsensor,
-            (0x0426, 0x3011) => IkkegolModel::Scythe,
-            (0x055a, 0x0998) => IkkegolModel::Scythe2,
+            (0x0426, 0x3011) | (0x055a, 0x0998) => {
+                let default = if product_id == 0x3011 { IkkegolModel::Scythe } else { IkkegolModel::Scythe2 };
+                match &model_reply {
+                    Some((model_str, _)) => IkkegolModel::from_scythe_str(model_str, default),
+                    None => default,
+                }
+            },
             (0x5131, 0x2019) => IkkegolModel::FootSwitch1P,
             (0x1a86, 0xe026) => {
-                // For iKKEGOL devices, try to read the model from the device
-                if let Ok((model_str, _)) = Self::read_model_and_version_static(&device) {
-                    IkkegolModel::from_str(&model_str)
-                } else {
-                    IkkegolModel::FS2020U1IR // Default to 3-pedal model
+                // For iKKEGOL devices, use the model the device reports
+                match &model_reply {
+                    Some((model_str, _)) => IkkegolModel::from_str(model_str),
+                    None => IkkegolModel::FS2020U1IR, // Default to 3-pedal model
                 }
             },
             _ => IkkegolModel::Unknown(format!("{:04x}:{:04x}", vendor_id, product_id)),
         };
 
-        // Try to read version from device (may not work for all models)
-        let version = if let Ok((_, ver)) = Self::read_model_and_version_static(&device) {
-            ver
-        } else {
-            "unknown".to_string()
-        };
+        let version = model_reply.map_or_else(|| "unknown".to_string(), |(_, version)| version);
+
+        let mut capabilities = model.capabilities();
+
+        // Unknown models may have any number of pedals; ask the device
+        if let IkkegolModel::Unknown(usb_id) = &model {
+            let detected = Self::probe_pedal_count(&device, 100);
+            if detected > 0 {
+                info!("Detected {} pedal(s) on unknown model {}", detected, usb_id);
+                capabilities.pedal_count = detected;
+                capabilities.pedal_names = probed_pedal_names(detected);
+                capabilities.physical_order = (0..detected).collect();
+            } else {
+                warn!("Could not detect the pedal count of unknown model {}, assuming {}",
+                      usb_id, capabilities.pedal_count);
+            }
+        }
 
-        let capabilities = model.capabilities();
+        let capabilities = capabilities
+            .with_names(&options.pedal_names)
+            .with_physical_order(&options.physical_order);
+        capabilities.check_pedal_count(&format!("{:04x}:{:04x}", vendor_id, product_id))?;
 
         // Initialize configuration storage
         let pedal_count = capabilities.pedal_count;
@@ -126,16 +402,24 @@ impl IkkegolDevice {
 
         Ok(Self {
             device: Mutex::new(device),
+            _lock: lock,
             id,
+            usb_ids: (vendor_id, product_id),
             model,
             version,
             capabilities,
             configurations: Mutex::new(configurations),
             trigger_modes: Mutex::new(trigger_modes),
             modified_pedals: Mutex::new(modified_pedals),
+            raw_configs: Mutex::new(vec![[0; 40]; pedal_count]),
         })
     }
 
+    /// Hardware model detected when the device was opened
+    pub fn hardware_model(&self) -> &IkkegolModel {
+        &self.model
+    }
+
     /// Write data to the device (8-byte chunks)
     fn hid_write(device: &HidDevice, data: &[u8]) -> Result<()> {
         // hidapi requires a report ID as the first byte
@@ -148,58 +432,63 @@ impl IkkegolDevice {
         Ok(())
     }
 
-    /// Read data from the device (8 bytes)
-    fn hid_read(device: &HidDevice, timeout_ms: i32) -> Result<[u8; 8]> {
+    /// Read data from the device (up to 8 bytes)
+    fn hid_read(device: &HidDevice, timeout_ms: i32) -> Result<Vec<u8>> {
         let mut buffer = [0u8; 8];
 
-        // hidapi read returns the number of bytes read
-        let bytes_read = device.read_timeout(&mut buffer, timeout_ms)?;
-
-        if bytes_read == 0 {
-            return Err(PedalError::Timeout);
-        }
+        let bytes_read = check_read(device.read_timeout(&mut buffer, timeout_ms))?;
 
         debug!("Read {} bytes: {:02x?}", bytes_read, &buffer[..bytes_read]);
-        Ok(buffer)
+        Ok(buffer[..bytes_read].to_vec())
     }
 
     /// Read model and version from device (static version for use during construction)
     fn read_model_and_version_static(device: &HidDevice) -> Result<(String, String)> {
         // Send read model command
-        let cmd = protocol::commands::READ_MODEL;
+        let cmd = Command::ReadModel.to_bytes();
 
         // Write with report ID prefix
         let mut buffer = vec![0x00];
         buffer.extend_from_slice(&cmd);
         device.write(&buffer)?;
 
-        // Read response (up to 32 bytes in 8-byte chunks)
-        let mut response = Vec::new();
-        for _ in 0..4 {
-            let mut buf = [0u8; 8];
-            match device.read_timeout(&mut buf, 500) {
-                Ok(n) if n > 0 => {
-                    response.extend_from_slice(&buf[..n]);
-                    if n < 8 {
-                        break;
-                    }
-                }
-                _ => break,
+        let response = assemble_model_response(
+            |buf, timeout_ms| Ok(device.read_timeout(buf, timeout_ms)?),
+            MODEL_READ_DEADLINE,
+        );
+
+        Ok(parse_model_response(&response))
+    }
+
+    /// Send a read-config command for a protocol slot and collect the 40-byte reply
+    ///
+    /// No response at all is a `PedalError::Timeout`. A reply that stops early
+    /// is padded with zeros, so an unconfigured pedal answering only its size
+    /// and type bytes reads as unconfigured.
+    fn read_packet_static(device: &HidDevice, protocol_index: usize, timeout_ms: i32) -> Result<[u8; 40]> {
+        let cmd = Command::ReadConfig { pedal: protocol_index as u8 }.to_bytes();
+        Self::hid_write(device, &cmd)?;
+
+        // Read response (40 bytes in chunks of up to 8)
+        let mut response = Vec::with_capacity(ConfigPacket::PACKET_SIZE);
+
+        while response.len() < ConfigPacket::PACKET_SIZE {
+            match Self::hid_read(device, timeout_ms) {
+                Ok(buffer) => response.extend_from_slice(&buffer),
+                Err(PedalError::Timeout) if !response.is_empty() => break,
+                Err(e) => return Err(e),
             }
         }
 
-        // Parse the response
-        let response_str = String::from_utf8_lossy(&response);
-        let response_str = response_str.trim_end_matches('\0');
+        packet_from_response(&response)
+    }
 
-        // Split on underscore to get model and version
-        if let Some(underscore_pos) = response_str.rfind('_') {
-            let model = response_str[..underscore_pos].to_string();
-            let version = response_str[underscore_pos + 1..].to_string();
-            Ok((model, version))
-        } else {
-            Ok((response_str.to_string(), "unknown".to_string()))
-        }
+    /// Count the pedals of an unknown model by reading slots until one fails
+    ///
+    /// Slots are read from 0 upwards; the first timeout or implausible reply
+    /// ends the probe. Returns 0 when not even slot 0 answers.
+    fn probe_pedal_count(device: &HidDevice, timeout_ms: i32) -> usize {
+        count_pedals(|slot| Self::read_packet_static(device, slot, timeout_ms))
     }
 
     /// Get timeout based on model
@@ -210,6 +499,79 @@ impl IkkegolDevice {
         }
     }
 
+    /// Read every pedal, sending the read commands of a batch before draining replies
+    ///
+    /// Falls back to [`read_pedal_config`](Self::read_pedal_config) for the
+    /// pedals of a batch whose replies can't be matched to their commands;
+    /// `PedalError::DeviceDisconnected` ends the read instead.
+    fn read_all_pedal_configs(&self) -> Result<()> {
+        let timeout_ms = self.get_timeout_ms();
+        let pedal_indices: Vec<usize> = (0..self.capabilities.pedal_count).collect();
+
+        for batch in pedal_indices.chunks(PIPELINE_BATCH) {
+            let pipelined = {
+                let device = self.device.lock()
+                    .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
+                let result = self.read_batch_pipelined(&device, batch, timeout_ms);
+                if result.is_err() && !matches!(result, Err(PedalError::DeviceDisconnected)) {
+                    // Stale replies would be taken for the next pedal's
+                    Self::drain_input(&device, timeout_ms);
+                }
+                result
+            };
+
+            match pipelined {
+                Ok(packets) => {
+                    for (&pedal_index, bytes) in batch.iter().zip(packets) {
+                        self.store_packet(pedal_index, bytes)
+                            .map_err(|e| e.for_pedal(pedal_index, PedalOperationKind::Read))?;
+                    }
+                }
+                // Reading one at a time can't help an unplugged device
+                Err(e @ PedalError::DeviceDisconnected) => return Err(e),
+                Err(e) => {
+                    let labels: Vec<String> = batch.iter()
+                        .map(|&i| self.capabilities.pedal_log_label(i))
+                        .collect();
+                    debug!("Pipelined read of {} failed ({}), reading them one at a time", labels.join(", "), e);
+                    for &pedal_index in batch {
+                        self.read_pedal_config(pedal_index)
+                            .map_err(|e| e.for_pedal(pedal_index, PedalOperationKind::Read))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send the read commands of `batch`, then collect all replies
+    fn read_batch_pipelined(&self, device: &HidDevice, batch: &[usize], timeout_ms: i32) -> Result<Vec<[u8; 40]>> {
+        for &pedal_index in batch {
+            let protocol_index = self.capabilities.get_protocol_index(pedal_index)
+                .ok_or(PedalError::InvalidPedalIndex(pedal_index, self.capabilities.pedal_count))?;
+            debug!("Requesting {} (pipelined)", self.capabilities.pedal_log_label(pedal_index));
+            Self::hid_write(device, &Command::ReadConfig { pedal: protocol_index as u8 }.to_bytes())?;
+        }
+
+        collect_pipelined_responses(
+            |buf, timeout_ms| Ok(device.read_timeout(buf, timeout_ms)?),
+            batch.len(),
+            timeout_ms,
+        )
+    }
+
+    /// Discard queued input reports until a read times out
+    fn drain_input(device: &HidDevice, timeout_ms: i32) {
+        let mut buffer = [0u8; 8];
+        while let Ok(n) = device.read_timeout(&mut buffer, timeout_ms) {
+            if n == 0 {
+                break;
+            }
+            debug!("Drained HID report: {:02x?}", &buffer[..n]);
+        }
+    }
+
     /// Read configuration for a specific pedal
     fn read_pedal_config(&self, pedal_index: usize) -> Result<()> {
         if pedal_index >= self.capabilities.pedal_count {
@@ -220,40 +582,30 @@ impl IkkegolDevice {
         }
 
         let protocol_index = self.capabilities.get_protocol_index(pedal_index)
-            .ok_or_else(|| PedalError::InvalidPedalIndex(pedal_index, self.capabilities.pedal_count))?;
+            .ok_or(PedalError::InvalidPedalIndex(pedal_index, self.capabilities.pedal_count))?;
 
         let device = self.device.lock()
             .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
 
-        // Send read config command
-        let cmd = protocol::commands::read_config(protocol_index as u8);
-        let timeout_ms = self.get_timeout_ms();
-
-        Self::hid_write(&device, &cmd)?;
-
-        // Read response (40 bytes in 8-byte chunks)
-        let mut packet_bytes = [0u8; 40];
-        let mut offset = 0;
-
-        while offset < 40 {
-            match Self::hid_read(&device, timeout_ms) {
-                Ok(buffer) => {
-                    let copy_len = std::cmp::min(8, 40 - offset);
-                    packet_bytes[offset..offset + copy_len].copy_from_slice(&buffer[..copy_len]);
-                    offset += copy_len;
-                }
-                Err(PedalError::Timeout) if offset > 0 => break,
-                Err(e) => return Err(e),
-            }
-        }
+        debug!("Reading {}", self.capabilities.pedal_log_label(pedal_index));
+        let packet_bytes = Self::read_packet_static(&device, protocol_index, self.get_timeout_ms())?;
 
         // Drop device lock before locking configurations
         drop(device);
 
-        // Parse the packet
+        self.store_packet(pedal_index, packet_bytes)
+    }
+
+    /// Parse a pedal's config reply and keep it with its raw bytes
+    fn store_packet(&self, pedal_index: usize, packet_bytes: [u8; 40]) -> Result<()> {
         let packet = ConfigPacket::from_bytes(&packet_bytes);
         let config = protocol::ikkegol::parse_config(&packet)?;
 
+        let mut raw_configs = self.raw_configs.lock()
+            .map_err(|_| PedalError::Hid("Failed to lock raw configurations".to_string()))?;
+        raw_configs[pedal_index] = packet_bytes;
+        drop(raw_configs);
+
         let mut configurations = self.configurations.lock()
             .map_err(|_| PedalError::Hid("Failed to lock configurations".to_string()))?;
         configurations[pedal_index] = config;
@@ -261,13 +613,20 @@ impl IkkegolDevice {
         Ok(())
     }
 
+    /// Write-config header for a 0-based pedal index, addressed by protocol slot
+    fn write_header(capabilities: &DeviceCapabilities, pedal_index: usize, size: u8) -> Result<[u8; 8]> {
+        let protocol_index = capabilities.get_protocol_index(pedal_index)
+            .ok_or(PedalError::InvalidPedalIndex(pedal_index, capabilities.pedal_count))?;
+        Ok(Command::WriteHeader { size, pedal: protocol_index as u8 }.to_bytes())
+    }
+
     /// Read trigger modes for all pedals
     fn read_trigger_modes(&self) -> Result<()> {
         let device = self.device.lock()
             .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
 
         // Send read trigger modes command
-        let cmd = protocol::commands::READ_TRIGGER_MODES;
+        let cmd = Command::ReadTriggerModes.to_bytes();
         let timeout_ms = self.get_timeout_ms();
 
         Self::hid_write(&device, &cmd)?;
@@ -282,10 +641,10 @@ impl IkkegolDevice {
         let mut trigger_modes = self.trigger_modes.lock()
             .map_err(|_| PedalError::Hid("Failed to lock trigger modes".to_string()))?;
 
-        for i in 0..self.capabilities.pedal_count {
-            if i < 8 {
-                trigger_modes[i] = TriggerMode::from_u8(buffer[i])
-                    .unwrap_or(TriggerMode::Press);
+        // The table is indexed by protocol slot, like the configurations
+        for (i, mode) in trigger_modes.iter_mut().enumerate() {
+            if let Some(&entry) = self.capabilities.get_protocol_index(i).and_then(|slot| buffer.get(slot)) {
+                *mode = TriggerMode::from_u8(entry).unwrap_or(TriggerMode::Press);
             }
         }
 
@@ -301,9 +660,6 @@ impl IkkegolDevice {
             ));
         }
 
-        let protocol_index = self.capabilities.get_protocol_index(pedal_index)
-            .ok_or_else(|| PedalError::InvalidPedalIndex(pedal_index, self.capabilities.pedal_count))?;
-
         // Get configuration first
         let config = {
             let configurations = self.configurations.lock()
@@ -314,22 +670,55 @@ impl IkkegolDevice {
         let device = self.device.lock()
             .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
 
-        // Begin write session
-        Self::hid_write(&device, &protocol::commands::BEGIN_WRITE)?;
+        debug!("Writing {}: {}", self.capabilities.pedal_log_label(pedal_index), config);
 
-        // Encode configuration
-        let packet = protocol::ikkegol::encode_config(&config)?;
-        let packet_bytes = packet.to_bytes();
+        for report in Self::pedal_reports(&self.capabilities, pedal_index, &config)? {
+            Self::hid_write(&device, &report)?;
+        }
 
-        // Send write config header
-        let cmd = protocol::commands::write_config_header(packet.size, protocol_index as u8);
-        Self::hid_write(&device, &cmd)?;
+        Ok(())
+    }
+
+    /// HID reports that write one pedal: start command, pedal header, then the
+    /// config packet in 8-byte chunks
+    fn pedal_reports(capabilities: &DeviceCapabilities, pedal_index: usize, config: &Configuration) -> Result<Vec<[u8; 8]>> {
+        let packet = protocol::ikkegol::encode_config(config)?;
+        let mut reports = vec![
+            BEGIN_WRITE,
+            Self::write_header(capabilities, pedal_index, packet.size)?,
+        ];
 
-        // Write packet data in 8-byte chunks
-        for chunk in packet_bytes.chunks(8) {
+        for chunk in packet.to_bytes().chunks(8) {
             let mut buffer = [0u8; 8];
             buffer[..chunk.len()].copy_from_slice(chunk);
-            Self::hid_write(&device, &buffer)?;
+            reports.push(buffer);
+        }
+
+        Ok(reports)
+    }
+
+    /// Write the trigger-mode table from the current configurations
+    fn write_trigger_modes(&self) -> Result<()> {
+        let table = {
+            let configurations = self.configurations.lock()
+                .map_err(|_| PedalError::Hid("Failed to lock configurations".to_string()))?;
+            protocol::ikkegol::encode_trigger_modes(&configurations, self.capabilities.first_pedal_index)
+        };
+
+        let device = self.device.lock()
+            .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
+
+        Self::hid_write(&device, &BEGIN_WRITE)?;
+        let cmd = Command::WriteTriggerModes { payload_size: table.len() as u8 }.to_bytes();
+        Self::hid_write(&device, &cmd)?;
+        Self::hid_write(&device, &table)?;
+
+        drop(device);
+
+        let mut trigger_modes = self.trigger_modes.lock()
+            .map_err(|_| PedalError::Hid("Failed to lock trigger modes".to_string()))?;
+        for (mode, &entry) in trigger_modes.iter_mut().zip(table.iter().skip(self.capabilities.first_pedal_index)) {
+            *mode = protocol::ikkegol::decode_trigger_mode(entry).into();
         }
 
         Ok(())
@@ -341,6 +730,10 @@ impl PedalDevice for IkkegolDevice {
         self.id
     }
 
+    fn usb_ids(&self) -> (u16, u16) {
+        self.usb_ids
+    }
+
     fn model(&self) -> &str {
         match &self.model {
             IkkegolModel::FS2020U1IR => "FS2020U1IR",
@@ -365,9 +758,7 @@ impl PedalDevice for IkkegolDevice {
         debug!("Loading configuration for device {}", self.id);
 
         // Read configurations for all pedals
-        for i in 0..self.capabilities.pedal_count {
-            self.read_pedal_config(i)?;
-        }
+        self.read_all_pedal_configs()?;
 
         // Read trigger modes
         self.read_trigger_modes()?;
@@ -407,11 +798,19 @@ impl PedalDevice for IkkegolDevice {
                 .collect()
         };
 
+        if modified_indices.is_empty() {
+            return Ok(());
+        }
+
         // Write modified pedal configurations
         for i in modified_indices {
-            self.write_pedal_config(i)?;
+            self.write_pedal_config(i)
+                .map_err(|e| e.for_pedal(i, PedalOperationKind::Write))?;
         }
 
+        // The trigger of every pedal lives in a separate table
+        self.write_trigger_modes()?;
+
         // Clear modification flags
         {
             let mut modified_pedals = self.modified_pedals.lock()
@@ -422,6 +821,24 @@ impl PedalDevice for IkkegolDevice {
         Ok(())
     }
 
+    fn reset_triggers(&mut self) -> Result<()> {
+        debug!("Resetting triggers for device {}", self.id);
+
+        // The pedal packets carry no trigger, so only the table is written
+        {
+            let mut configurations = self.configurations.lock()
+                .map_err(|_| PedalError::Hid("Failed to lock configurations".to_string()))?;
+            for config in configurations.iter_mut() {
+                config.set_trigger(Trigger::OnPress);
+            }
+            let mut trigger_modes = self.trigger_modes.lock()
+                .map_err(|_| PedalError::Hid("Failed to lock trigger modes".to_string()))?;
+            trigger_modes.fill(TriggerMode::Press);
+        }
+
+        self.write_trigger_modes()
+    }
+
     fn get_pedal_configuration(&self, pedal_index: usize) -> Result<Configuration> {
         if pedal_index >= self.capabilities.pedal_count {
             return Err(PedalError::InvalidPedalIndex(
@@ -435,6 +852,14 @@ impl PedalDevice for IkkegolDevice {
         Ok(configurations[pedal_index].clone())
     }
 
+    fn get_pedal_trigger(&self, pedal_index: usize) -> Result<Trigger> {
+        let trigger_modes = self.trigger_modes.lock()
+            .map_err(|_| PedalError::Hid("Failed to lock trigger modes".to_string()))?;
+        trigger_modes.get(pedal_index)
+            .map(|&mode| Trigger::from(mode))
+            .ok_or(PedalError::InvalidPedalIndex(pedal_index, self.capabilities.pedal_count))
+    }
+
     fn set_pedal_configuration(&mut self, pedal_index: usize, config: Configuration) -> Result<()> {
         if pedal_index >= self.capabilities.pedal_count {
             return Err(PedalError::InvalidPedalIndex(
@@ -442,10 +867,17 @@ impl PedalDevice for IkkegolDevice {
                 self.capabilities.pedal_count,
             ));
         }
+        if let Configuration::Text(text) = &config {
+            self.capabilities.validate_text(text)?;
+        }
 
         {
             let mut configurations = self.configurations.lock()
                 .map_err(|_| PedalError::Hid("Failed to lock configurations".to_string()))?;
+            // Unchanged pedals stay unmodified, see `PedalDevice::set_pedal_configuration`
+            if configurations[pedal_index] == config {
+                return Ok(());
+            }
             configurations[pedal_index] = config;
         }
 
@@ -458,6 +890,22 @@ impl PedalDevice for IkkegolDevice {
         Ok(())
     }
 
+    fn get_pedal_raw(&self, pedal_index: usize) -> Result<[u8; 40]> {
+        let raw_configs = self.raw_configs.lock()
+            .map_err(|_| PedalError::Hid("Failed to lock raw configurations".to_string()))?;
+        raw_configs.get(pedal_index)
+            .copied()
+            .ok_or(PedalError::InvalidPedalIndex(pedal_index, self.capabilities.pedal_count))
+    }
+
+    fn parse_pedal_raw(&self, raw: &[u8; 40]) -> Result<Configuration> {
+        protocol::ikkegol::parse_config(&ConfigPacket::from_bytes(raw))
+    }
+
+    fn encode_pedal_reports(&self, pedal_index: usize, config: &Configuration) -> Result<Vec<[u8; 8]>> {
+        Self::pedal_reports(&self.capabilities, pedal_index, config)
+    }
+
     fn has_modifications(&self) -> bool {
         if let Ok(modified_pedals) = self.modified_pedals.lock() {
             modified_pedals.iter().any(|&m| m)
@@ -470,3 +918,195 @@ impl PedalDevice for IkkegolDevice {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{MediaConfiguration, TextConfiguration};
+    use crate::protocol::MediaButton;
+
+    /// Read function replaying `chunks`; an empty chunk is a read that timed out
+    fn replay(chunks: Vec<&'static [u8]>) -> impl FnMut(&mut [u8; 8], i32) -> Result<usize> {
+        let mut chunks = chunks.into_iter();
+        move |buf, _timeout_ms| {
+            let chunk = chunks.next().unwrap_or(&[]);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_model_response_survives_empty_reads() {
+        let read = replay(vec![b"FS2020U1", b"", b"", b"IR_V3.4\0"]);
+        let response = assemble_model_response(read, MODEL_READ_DEADLINE);
+        assert_eq!(response, b"FS2020U1IR_V3.4\0");
+
+        // Nothing ever arrives: give up at the deadline
+        let response = assemble_model_response(replay(Vec::new()), Duration::from_millis(10));
+        assert!(response.is_empty());
+
+        // A reply without a terminator ends once the device goes quiet, not at the deadline
+        let started = Instant::now();
+        let response = assemble_model_response(replay(vec![b"FS2020U1", b"IR_V3.40"]), Duration::from_secs(30));
+        assert_eq!(response, b"FS2020U1IR_V3.40");
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    /// Read function streaming `packets` in 8-byte reports, then timing out
+    fn stream(packets: &[[u8; 40]]) -> impl FnMut(&mut [u8; 8], i32) -> Result<usize> {
+        let mut reports: Vec<[u8; 8]> = packets
+            .iter()
+            .flat_map(|packet| packet.chunks_exact(8).map(|chunk| chunk.try_into().unwrap()))
+            .collect();
+        reports.reverse();
+        move |buf, _timeout_ms| match reports.pop() {
+            Some(report) => {
+                *buf = report;
+                Ok(8)
+            }
+            None => Ok(0),
+        }
+    }
+
+    #[test]
+    fn test_pipelined_responses_keep_pedal_order() {
+        let configs = [
+            Configuration::Media(MediaConfiguration::new(MediaButton::Play)),
+            Configuration::Unconfigured,
+            Configuration::Text(TextConfiguration::new("hi".to_string())),
+        ];
+        let packets: Vec<[u8; 40]> = configs
+            .iter()
+            .map(|config| protocol::ikkegol::encode_config(config).unwrap().to_bytes())
+            .collect();
+
+        let replies = collect_pipelined_responses(stream(&packets), 3, 100).unwrap();
+        assert_eq!(replies, packets);
+        for (bytes, config) in replies.iter().zip(&configs) {
+            assert_eq!(&protocol::ikkegol::parse_config(&ConfigPacket::from_bytes(bytes)).unwrap(), config);
+        }
+
+        // A missing reply can't be matched to its command
+        assert!(collect_pipelined_responses(stream(&packets[..2]), 3, 100).is_err());
+
+        // Nor can a reply that isn't a config packet
+        let mut garbled = packets.clone();
+        garbled[1][ConfigPacket::CONFIG_TYPE_OFFSET] = 0xee;
+        assert!(collect_pipelined_responses(stream(&garbled), 3, 100).is_err());
+    }
+
+    #[test]
+    fn test_parse_model_response() {
+        let parsed = parse_model_response;
+        let pair = |model: &str, version: &str| (model.to_string(), version.to_string());
+
+        assert_eq!(parsed(b"FS2020U1IR_V3.4"), pair("FS2020U1IR", "V3.4"));
+        assert_eq!(parsed(b"GARBAGE"), pair("GARBAGE", "unknown"));
+        assert_eq!(parsed(b"FS20\x0020U1IR_V3.4\0\0\0"), pair("FS2020U1IR", "V3.4"));
+        assert_eq!(parsed(b"\xff\xfeFS2017U1IR_1.0\x07"), pair("FS2017U1IR", "1.0"));
+
+        // Only a version-like last segment is split off
+        assert_eq!(parsed(b"USB_Foot_Switch"), pair("USB_Foot_Switch", "unknown"));
+        assert_eq!(parsed(b"FS_2020_V2"), pair("FS_2020", "V2"));
+        assert_eq!(parsed(b"_V1"), pair("_V1", "unknown"));
+        assert_eq!(parsed(b""), pair("", "unknown"));
+
+        assert_eq!(parsed(&[b'A'; 100]).0.len(), MODEL_RESPONSE_MAX);
+    }
+
+    fn scythe(s: &str) -> IkkegolModel {
+        IkkegolModel::from_scythe_str(s, IkkegolModel::Scythe)
+    }
+
+    #[test]
+    fn test_scythe_model_strings() {
+        assert_eq!(scythe("USB Foot Switch"), IkkegolModel::Scythe);
+        assert_eq!(scythe("Scythe USB FootSwitch"), IkkegolModel::Scythe);
+        assert_eq!(scythe("USB Foot Switch II"), IkkegolModel::Scythe2);
+        assert_eq!(scythe("scythe foot switch 2"), IkkegolModel::Scythe2);
+        assert_eq!(scythe("FS2017U1IR"), IkkegolModel::FS2017U1IR);
+    }
+
+    #[test]
+    fn test_single_pedal_fs2017_writes_protocol_slot_1() {
+        let capabilities = IkkegolModel::FS2017U1IR.capabilities();
+
+        // `clutchctl set 0 1 ...` and `clutchctl set 0 pedal ...`
+        for selector in ["1", "pedal"] {
+            let indices = capabilities.resolve_pedals(selector).unwrap();
+            assert_eq!(indices, vec![0]);
+            assert_eq!(capabilities.get_protocol_index(indices[0]), Some(1));
+
+            // The header carries the slot 1-based, so slot 1 is sent as 2
+            let header = IkkegolDevice::write_header(&capabilities, indices[0], 40).unwrap();
+            assert_eq!(header, Command::WriteHeader { size: 40, pedal: 1 }.to_bytes());
+            assert_eq!(header[3], 2);
+        }
+
+        assert!(IkkegolDevice::write_header(&capabilities, 1, 40).is_err());
+    }
+
+    #[test]
+    fn test_pedal_reports_match_packet() {
+        let capabilities = IkkegolModel::FS2017U1IR.capabilities();
+        let config = Configuration::Media(MediaConfiguration::new(MediaButton::Play));
+        let packet = protocol::ikkegol::encode_config(&config).unwrap();
+
+        let reports = IkkegolDevice::pedal_reports(&capabilities, 0, &config).unwrap();
+        assert_eq!(reports[0], BEGIN_WRITE);
+        assert_eq!(reports[1], Command::WriteHeader { size: packet.size, pedal: 1 }.to_bytes());
+        assert_eq!(reports[2..].concat(), packet.to_bytes().to_vec());
+    }
+
+    #[test]
+    fn test_probe_config_plausibility() {
+        let mut packet = ConfigPacket::unconfigured();
+        assert!(is_plausible_config(&packet));
+
+        packet.size = 0xff;
+        assert!(!is_plausible_config(&packet));
+
+        // Unknown config types are kept as raw, so they are pedals too
+        packet.size = 8;
+        packet.config_type = 0x5a;
+        assert!(is_plausible_config(&packet));
+
+        assert_eq!(probed_pedal_names(2), vec!["left", "right"]);
+        assert_eq!(probed_pedal_names(5)[4], "pedal5");
+    }
+
+    #[test]
+    fn test_probe_keeps_unknown_config_types() {
+        let mut unknown = [0u8; 40];
+        unknown[0] = 40;
+        unknown[1] = 0x5a;
+        let mut garbage = [0u8; 40];
+        garbage[0] = 0xff;
+
+        // Pedal 2 has a vendor config type; slot 3 times out
+        let slots = [[0u8; 40], unknown, [0u8; 40]];
+        assert_eq!(count_pedals(|slot| slots.get(slot).copied().ok_or(PedalError::Timeout)), 3);
+
+        // An impossible size ends the probe
+        let slots = [[0u8; 40], garbage];
+        assert_eq!(count_pedals(|slot| slots.get(slot).copied().ok_or(PedalError::Timeout)), 1);
+    }
+
+    #[test]
+    fn test_short_response_reads_as_unconfigured() {
+        let bytes = packet_from_response(&[0, 0]).unwrap();
+        let config = protocol::ikkegol::parse_config(&ConfigPacket::from_bytes(&bytes)).unwrap();
+        assert_eq!(config, Configuration::Unconfigured);
+
+        assert!(matches!(packet_from_response(&[0]), Err(PedalError::Protocol(_))));
+    }
+
+    #[test]
+    fn test_unrecognized_scythe_string_keeps_default() {
+        assert_eq!(IkkegolModel::from_scythe_str("", IkkegolModel::Scythe2), IkkegolModel::Scythe2);
+        assert_eq!(
+            IkkegolModel::from_scythe_str("\u{0}\u{1}garbage", IkkegolModel::Scythe),
+            IkkegolModel::Scythe
+        );
+    }
+}
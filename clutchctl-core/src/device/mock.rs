@@ -0,0 +1,253 @@
+//! In-memory pedal device for demos and tests without hardware
+//!
+//! After [`enable_simulation`], discovery returns a single simulated device
+//! instead of enumerating the HID bus. Its configuration lives only in the
+//! [`MockDevice`] value, so it starts unconfigured every time it is opened and
+//! nothing carries over between processes.
+
+use crate::configuration::{Configuration, ConfigurationType, Trigger};
+use crate::configuration::text::MAX_TEXT_BYTES;
+use crate::device::{DeviceCapabilities, DeviceOptions, PedalDevice};
+use crate::error::{PedalError, Result};
+use crate::protocol;
+use crate::usb::HidDeviceInfo;
+use std::ffi::CString;
+use std::sync::OnceLock;
+
+/// Device type of the simulated device in discovery
+pub const SIMULATED_DEVICE_TYPE: &str = "Simulated";
+
+/// Pedal count of the simulated device, once simulation is enabled
+static SIMULATED_PEDALS: OnceLock<usize> = OnceLock::new();
+
+/// Make discovery return a simulated device with `pedal_count` pedals
+///
+/// Only the first call in a process has an effect.
+pub fn enable_simulation(pedal_count: usize) {
+    let _ = SIMULATED_PEDALS.set(pedal_count.max(1));
+}
+
+/// Pedal count of the simulated device, if simulation is enabled
+pub fn simulated_pedal_count() -> Option<usize> {
+    SIMULATED_PEDALS.get().copied()
+}
+
+/// HID info discovery reports for the simulated device
+pub fn simulated_info() -> HidDeviceInfo {
+    HidDeviceInfo {
+        vendor_id: 0,
+        product_id: 0,
+        path: CString::new("simulated").expect("no NUL in path"),
+        serial_number: Some("SIMULATED".to_string()),
+        manufacturer: Some("clutchctl".to_string()),
+        product: Some("Simulated FootSwitch".to_string()),
+        interface_number: 1,
+    }
+}
+
+/// Pedal device whose configuration lives in memory
+pub struct MockDevice {
+    id: usize,
+    capabilities: DeviceCapabilities,
+    configurations: Vec<Configuration>,
+    modified_pedals: Vec<bool>,
+    /// Number of saves that had something to write
+    saves: usize,
+}
+
+impl MockDevice {
+    /// Create an unconfigured device with `pedal_count` pedals
+    pub fn new(id: usize, pedal_count: usize) -> Result<Self> {
+        Self::with_options(id, pedal_count, &DeviceOptions::default())
+    }
+
+    /// Create an unconfigured device, applying the names and order in `options`
+    ///
+    /// Like the real devices, a device without pedals is refused.
+    pub fn with_options(id: usize, pedal_count: usize, options: &DeviceOptions) -> Result<Self> {
+        let pedal_names = if pedal_count == 3 {
+            vec!["left".to_string(), "middle".to_string(), "right".to_string()]
+        } else {
+            (1..=pedal_count).map(|i| format!("pedal{}", i)).collect()
+        };
+
+        let capabilities = DeviceCapabilities {
+            pedal_count,
+            first_pedal_index: 0,
+            pedal_names,
+            supported_triggers: vec![Trigger::OnPress, Trigger::OnRelease],
+            supported_config_types: ConfigurationType::all().to_vec(),
+            supports_mouse_axis: true,
+            max_text_length: MAX_TEXT_BYTES,
+            physical_order: (0..pedal_count).collect(),
+        }
+        .with_names(&options.pedal_names)
+        .with_physical_order(&options.physical_order);
+        capabilities.check_pedal_count("Simulated FootSwitch")?;
+
+        Ok(Self {
+            id,
+            capabilities,
+            configurations: vec![Configuration::Unconfigured; pedal_count],
+            modified_pedals: vec![false; pedal_count],
+            saves: 0,
+        })
+    }
+
+    /// Number of saves that wrote at least one pedal
+    pub fn save_count(&self) -> usize {
+        self.saves
+    }
+
+    fn check_index(&self, pedal_index: usize) -> Result<()> {
+        if pedal_index >= self.capabilities.pedal_count {
+            return Err(PedalError::InvalidPedalIndex(
+                pedal_index,
+                self.capabilities.pedal_count,
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl PedalDevice for MockDevice {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn usb_ids(&self) -> (u16, u16) {
+        (0, 0)
+    }
+
+    fn model(&self) -> &str {
+        "Simulated FootSwitch"
+    }
+
+    fn version(&self) -> &str {
+        "simulated"
+    }
+
+    fn capabilities(&self) -> &DeviceCapabilities {
+        &self.capabilities
+    }
+
+    fn load_configuration(&mut self) -> Result<()> {
+        // Memory is the device; there is nothing newer to read
+        self.modified_pedals.fill(false);
+        Ok(())
+    }
+
+    fn save_configuration(&mut self) -> Result<()> {
+        if !self.has_modifications() {
+            return Ok(());
+        }
+
+        // Reject what the real protocol couldn't encode
+        for config in &self.configurations {
+            protocol::ikkegol::encode_config(config)?;
+        }
+
+        self.saves += 1;
+        self.modified_pedals.fill(false);
+        Ok(())
+    }
+
+    fn get_pedal_configuration(&self, pedal_index: usize) -> Result<Configuration> {
+        self.check_index(pedal_index)?;
+        Ok(self.configurations[pedal_index].clone())
+    }
+
+    fn set_pedal_configuration(&mut self, pedal_index: usize, config: Configuration) -> Result<()> {
+        self.check_index(pedal_index)?;
+        if let Configuration::Text(text) = &config {
+            self.capabilities.validate_text(text)?;
+        }
+        if self.configurations[pedal_index] != config {
+            self.configurations[pedal_index] = config;
+            self.modified_pedals[pedal_index] = true;
+        }
+        Ok(())
+    }
+
+    fn get_pedal_raw(&self, pedal_index: usize) -> Result<[u8; 40]> {
+        self.check_index(pedal_index)?;
+        Ok(protocol::ikkegol::encode_config(&self.configurations[pedal_index])?.to_bytes())
+    }
+
+    fn parse_pedal_raw(&self, raw: &[u8; 40]) -> Result<Configuration> {
+        protocol::ikkegol::parse_config(&protocol::ConfigPacket::from_bytes(raw))
+    }
+
+    /// Nothing is sent, so these are the raw bytes split into reports
+    fn encode_pedal_reports(&self, pedal_index: usize, config: &Configuration) -> Result<Vec<[u8; 8]>> {
+        self.check_index(pedal_index)?;
+        let packet = protocol::ikkegol::encode_config(config)?.to_bytes();
+        Ok(packet.chunks(8).map(|chunk| chunk.try_into().expect("40 bytes split in 8")).collect())
+    }
+
+    fn has_modifications(&self) -> bool {
+        self.modified_pedals.iter().any(|&m| m)
+    }
+
+    fn last_error(&self) -> Option<&str> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::TextConfiguration;
+
+    #[test]
+    fn test_set_then_show() {
+        let mut device = MockDevice::new(0, 3).unwrap();
+        device.load_configuration().unwrap();
+        assert_eq!(device.capabilities().resolve_pedal("middle").unwrap(), 1);
+
+        let config = Configuration::Text(TextConfiguration::new("hello".to_string()));
+        device.set_pedal_configuration(1, config.clone()).unwrap();
+        assert!(device.has_modifications());
+        device.save_configuration().unwrap();
+
+        assert_eq!(device.get_pedal_configuration(1).unwrap(), config);
+        assert_eq!(device.get_pedal_configuration(0).unwrap(), Configuration::Unconfigured);
+        assert_eq!(device.config_summary(), vec![None, Some(ConfigurationType::Text), None]);
+
+        // Setting the same configuration again writes nothing
+        device.set_pedal_configuration(1, config).unwrap();
+        assert!(!device.has_modifications());
+        device.save_configuration().unwrap();
+        assert_eq!(device.save_count(), 1);
+
+        assert!(matches!(
+            device.set_pedal_configuration(3, Configuration::Unconfigured),
+            Err(PedalError::InvalidPedalIndex(3, 3))
+        ));
+    }
+
+    #[test]
+    fn test_reset_triggers_keeps_configuration() {
+        let mut device = MockDevice::new(0, 3).unwrap();
+        let mut released = Configuration::Text(TextConfiguration::new("hi".to_string()));
+        released.set_trigger(Trigger::OnRelease);
+        device.set_pedal_configuration(0, released).unwrap();
+        device.save_configuration().unwrap();
+
+        device.reset_triggers().unwrap();
+        let config = device.get_pedal_configuration(0).unwrap();
+        assert_eq!(config.trigger(), Some(Trigger::OnPress));
+        assert_eq!(config, Configuration::Text(TextConfiguration::new("hi".to_string())));
+        assert_eq!(device.get_pedal_configuration(1).unwrap(), Configuration::Unconfigured);
+        assert_eq!(device.save_count(), 2);
+    }
+
+    #[test]
+    fn test_pedal_count() {
+        let device = MockDevice::new(0, 5).unwrap();
+        assert_eq!(device.capabilities().pedal_count, 5);
+        assert_eq!(device.capabilities().get_pedal_name(4), Some("pedal5"));
+
+        assert!(matches!(MockDevice::new(0, 0), Err(PedalError::UnsupportedDevice(_))));
+    }
+}
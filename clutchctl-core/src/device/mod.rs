@@ -1,11 +1,27 @@
 //! Device abstraction layer
 
 pub mod discovery;
+pub mod events;
+#[cfg(feature = "hotplug")]
+pub mod hotplug;
 pub mod ikkegol;
+#[cfg(any(test, feature = "simulate"))]
+pub mod mock;
+pub mod options;
 pub mod pcsensor;
+pub mod registry;
 pub mod traits;
 
-pub use discovery::discover_devices;
-pub use ikkegol::IkkegolDevice;
-pub use pcsensor::PCsensorDevice;
-pub use traits::{PedalDevice, DeviceCapabilities};
\ No newline at end of file
+pub use discovery::{
+    discover_devices, discover_devices_detailed, discover_devices_lazy, find_device_by_id,
+    open_by_path, supported_device_ids, DeviceHandle, DiscoveryReport,
+};
+#[cfg(feature = "hotplug")]
+pub use hotplug::{watch_hotplug, HotplugEvent, HotplugWatcher};
+pub use ikkegol::{IkkegolDevice, IkkegolModel};
+#[cfg(any(test, feature = "simulate"))]
+pub use mock::{enable_simulation, MockDevice};
+pub use options::DeviceOptions;
+pub use pcsensor::{PCsensorDevice, PCsensorModel, PCsensorTiming};
+pub use registry::{DeviceRegistry, SharedDevice};
+pub use traits::{PedalDevice, DeviceCapabilities, GlobalSettings, LedMode, PedalNumbering, ALL_PEDALS};
\ No newline at end of file
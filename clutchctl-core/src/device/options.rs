@@ -0,0 +1,22 @@
+//! Options applied when opening a device
+
+use crate::device::pcsensor::PCsensorTiming;
+
+/// Tunables passed to device constructors
+///
+/// The defaults match the behaviour of [`DeviceHandle::open`](crate::device::DeviceHandle::open).
+#[derive(Debug, Clone, Default)]
+pub struct DeviceOptions {
+    /// Write delays for PCsensor devices
+    pub pcsensor_timing: PCsensorTiming,
+    /// Pedal names replacing the model defaults, in pedal order; empty keeps them
+    ///
+    /// When empty, [`DeviceHandle::open_with_options`](crate::device::DeviceHandle::open_with_options)
+    /// uses the names configured for the device's serial number in
+    /// `pedal-names.toml`, if any.
+    pub pedal_names: Vec<String>,
+    /// Pedal indices from the leftmost pedal on; empty keeps the model's order
+    ///
+    /// Falls back to `pedal-names.toml` the same way as `pedal_names`.
+    pub physical_order: Vec<usize>,
+}
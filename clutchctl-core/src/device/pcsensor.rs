@@ -1,44 +1,149 @@
 //! PCsensor USB pedal device implementation using HID protocol
 
-use crate::configuration::Configuration;
-use crate::device::{DeviceCapabilities, PedalDevice};
-use crate::error::{PedalError, Result};
-use crate::protocol::{TriggerMode, ModifierKeys, HID_KEYMAP};
+use crate::configuration::{Configuration, ConfigurationType, Trigger};
+use crate::device::{DeviceCapabilities, DeviceOptions, PedalDevice};
+use crate::error::{PedalError, PedalOperationKind, Result};
+use crate::protocol::{key_name, parse_hex_scan_code, parse_raw_key, Command, TriggerMode, ModifierKeys, HID_KEYMAP};
 use crate::configuration::keyboard::{KeyboardConfiguration, KeyMode};
 use crate::configuration::mouse::{MouseConfiguration, MouseButton, MouseMode};
-use crate::configuration::text::TextConfiguration;
-use crate::usb::{open_device_path, HidDeviceInfo};
+use crate::configuration::text::{TextConfiguration, MAX_TEXT_BYTES};
+use crate::usb::{check_read, open_device_path, DeviceLock, HidDeviceInfo};
 use hidapi::HidDevice;
 use log::debug;
 use std::sync::Mutex;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Pedal slots written on every save, whatever the pedal count
+const PEDAL_SLOTS: usize = 3;
+
+/// Configuration types the PCsensor report format can encode
+///
+/// The protocol has no media or gamepad report; those pedals read back raw.
+const SUPPORTED_CONFIG_TYPES: &[ConfigurationType] = &[
+    ConfigurationType::Keyboard,
+    ConfigurationType::Mouse,
+    ConfigurationType::Text,
+];
+
+/// Command that begins the write of each pedal
+const BEGIN_WRITE: [u8; 8] = Command::BeginWrite { arg: 0 }.to_bytes();
+
+/// Upper bound on reports drained when leaving config mode
+const MAX_DRAIN_REPORTS: usize = 16;
+
+/// Read timeout while draining reports, in milliseconds
+const DRAIN_TIMEOUT_MS: i32 = 50;
+
+/// Delays used when writing to a PCsensor device
+///
+/// The firmware drops reports that arrive too quickly, and needs time after
+/// the start command before it accepts pedal data. Some units cope with much
+/// shorter delays than the defaults; others need longer ones.
+///
+/// Both delays block the thread calling
+/// [`save_configuration`](crate::device::PedalDevice::save_configuration):
+/// the start delay is paid once per pedal, so a 3-pedal save takes over three
+/// seconds with the defaults. GUIs should save from a worker thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PCsensorTiming {
+    /// Delay after every HID report written (default 30ms)
+    pub write_delay: Duration,
+    /// Extra delay after the start-write command (default 1s)
+    pub start_delay: Duration,
+    /// Wait for a report from the device instead of sleeping blindly after
+    /// the start command, for at most `start_delay` (default off)
+    ///
+    /// The protocol has no documented acknowledgement. Units that answer the
+    /// start command continue as soon as they do; units that don't still
+    /// wait the full `start_delay`.
+    pub wait_for_ack: bool,
+}
+
+impl Default for PCsensorTiming {
+    fn default() -> Self {
+        Self {
+            write_delay: Duration::from_millis(30),
+            start_delay: Duration::from_secs(1),
+            wait_for_ack: false,
+        }
+    }
+}
 
 /// PCsensor device models
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PCsensorModel {
     FootSwitch3Pedal,  // Most PCsensor devices have 3 pedals
     FootSwitch1Pedal,  // Single pedal variant (VID: 5131, PID: 2019)
 }
 
+impl PCsensorModel {
+    /// Get device capabilities
+    fn capabilities(&self) -> DeviceCapabilities {
+        match self {
+            Self::FootSwitch3Pedal => DeviceCapabilities {
+                pedal_count: 3,
+                first_pedal_index: 0,
+                pedal_names: vec![
+                    "left".to_string(),
+                    "middle".to_string(),
+                    "right".to_string(),
+                ],
+                // The PCsensor protocol has no trigger mode table
+                supported_triggers: vec![Trigger::OnPress],
+                supported_config_types: SUPPORTED_CONFIG_TYPES.to_vec(),
+                supports_mouse_axis: true,
+                max_text_length: MAX_TEXT_BYTES,
+                physical_order: vec![0, 1, 2],
+            },
+            Self::FootSwitch1Pedal => DeviceCapabilities {
+                pedal_count: 1,
+                first_pedal_index: 0,
+                pedal_names: vec!["pedal".to_string()],
+                supported_triggers: vec![Trigger::OnPress],
+                supported_config_types: SUPPORTED_CONFIG_TYPES.to_vec(),
+                supports_mouse_axis: true,
+                max_text_length: MAX_TEXT_BYTES,
+                physical_order: vec![0],
+            },
+        }
+    }
+}
+
 /// PCsensor pedal device using HID protocol
 pub struct PCsensorDevice {
     device: Mutex<HidDevice>,
+    /// Cross-process lock, held from open until the device is dropped so no
+    /// other process can write between a load and a save
+    _lock: DeviceLock,
     id: usize,
+    /// USB vendor and product ID
+    usb_ids: (u16, u16),
     model: PCsensorModel,
     version: String,
     capabilities: DeviceCapabilities,
     configurations: Mutex<Vec<Configuration>>,
     trigger_modes: Mutex<Vec<TriggerMode>>,
     modified_pedals: Mutex<Vec<bool>>,
+    /// Bytes of each pedal's configuration as last read
+    raw_configs: Mutex<Vec<[u8; 40]>>,
+    timing: PCsensorTiming,
 }
 
 impl PCsensorDevice {
     /// Create a new PCsensor device
     pub fn new(info: HidDeviceInfo, id: usize) -> Result<Self> {
+        Self::with_options(info, id, &DeviceOptions::default())
+    }
+
+    /// Create a new PCsensor device with custom options
+    pub fn with_options(info: HidDeviceInfo, id: usize, options: &DeviceOptions) -> Result<Self> {
         debug!("Opening PCsensor device {:04x}:{:04x} at path {:?}",
                info.vendor_id, info.product_id, info.path);
 
+        // See `DeviceLock::acquire`
+        let lock = DeviceLock::acquire(&info.device_key())?;
+
         // Open the device by path
         let device = open_device_path(&info.path)?;
 
@@ -52,22 +157,10 @@ impl PCsensorDevice {
             PCsensorModel::FootSwitch3Pedal
         };
 
-        let capabilities = match model {
-            PCsensorModel::FootSwitch3Pedal => DeviceCapabilities {
-                pedal_count: 3,
-                first_pedal_index: 0,
-                pedal_names: vec![
-                    "left".to_string(),
-                    "middle".to_string(),
-                    "right".to_string(),
-                ],
-            },
-            PCsensorModel::FootSwitch1Pedal => DeviceCapabilities {
-                pedal_count: 1,
-                first_pedal_index: 0,
-                pedal_names: vec!["pedal".to_string()],
-            },
-        };
+        let capabilities = model.capabilities()
+            .with_names(&options.pedal_names)
+            .with_physical_order(&options.physical_order);
+        capabilities.check_pedal_count(&format!("{:04x}:{:04x}", info.vendor_id, info.product_id))?;
 
         let pedal_count = capabilities.pedal_count;
         let configurations = vec![Configuration::Unconfigured; pedal_count];
@@ -76,13 +169,17 @@ impl PCsensorDevice {
 
         let mut device_obj = Self {
             device: Mutex::new(device),
+            _lock: lock,
             id,
+            usb_ids: (info.vendor_id, info.product_id),
             model,
             version: "V5.7".to_string(), // Default version
             capabilities,
             configurations: Mutex::new(configurations),
             trigger_modes: Mutex::new(trigger_modes),
             modified_pedals: Mutex::new(modified_pedals),
+            raw_configs: Mutex::new(vec![[0; 40]; pedal_count]),
+            timing: options.pcsensor_timing,
         };
 
         // Load current configuration
@@ -93,8 +190,61 @@ impl PCsensorDevice {
         Ok(device_obj)
     }
 
+    /// Hardware model, from the USB ID
+    pub fn hardware_model(&self) -> PCsensorModel {
+        self.model
+    }
+
+    /// Write every pedal slot; the PCsensor protocol requires all three
+    fn write_all_pedals(&self) -> Result<()> {
+        let (configurations, trigger_modes) = {
+            let configurations = self.configurations.lock()
+                .map_err(|_| PedalError::Hid("Failed to lock configurations".to_string()))?;
+            let trigger_modes = self.trigger_modes.lock()
+                .map_err(|_| PedalError::Hid("Failed to lock trigger modes".to_string()))?;
+            (configurations.clone(), trigger_modes.clone())
+        };
+
+        for (i, reports) in Self::save_reports(&configurations, &trigger_modes)?.iter().enumerate() {
+            match configurations.get(i) {
+                Some(config) => debug!("Writing {}: {}", self.capabilities.pedal_log_label(i), config),
+                // The single-pedal model still needs its unused slots written
+                None => debug!("Writing unused slot {} (wire 0x{:02x}) as unconfigured", i, i + 1),
+            }
+            let result = self.write_reports(reports);
+            if i < self.capabilities.pedal_count {
+                result.map_err(|e| e.for_pedal(i, PedalOperationKind::Write))?;
+            } else {
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Report sequences of a full save, one per pedal slot
+    ///
+    /// Slots past the device's pedals (2 and 3 of the single-pedal model) get
+    /// only a header and an empty report, without a start command, as they
+    /// always have. Every pedal is encoded before anything is written, so a
+    /// configuration that can't be encoded leaves the device untouched.
+    fn save_reports(configurations: &[Configuration], trigger_modes: &[TriggerMode]) -> Result<Vec<Vec<[u8; 8]>>> {
+        (0..PEDAL_SLOTS.max(configurations.len()))
+            .map(|i| match configurations.get(i) {
+                Some(config) => {
+                    let trigger_mode = trigger_modes.get(i).copied().unwrap_or(TriggerMode::Press);
+                    Self::pedal_reports(i, config, trigger_mode)
+                        .map_err(|e| e.for_pedal(i, PedalOperationKind::Write))
+                }
+                None => Ok(vec![
+                    Command::WriteHeader { size: 0x08, pedal: i as u8 }.to_bytes(),
+                    [8, 0, 0, 0, 0, 0, 0, 0],
+                ]),
+            })
+            .collect()
+    }
+
     /// Write HID report to device
-    fn hid_write(device: &HidDevice, data: &[u8; 8]) -> Result<()> {
+    fn hid_write(&self, device: &HidDevice, data: &[u8; 8]) -> Result<()> {
         debug!("Writing HID report: {:02x?}", data);
 
         // hidapi requires a report ID as the first byte
@@ -104,7 +254,24 @@ impl PCsensorDevice {
         buffer[1..9].copy_from_slice(data);
 
         device.write(&buffer)?;
-        thread::sleep(Duration::from_millis(30));
+        thread::sleep(self.timing.write_delay);
+        Ok(())
+    }
+
+    /// Give the firmware time to accept pedal data after the start command
+    fn wait_after_start(&self, device: &HidDevice) -> Result<()> {
+        if !self.timing.wait_for_ack {
+            thread::sleep(self.timing.start_delay);
+            return Ok(());
+        }
+
+        let timeout_ms = self.timing.start_delay.as_millis().min(i32::MAX as u128) as i32;
+        let started = Instant::now();
+        let mut buffer = [0u8; 8];
+        match device.read_timeout(&mut buffer, timeout_ms)? {
+            0 => debug!("No acknowledgement within {:?}", self.timing.start_delay),
+            n => debug!("Start acknowledged after {:?}: {:02x?}", started.elapsed(), &buffer[..n]),
+        }
         Ok(())
     }
 
@@ -113,11 +280,7 @@ impl PCsensorDevice {
         let mut buffer = [0u8; 8];
         let timeout_ms = 1000;
 
-        let bytes_read = device.read_timeout(&mut buffer, timeout_ms)?;
-
-        if bytes_read == 0 {
-            return Err(PedalError::Timeout);
-        }
+        let bytes_read = check_read(device.read_timeout(&mut buffer, timeout_ms))?;
 
         if bytes_read != 8 {
             return Err(PedalError::Protocol(
@@ -129,6 +292,37 @@ impl PCsensorDevice {
         Ok(buffer)
     }
 
+    /// Collect a text configuration whose first report is `first`
+    ///
+    /// The first report holds the declared length (text plus two header
+    /// bytes), the type and the first 6 bytes of text; the rest follows in
+    /// 8-byte reports read with `read_next`. Every report the declared length
+    /// calls for is read, so none is left queued for the next command, but
+    /// only the declared bytes are kept: padding in the last report is
+    /// dropped and text beyond `max_len` is discarded. Returns the text scan
+    /// codes and the raw bytes, with the text following the header and zeros
+    /// after it.
+    fn read_text(
+        first: &[u8; 8],
+        max_len: usize,
+        mut read_next: impl FnMut() -> Result<[u8; 8]>,
+    ) -> Result<(Vec<u8>, [u8; 40])> {
+        let declared = (first[0] as usize).saturating_sub(2);
+        let mut data = first[2..2 + declared.min(6)].to_vec();
+        while data.len() < declared {
+            let packet = read_next()?;
+            let chunk_len = (declared - data.len()).min(8);
+            data.extend_from_slice(&packet[..chunk_len]);
+        }
+        data.truncate(max_len);
+
+        let mut raw = [0u8; 40];
+        raw[..2].copy_from_slice(&first[..2]);
+        let stored = data.len().min(raw.len() - 2);
+        raw[2..2 + stored].copy_from_slice(&data[..stored]);
+        Ok((data, raw))
+    }
+
     /// Parse configuration from HID report
     fn parse_configuration(data: &[u8; 8]) -> Configuration {
         match data[1] {
@@ -137,13 +331,7 @@ impl PCsensorDevice {
                 // Keyboard configuration
                 let mut keys = Vec::new();
                 if data[3] != 0 {
-                    // Try to decode scan code to key name using HID keymap
-                    if let Some(key_name) = HID_KEYMAP.decode_key(data[3]) {
-                        keys.push(key_name.to_string());
-                    } else {
-                        // Fall back to hex representation for unknown codes
-                        keys.push(format!("0x{:02x}", data[3]));
-                    }
+                    keys.push(key_name(data[3]));
                 }
 
                 let modifiers = ModifierKeys::from_bits_truncate(data[2]);
@@ -156,47 +344,47 @@ impl PCsensorDevice {
                 Configuration::Keyboard(KeyboardConfiguration::with_modifiers(mode, keys, modifiers))
             },
             2 => {
-                // Mouse configuration
-                let buttons = match data[4] {
-                    1 => vec![MouseButton::Left],
-                    2 => vec![MouseButton::Right],
-                    4 => vec![MouseButton::Middle],
-                    _ => vec![],
-                };
-
-                let x = data[5] as i8;
-                let y = data[6] as i8;
-                let wheel = data[7] as i8;
-
-                if !buttons.is_empty() {
-                    Configuration::Mouse(MouseConfiguration::buttons(buttons.into_iter().collect()))
+                // Mouse configuration; a nonzero button byte takes priority over
+                // movement, otherwise x/y/wheel are signed relative values.
+                // Button bits other than left, right and middle can't be encoded
+                // again, so such a report is kept as read
+                if data[4] & !0x07 != 0 {
+                    Self::raw_report(data)
+                } else if data[4] != 0 {
+                    let buttons = [
+                        (1, MouseButton::Left),
+                        (2, MouseButton::Right),
+                        (4, MouseButton::Middle),
+                    ]
+                    .into_iter()
+                    .filter(|&(bit, _)| data[4] & bit != 0)
+                    .map(|(_, button)| button)
+                    .collect();
+                    Configuration::Mouse(MouseConfiguration::buttons(buttons))
                 } else {
+                    let x = data[5] as i8;
+                    let y = data[6] as i8;
+                    let wheel = data[7] as i8;
                     Configuration::Mouse(MouseConfiguration::axis(x, y, wheel))
                 }
             },
-            3 => {
-                // Combined keyboard and mouse - for now, just return keyboard part
-                let mut keys = Vec::new();
-                if data[3] != 0 {
-                    // Try to decode scan code to key name using HID keymap
-                    if let Some(key_name) = HID_KEYMAP.decode_key(data[3]) {
-                        keys.push(key_name.to_string());
-                    } else {
-                        // Fall back to hex representation for unknown codes
-                        keys.push(format!("0x{:02x}", data[3]));
-                    }
-                }
-                let modifiers = ModifierKeys::from_bits_truncate(data[2]);
-                Configuration::Keyboard(KeyboardConfiguration::with_modifiers(KeyMode::Standard, keys, modifiers))
-            },
             4 => {
                 // String configuration - would need to read more data
                 Configuration::Text(TextConfiguration::new(String::new()))
             },
-            _ => Configuration::Unconfigured,
+            // Keep the report so saving writes it back unchanged; this includes
+            // combined keyboard and mouse (0x03), which has no configuration type
+            _ => Self::raw_report(data),
         }
     }
 
+    /// Keep a report the parser can't represent, so saving writes it back unchanged
+    fn raw_report(data: &[u8; 8]) -> Configuration {
+        let mut raw = [0u8; 38];
+        raw[..6].copy_from_slice(&data[2..]);
+        Configuration::Raw { config_type: data[1], data: raw, trigger: Trigger::OnPress }
+    }
+
     /// Read configuration for a specific pedal
     fn read_pedal_config(&self, pedal_index: usize) -> Result<()> {
         if pedal_index >= self.capabilities.pedal_count {
@@ -210,55 +398,43 @@ impl PCsensorDevice {
             .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
 
         // Send read command for this pedal
-        let query: [u8; 8] = [0x01, 0x82, 0x08, (pedal_index + 1) as u8, 0, 0, 0, 0];
-        Self::hid_write(&device, &query)?;
+        debug!("Reading {}", self.capabilities.pedal_log_label(pedal_index));
+        let query = Command::ReadConfig { pedal: pedal_index as u8 }.to_bytes();
+        self.hid_write(&device, &query)?;
 
         // Read first response packet
         let response = Self::hid_read(&device)?;
+        let mut raw = [0u8; 40];
+        raw[..8].copy_from_slice(&response);
 
         // Check if this is a text configuration that needs more data
         let (config, trigger_mode) = if response[1] == 0x04 {
             // Text configuration - read additional packets
-            let text_len = (response[0] as usize).saturating_sub(2).min(38);
-            let mut text_data = vec![0u8; 38];
-
-            // The first packet contains the first 6 bytes of text
-            if text_len > 0 {
-                let first_chunk_len = text_len.min(6);
-                text_data[..first_chunk_len].copy_from_slice(&response[2..2 + first_chunk_len]);
-            }
-
-            // Read remaining packets if needed
-            let mut bytes_read = 6;
-            while bytes_read < text_len {
-                let packet = Self::hid_read(&device)?;
-                let chunk_len = (text_len - bytes_read).min(8);
-                text_data[bytes_read..bytes_read + chunk_len].copy_from_slice(&packet[..chunk_len]);
-                bytes_read += chunk_len;
-            }
+            let max_len = self.capabilities.max_text_length;
+            let (text_data, text_raw) = Self::read_text(&response, max_len, || Self::hid_read(&device))?;
+            raw = text_raw;
 
             // Decode text from HID scan codes
-            let mut text_array: [u8; 38] = [0; 38];
-            text_array.copy_from_slice(&text_data);
-            let text = TextConfiguration::decode_from_protocol(&text_array);
+            let text = TextConfiguration::decode_scan_codes(&text_data);
             (Configuration::Text(TextConfiguration::new(text)), TriggerMode::Press)
         } else {
             // Parse other configuration types normally
             let config = Self::parse_configuration(&response);
 
-            // Trigger mode is in the response type (0x81 means inverted/one-shot)
-            let trigger_mode = if response[1] & 0x80 != 0 {
-                TriggerMode::Release
-            } else {
-                TriggerMode::Press
-            };
-
-            (config, trigger_mode)
+            // The 0x80 bit of the type byte is the one-shot flag, already decoded
+            // into the key mode; PCsensor devices always trigger on press
+            (config, TriggerMode::Press)
         };
 
         // Drop device lock before acquiring other locks
         drop(device);
 
+        {
+            let mut raw_configs = self.raw_configs.lock()
+                .map_err(|_| PedalError::Hid("Failed to lock raw configurations".to_string()))?;
+            raw_configs[pedal_index] = raw;
+        }
+
         // Update configurations
         {
             let mut configurations = self.configurations.lock()
@@ -277,7 +453,7 @@ impl PCsensorDevice {
     }
 
     /// Encode configuration to HID format
-    fn encode_configuration(config: &Configuration, _trigger: TriggerMode) -> Vec<u8> {
+    fn encode_configuration(config: &Configuration, _trigger: TriggerMode) -> Result<Vec<u8>> {
         let mut data = Vec::new();
 
         match config {
@@ -288,20 +464,27 @@ impl PCsensorDevice {
                 } else {
                     0x01
                 };
+                if kb.keys.len() > 1 {
+                    return Err(PedalError::InvalidConfiguration(format!(
+                        "PCsensor pedals hold a single key plus modifiers, got {} keys",
+                        kb.keys.len()
+                    )));
+                }
+
                 data.push(8); // Length
                 data.push(type_byte);
                 data.push(kb.modifiers.bits());
                 // Parse first key if it exists
-                let key_code = if !kb.keys.is_empty() {
-                    // First try hex codes for backward compatibility
-                    if kb.keys[0].starts_with("0x") {
-                        u8::from_str_radix(&kb.keys[0][2..], 16).unwrap_or(0)
-                    } else {
+                let key_code = match kb.keys.first() {
+                    // Raw usage codes bypass the keymap, then try hex codes for backward compatibility
+                    Some(key) => match parse_raw_key(key).or_else(|| parse_hex_scan_code(key)) {
+                        Some(code) => code?,
                         // Try to encode key name using HID keymap
-                        HID_KEYMAP.encode_key(&kb.keys[0]).unwrap_or(0)
-                    }
-                } else {
-                    0
+                        None => HID_KEYMAP.encode_key(key).ok_or_else(|| {
+                            PedalError::InvalidConfiguration(format!("unknown key '{}'", key))
+                        })?,
+                    },
+                    None => 0,
                 };
                 data.push(key_code);
                 data.extend_from_slice(&[0, 0, 0, 0]); // Padding
@@ -315,15 +498,28 @@ impl PCsensorDevice {
                 // Encode mouse buttons or axis
                 match &m.mode {
                     MouseMode::Buttons(buttons) => {
+                        // The report has no bits for back and forward; dropping them
+                        // silently would read back as a different configuration
                         let mut button_byte = 0u8;
                         for button in buttons {
                             match button {
                                 MouseButton::Left => button_byte |= 1,
                                 MouseButton::Right => button_byte |= 2,
                                 MouseButton::Middle => button_byte |= 4,
-                                _ => {}
+                                other => {
+                                    return Err(PedalError::InvalidConfiguration(format!(
+                                        "PCsensor mouse pedals support left, right and middle, not {}",
+                                        other.as_str()
+                                    )))
+                                }
                             }
                         }
+                        // Zero would read back as a motionless axis configuration
+                        if button_byte == 0 {
+                            return Err(PedalError::InvalidConfiguration(
+                                "PCsensor mouse pedals need at least one of left, right and middle".to_string(),
+                            ));
+                        }
                         data.push(button_byte);
                         data.push(0);
                         data.push(0);
@@ -361,86 +557,91 @@ impl PCsensorDevice {
 
                 data.extend_from_slice(&packet);
             },
-            _ => {
-                // Unconfigured
+            Configuration::Raw { config_type, data: raw, .. } => {
+                // Re-emit the report as read
+                data.push(8);
+                data.push(*config_type);
+                data.extend_from_slice(&raw[..6]);
+            }
+            Configuration::Media(_) | Configuration::Gamepad(_) => {
+                return Err(PedalError::InvalidConfiguration(format!(
+                    "PCsensor pedals do not support {} configurations",
+                    config.configuration_type().map(|t| t.to_string()).unwrap_or_default()
+                )));
+            }
+            Configuration::Unconfigured => {
                 data.extend_from_slice(&[8, 0, 0, 0, 0, 0, 0, 0]);
             }
         }
 
-        data
+        Ok(data)
     }
 
-    /// Write configuration for a specific pedal
-    fn write_pedal_config(&self, pedal_index: usize) -> Result<()> {
-        if pedal_index >= self.capabilities.pedal_count {
-            return Err(PedalError::InvalidPedalIndex(
-                pedal_index,
-                self.capabilities.pedal_count,
-            ));
-        }
-
-        // Get configuration and trigger mode first
-        let (config, trigger_mode) = {
-            let configurations = self.configurations.lock()
-                .map_err(|_| PedalError::Hid("Failed to lock configurations".to_string()))?;
-            let trigger_modes = self.trigger_modes.lock()
-                .map_err(|_| PedalError::Hid("Failed to lock trigger modes".to_string()))?;
-            (configurations[pedal_index].clone(), trigger_modes[pedal_index])
-        };
-
+    /// Write one pedal's report sequence, pausing after the start command
+    fn write_reports(&self, reports: &[[u8; 8]]) -> Result<()> {
         let device = self.device.lock()
             .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
 
-        // Start write sequence
-        let start: [u8; 8] = [0x01, 0x80, 0x08, 0, 0, 0, 0, 0];
-        Self::hid_write(&device, &start)?;
-        thread::sleep(Duration::from_secs(1));
+        // The start command needs time before the firmware accepts the rest
+        for report in reports {
+            self.hid_write(&device, report)?;
+            if *report == BEGIN_WRITE {
+                self.wait_after_start(&device)?;
+            }
+        }
 
-        // Write pedal header
-        let header: [u8; 8] = [0x01, 0x81, 0x08, (pedal_index + 1) as u8, 0, 0, 0, 0];
-        Self::hid_write(&device, &header)?;
+        Ok(())
+    }
 
-        // Special handling for text configuration
-        if let Configuration::Text(text) = &config {
-            // Text configuration requires special multi-packet format
-            let text_data = text.encode_for_protocol();
+    /// HID reports that write one pedal: start command, pedal header, then data
+    fn pedal_reports(pedal_index: usize, config: &Configuration, trigger_mode: TriggerMode) -> Result<Vec<[u8; 8]>> {
+        // Start write sequence and pedal header
+        let mut reports = vec![
+            BEGIN_WRITE,
+            Command::WriteHeader { size: 0x08, pedal: pedal_index as u8 }.to_bytes(),
+        ];
 
-            // Count actual text bytes (non-zero)
-            let text_len = text_data.iter().take_while(|&&b| b != 0).count();
+        // Special handling for text configuration
+        if let Configuration::Text(text) = config {
+            // Text configuration requires special multi-packet format; the
+            // length was checked against the capabilities when it was set
+            let text_data = text.scan_codes();
+            let text_len = text_data.len();
+            let length_byte = u8::try_from(text_len + 2).map_err(|_| {
+                PedalError::InvalidConfiguration(format!("Text too long: {} bytes", text_len))
+            })?;
 
             // First packet: length, type, and first 6 bytes of text
             let mut first_packet = [0u8; 8];
-            first_packet[0] = (text_len + 2).min(40) as u8; // Length includes 2-byte header
+            first_packet[0] = length_byte; // Length includes 2-byte header
             first_packet[1] = 0x04; // Text type
 
             let first_chunk_len = text_len.min(6);
             if first_chunk_len > 0 {
                 first_packet[2..2 + first_chunk_len].copy_from_slice(&text_data[..first_chunk_len]);
             }
-            Self::hid_write(&device, &first_packet)?;
+            reports.push(first_packet);
 
-            // Write remaining text in 8-byte packets
+            // Remaining text in 8-byte packets
             let mut offset = 6;
             while offset < text_len {
                 let mut packet = [0u8; 8];
                 let chunk_len = (text_len - offset).min(8);
                 packet[..chunk_len].copy_from_slice(&text_data[offset..offset + chunk_len]);
-                Self::hid_write(&device, &packet)?;
+                reports.push(packet);
                 offset += 8;
             }
         } else {
-            // Encode and write other configuration types
-            let config_data = Self::encode_configuration(&config, trigger_mode);
-
-            // Write in 8-byte chunks
+            // Encode other configuration types in 8-byte chunks
+            let config_data = Self::encode_configuration(config, trigger_mode)?;
             for chunk in config_data.chunks(8) {
                 let mut packet = [0u8; 8];
                 packet[..chunk.len()].copy_from_slice(chunk);
-                Self::hid_write(&device, &packet)?;
+                reports.push(packet);
             }
         }
 
-        Ok(())
+        Ok(reports)
     }
 }
 
@@ -449,6 +650,10 @@ impl PedalDevice for PCsensorDevice {
         self.id
     }
 
+    fn usb_ids(&self) -> (u16, u16) {
+        self.usb_ids
+    }
+
     fn model(&self) -> &str {
         match self.model {
             PCsensorModel::FootSwitch3Pedal => "PCsensor FootSwitch",
@@ -465,27 +670,28 @@ impl PedalDevice for PCsensorDevice {
     }
 
     fn load_configuration(&mut self) -> Result<()> {
-        for i in 0..self.capabilities.pedal_count {
-            self.read_pedal_config(i)?;
-        }
-        Ok(())
+        let result = (0..self.capabilities.pedal_count)
+            .try_for_each(|i| {
+                self.read_pedal_config(i)
+                    .map_err(|e| e.for_pedal(i, PedalOperationKind::Read))
+            });
+        let exited = self.exit_config_mode();
+        result.and(exited)
     }
 
     fn save_configuration(&mut self) -> Result<()> {
-        // Write all three pedals (PCsensor protocol requires this)
-        for i in 0..3 {
-            if i < self.capabilities.pedal_count {
-                self.write_pedal_config(i)?;
-            } else {
-                // Write empty config for non-existent pedals
-                let device = self.device.lock()
-                    .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
-                let header: [u8; 8] = [0x01, 0x81, 0x08, (i + 1) as u8, 0, 0, 0, 0];
-                Self::hid_write(&device, &header)?;
-                let empty: [u8; 8] = [8, 0, 0, 0, 0, 0, 0, 0];
-                Self::hid_write(&device, &empty)?;
-            }
+        if !self.has_modifications() {
+            return Ok(());
         }
+
+        let result = self.write_all_pedals();
+        let exited = self.exit_config_mode();
+        result.and(exited)?;
+
+        // Clear modified flags
+        let mut modified_pedals = self.modified_pedals.lock()
+            .map_err(|_| PedalError::Hid("Failed to lock modified flags".to_string()))?;
+        modified_pedals.fill(false);
         Ok(())
     }
 
@@ -512,10 +718,17 @@ impl PedalDevice for PCsensorDevice {
                 self.capabilities.pedal_count,
             ));
         }
+        if let Configuration::Text(text) = &config {
+            self.capabilities.validate_text(text)?;
+        }
 
         {
             let mut configurations = self.configurations.lock()
                 .map_err(|_| PedalError::Hid("Failed to lock configurations".to_string()))?;
+            // Unchanged pedals stay unmodified, see `PedalDevice::set_pedal_configuration`
+            if configurations[pedal_index] == config {
+                return Ok(());
+            }
             configurations[pedal_index] = config;
         }
 
@@ -528,6 +741,40 @@ impl PedalDevice for PCsensorDevice {
         Ok(())
     }
 
+    fn get_pedal_raw(&self, pedal_index: usize) -> Result<[u8; 40]> {
+        let raw_configs = self.raw_configs.lock()
+            .map_err(|_| PedalError::Hid("Failed to lock raw configurations".to_string()))?;
+        raw_configs.get(pedal_index)
+            .copied()
+            .ok_or(PedalError::InvalidPedalIndex(pedal_index, self.capabilities.pedal_count))
+    }
+
+    fn parse_pedal_raw(&self, raw: &[u8; 40]) -> Result<Configuration> {
+        if raw[1] == 0x04 {
+            // Text reports are stored back to back, so the text follows the header
+            let text_len = (raw[0] as usize).saturating_sub(2)
+                .min(self.capabilities.max_text_length)
+                .min(raw.len() - 2);
+            let text = TextConfiguration::decode_scan_codes(&raw[2..2 + text_len]);
+            return Ok(Configuration::Text(TextConfiguration::new(text)));
+        }
+
+        let mut report = [0u8; 8];
+        report.copy_from_slice(&raw[..8]);
+        Ok(Self::parse_configuration(&report))
+    }
+
+    fn encode_pedal_reports(&self, pedal_index: usize, config: &Configuration) -> Result<Vec<[u8; 8]>> {
+        if pedal_index >= self.capabilities.pedal_count {
+            return Err(PedalError::InvalidPedalIndex(
+                pedal_index,
+                self.capabilities.pedal_count,
+            ));
+        }
+        let trigger_mode = config.trigger().unwrap_or(Trigger::OnPress).into();
+        Self::pedal_reports(pedal_index, config, trigger_mode)
+    }
+
     fn has_modifications(&self) -> bool {
         if let Ok(modified_pedals) = self.modified_pedals.lock() {
             modified_pedals.iter().any(|&m| m)
@@ -539,4 +786,253 @@ impl PedalDevice for PCsensorDevice {
     fn last_error(&self) -> Option<&str> {
         None
     }
+
+    fn exit_config_mode(&self) -> Result<()> {
+        // No exit command is known for this firmware; the only traffic is the
+        // `01 82 08 NN` read query and the `01 80`/`01 81` write sequence. Some
+        // units stop sending key presses while a response report is still
+        // queued on the config interface, so drain whatever is left.
+        let device = self.device.lock()
+            .map_err(|_| PedalError::Hid("Failed to lock device".to_string()))?;
+
+        let mut buffer = [0u8; 8];
+        let mut drained = 0;
+        while drained < MAX_DRAIN_REPORTS && device.read_timeout(&mut buffer, DRAIN_TIMEOUT_MS)? > 0 {
+            debug!("Drained HID report: {:02x?}", buffer);
+            drained += 1;
+        }
+
+        if drained > 0 {
+            debug!("Drained {} pending report(s) after configuration access", drained);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{GamepadConfiguration, MediaConfiguration};
+    use crate::protocol::{GameKey, MediaButton};
+
+    #[test]
+    fn test_single_pedal_capabilities() {
+        let capabilities = PCsensorModel::FootSwitch1Pedal.capabilities();
+
+        assert_eq!(capabilities.pedal_count, 1);
+        assert_eq!(capabilities.supported_triggers, vec![Trigger::OnPress]);
+        assert!(capabilities.supports_trigger(Trigger::OnPress));
+        assert!(!capabilities.supports_trigger(Trigger::OnRelease));
+        assert!(capabilities.supports_config_type(ConfigurationType::Text));
+        assert!(!capabilities.supports_config_type(ConfigurationType::Media));
+        assert!(!capabilities.supports_config_type(ConfigurationType::Gamepad));
+        assert_eq!(capabilities.max_text_length, MAX_TEXT_BYTES);
+    }
+
+    fn axis_of(config: &Configuration) -> (i8, i8, i8) {
+        match config {
+            Configuration::Mouse(m) => match m.mode {
+                MouseMode::Axis { x, y, wheel } => (x, y, wheel),
+                ref other => panic!("expected axis mode, got {:?}", other),
+            },
+            other => panic!("expected mouse configuration, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_negative_axis() {
+        let data = [8, 0x02, 0, 0, 0, (-5i8) as u8, 0, (-3i8) as u8];
+        let config = PCsensorDevice::parse_configuration(&data);
+        assert_eq!(axis_of(&config), (-5, 0, -3));
+    }
+
+    #[test]
+    fn test_parse_wheel_only() {
+        let data = [8, 0x02, 0, 0, 0, 0, 0, 10];
+        let config = PCsensorDevice::parse_configuration(&data);
+        assert_eq!(axis_of(&config), (0, 0, 10));
+    }
+
+    #[test]
+    fn test_mouse_axis_roundtrip() {
+        for (x, y, wheel) in [(-5, 0, -3), (0, 0, 10), (0, 0, -1), (127, -128, 0)] {
+            let config = Configuration::Mouse(MouseConfiguration::axis(x, y, wheel));
+            let encoded = PCsensorDevice::encode_configuration(&config, TriggerMode::Press).unwrap();
+
+            let mut data = [0u8; 8];
+            data.copy_from_slice(&encoded[..8]);
+            assert_eq!(axis_of(&PCsensorDevice::parse_configuration(&data)), (x, y, wheel));
+        }
+    }
+
+    #[test]
+    fn test_read_text_honors_declared_length() {
+        // "abcdefghij" with the padding of the last report left as garbage
+        let first = [12, 0x04, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
+        let reports = [[0x0a, 0x0b, 0x0c, 0x0d, 0xaa, 0xbb, 0xcc, 0xdd]];
+
+        let mut queue = reports.iter().copied();
+        let (data, raw) = PCsensorDevice::read_text(&first, MAX_TEXT_BYTES, || Ok(queue.next().unwrap())).unwrap();
+        assert_eq!(TextConfiguration::decode_scan_codes(&data), "abcdefghij");
+        assert_eq!(&raw[..12], &[12, 0x04, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d]);
+        assert!(raw[12..].iter().all(|&b| b == 0));
+
+        // Text beyond the limit is dropped, but its reports are still drained
+        let mut queue = reports.iter().copied();
+        let (data, _) = PCsensorDevice::read_text(&first, 8, || Ok(queue.next().unwrap())).unwrap();
+        assert_eq!(TextConfiguration::decode_scan_codes(&data), "abcdefgh");
+        assert!(queue.next().is_none());
+
+        // A short text needs no further reports
+        let (data, _) = PCsensorDevice::read_text(&[4, 0x04, 0x0b, 0x0c, 0xee, 0xee, 0, 0], MAX_TEXT_BYTES, || {
+            panic!("no report expected")
+        }).unwrap();
+        assert_eq!(TextConfiguration::decode_scan_codes(&data), "hi");
+    }
+
+    const START: [u8; 8] = [0x01, 0x80, 0x08, 0, 0, 0, 0, 0];
+    const EMPTY: [u8; 8] = [8, 0, 0, 0, 0, 0, 0, 0];
+
+    fn header(slot: u8) -> [u8; 8] {
+        [0x01, 0x81, 0x08, slot, 0, 0, 0, 0]
+    }
+
+    #[test]
+    fn test_save_sequence_three_pedals() {
+        let configurations = vec![
+            Configuration::Keyboard(KeyboardConfiguration::new(KeyMode::Standard, vec!["a".to_string()])),
+            Configuration::Unconfigured,
+            Configuration::Mouse(MouseConfiguration::axis(0, 0, 10)),
+        ];
+        let reports = PCsensorDevice::save_reports(&configurations, &[TriggerMode::Press; 3]).unwrap();
+
+        assert_eq!(reports, vec![
+            vec![START, header(1), [8, 0x01, 0, 0x04, 0, 0, 0, 0]],
+            vec![START, header(2), EMPTY],
+            vec![START, header(3), [8, 0x02, 0, 0, 0, 0, 0, 10]],
+        ]);
+    }
+
+    #[test]
+    fn test_save_sequence_single_pedal() {
+        let configurations = vec![Configuration::Mouse(MouseConfiguration::axis(0, 0, 10))];
+        let reports = PCsensorDevice::save_reports(&configurations, &[TriggerMode::Press]).unwrap();
+
+        // Phantom slots get only the header and empty report, with no start command
+        assert_eq!(reports, vec![
+            vec![START, header(1), [8, 0x02, 0, 0, 0, 0, 0, 10]],
+            vec![header(2), EMPTY],
+            vec![header(3), EMPTY],
+        ]);
+    }
+
+    #[test]
+    fn test_unencodable_mouse_buttons_are_kept_raw() {
+        for button_byte in [0x08, 0x10, 0x09] {
+            let report = [8, 0x02, 0, 0, button_byte, 0, 0, 0];
+            let mut configurations = vec![
+                PCsensorDevice::parse_configuration(&report),
+                Configuration::Unconfigured,
+                Configuration::Unconfigured,
+            ];
+            assert!(matches!(configurations[0], Configuration::Raw { config_type: 0x02, .. }));
+
+            // Saving another pedal writes the report back as read
+            configurations[1] = Configuration::Keyboard(KeyboardConfiguration::new(KeyMode::Standard, vec!["a".to_string()]));
+            let reports = PCsensorDevice::save_reports(&configurations, &[TriggerMode::Press; 3]).unwrap();
+            assert_eq!(reports[0], vec![START, header(1), report]);
+            assert_eq!(reports[1], vec![START, header(2), [8, 0x01, 0, 0x04, 0, 0, 0, 0]]);
+        }
+    }
+
+    #[test]
+    fn test_keyboard_and_mouse_report_round_trips() {
+        let report = [8, 0x03, 0x01, 0x06, 0x01, 0, 0, 0];
+        let config = PCsensorDevice::parse_configuration(&report);
+        assert!(matches!(config, Configuration::Raw { config_type: 0x03, .. }));
+
+        let reports = PCsensorDevice::save_reports(&[config], &[TriggerMode::Press]).unwrap();
+        assert_eq!(reports[0], vec![START, header(1), report]);
+    }
+
+    #[test]
+    fn test_unsupported_types_are_rejected() {
+        let media = Configuration::Media(MediaConfiguration::new(MediaButton::Play));
+        let gamepad = Configuration::Gamepad(GamepadConfiguration::new(GameKey::Button1));
+        for config in [media, gamepad] {
+            assert!(matches!(
+                PCsensorDevice::encode_configuration(&config, TriggerMode::Press),
+                Err(PedalError::InvalidConfiguration(_))
+            ), "{} should be rejected", config);
+        }
+    }
+
+    #[test]
+    fn test_invalid_keys_are_rejected() {
+        for key in ["0xGG", "0x", "notakey"] {
+            let configurations = vec![
+                Configuration::Keyboard(KeyboardConfiguration::new(KeyMode::Standard, vec![key.to_string()])),
+            ];
+            let result = PCsensorDevice::save_reports(&configurations, &[TriggerMode::Press]);
+            assert!(matches!(
+                result.unwrap_err(),
+                PedalError::PedalOperation { index: 0, source, .. } if matches!(*source, PedalError::InvalidConfiguration(_))
+            ), "{} should be rejected", key);
+        }
+    }
+
+    #[test]
+    fn test_mouse_without_encodable_buttons_is_rejected() {
+        let buttons = [
+            std::collections::HashSet::new(),
+            [MouseButton::Forward].into_iter().collect(),
+            MouseConfiguration::parse_buttons("left+back").unwrap(),
+        ];
+        for buttons in buttons {
+            let config = Configuration::Mouse(MouseConfiguration::buttons(buttons));
+            assert!(matches!(
+                PCsensorDevice::encode_configuration(&config, TriggerMode::Press),
+                Err(PedalError::InvalidConfiguration(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_modifier_only_keyboard_roundtrip() {
+        let config = Configuration::Keyboard(KeyboardConfiguration::with_modifiers(
+            KeyMode::Standard,
+            Vec::new(),
+            ModifierKeys::LEFT_SHIFT,
+        ));
+        let encoded = PCsensorDevice::encode_configuration(&config, TriggerMode::Press).unwrap();
+        assert_eq!(encoded, [8, 0x01, ModifierKeys::LEFT_SHIFT.bits(), 0, 0, 0, 0, 0]);
+
+        let mut data = [0u8; 8];
+        data.copy_from_slice(&encoded);
+        assert_eq!(PCsensorDevice::parse_configuration(&data), config);
+    }
+
+    #[test]
+    fn test_mouse_buttons_display_after_roundtrip() {
+        let config = Configuration::Mouse(MouseConfiguration::buttons(
+            MouseConfiguration::parse_buttons("right+left").unwrap(),
+        ));
+        let encoded = PCsensorDevice::encode_configuration(&config, TriggerMode::Press).unwrap();
+
+        let mut data = [0u8; 8];
+        data.copy_from_slice(&encoded[..8]);
+        assert_eq!(PCsensorDevice::parse_configuration(&data).to_string(), "Mouse: left+right");
+    }
+
+    #[test]
+    fn test_parse_button_combination() {
+        let data = [8, 0x02, 0, 0, 0x03, 0, 0, 0];
+        match PCsensorDevice::parse_configuration(&data) {
+            Configuration::Mouse(m) => assert_eq!(
+                m.mode,
+                MouseMode::Buttons([MouseButton::Left, MouseButton::Right].into_iter().collect())
+            ),
+            other => panic!("expected mouse configuration, got {:?}", other),
+        }
+    }
 }
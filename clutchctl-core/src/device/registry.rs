@@ -0,0 +1,159 @@
+//! Process-wide cache of discovered and opened devices
+//!
+//! Every free discovery function enumerates the HID bus again, and opening a
+//! device reads its model (and, for PCsensor devices, its whole configuration).
+//! A [`DeviceRegistry`] enumerates once and opens each device at most once, so
+//! a batch of operations on the same devices only pays for that a single time.
+
+use crate::device::{discover_devices_lazy, DeviceHandle, DeviceOptions, PedalDevice};
+use crate::error::{PedalError, Result};
+use log::debug;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Shared handle to an opened device, as returned by discovery
+pub type SharedDevice = Arc<dyn PedalDevice + Send + Sync>;
+
+/// Devices enumerated once and opened on first use
+///
+/// [`get`](Self::get) hands out clones of the cached `Arc`. Loading and saving
+/// need `&mut` access, which [`with_device_mut`](Self::with_device_mut) only
+/// grants while no clone is held outside the registry.
+pub struct DeviceRegistry {
+    handles: Vec<DeviceHandle>,
+    options: DeviceOptions,
+    opened: Mutex<HashMap<usize, SharedDevice>>,
+}
+
+impl DeviceRegistry {
+    /// Enumerate supported devices; nothing is opened yet
+    pub fn discover() -> Result<Self> {
+        Self::discover_with_options(DeviceOptions::default())
+    }
+
+    /// Enumerate supported devices, opening them later with custom options
+    pub fn discover_with_options(options: DeviceOptions) -> Result<Self> {
+        let handles = discover_devices_lazy()?;
+        debug!("Registry enumerated {} device(s)", handles.len());
+        Ok(Self {
+            handles,
+            options,
+            opened: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Handles of every enumerated device, in ID order
+    pub fn handles(&self) -> &[DeviceHandle] {
+        &self.handles
+    }
+
+    /// Get a device by ID, opening it on first use
+    ///
+    /// Returns `Ok(None)` when no device with that ID was enumerated.
+    pub fn get(&self, id: usize) -> Result<Option<SharedDevice>> {
+        let Some(handle) = self.handles.iter().find(|handle| handle.id() == id) else {
+            return Ok(None);
+        };
+
+        let mut opened = self.lock()?;
+        if let Some(device) = opened.get(&id) {
+            return Ok(Some(Arc::clone(device)));
+        }
+
+        let device = handle.open_with_options(&self.options)?;
+        opened.insert(id, Arc::clone(&device));
+        Ok(Some(device))
+    }
+
+    /// Run `f` with mutable access to a device, opening it on first use
+    ///
+    /// Fails if a clone handed out by [`get`](Self::get) is still alive.
+    pub fn with_device_mut<T>(
+        &self,
+        id: usize,
+        f: impl FnOnce(&mut (dyn PedalDevice + Send + Sync)) -> Result<T>,
+    ) -> Result<Option<T>> {
+        // Make sure the device is open and cached, dropping the returned clone
+        if self.get(id)?.is_none() {
+            return Ok(None);
+        }
+
+        let mut opened = self.lock()?;
+        let device = opened
+            .get_mut(&id)
+            .and_then(Arc::get_mut)
+            .ok_or_else(|| PedalError::Hid(format!("Device {} is in use elsewhere", id)))?;
+        f(device).map(Some)
+    }
+
+    /// Forget an opened device so the next access opens it again
+    pub fn close(&self, id: usize) -> Result<()> {
+        self.lock()?.remove(&id);
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<usize, SharedDevice>>> {
+        self.opened
+            .lock()
+            .map_err(|_| PedalError::Hid("Failed to lock device registry".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{Configuration, MediaConfiguration};
+    use crate::protocol::MediaButton;
+
+    /// Registry over the simulated device, without enumerating the HID bus
+    fn simulated_registry() -> DeviceRegistry {
+        DeviceRegistry {
+            handles: vec![DeviceHandle::simulated()],
+            options: DeviceOptions::default(),
+            opened: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn play() -> Configuration {
+        Configuration::Media(MediaConfiguration::new(MediaButton::Play))
+    }
+
+    #[test]
+    fn test_get_and_with_device_mut_share_one_device() {
+        let registry = simulated_registry();
+        assert!(registry.get(1).unwrap().is_none());
+        assert!(registry.with_device_mut(1, |_| Ok(())).unwrap().is_none());
+
+        let first = registry.get(0).unwrap().unwrap();
+        let second = registry.get(0).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Mutable access waits for the clones to be dropped
+        assert!(registry.with_device_mut(0, |_| Ok(())).is_err());
+        drop((first, second));
+
+        registry
+            .with_device_mut(0, |device| device.set_pedal_configuration(0, play()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(registry.get(0).unwrap().unwrap().get_pedal_configuration(0).unwrap(), play());
+    }
+
+    #[test]
+    fn test_closed_device_is_reopened() {
+        let registry = simulated_registry();
+        registry
+            .with_device_mut(0, |device| device.set_pedal_configuration(0, play()))
+            .unwrap()
+            .unwrap();
+        let before = registry.get(0).unwrap().unwrap();
+
+        registry.close(0).unwrap();
+
+        // A fresh simulated device starts unconfigured
+        let after = registry.get(0).unwrap().unwrap();
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert_eq!(after.get_pedal_configuration(0).unwrap(), Configuration::Unconfigured);
+        assert_eq!(before.get_pedal_configuration(0).unwrap(), play());
+    }
+}
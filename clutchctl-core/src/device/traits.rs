@@ -1,7 +1,22 @@
 //! Device trait definitions
 
-use crate::configuration::Configuration;
-use crate::error::Result;
+use crate::configuration::{Configuration, ConfigurationType, TextConfiguration, Trigger};
+use crate::device::events::PedalEvent;
+use crate::error::{PedalError, Result};
+use log::warn;
+
+/// Pedal selector that expands to every pedal
+pub const ALL_PEDALS: &str = "all";
+
+/// How numeric pedal selectors are counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PedalNumbering {
+    /// `1` is the first pedal, as shown by clutchctl
+    #[default]
+    OneBased,
+    /// `0` is the first pedal, as in scripts for other tools
+    ZeroBased,
+}
 
 /// Device capabilities
 #[derive(Debug, Clone)]
@@ -12,6 +27,18 @@ pub struct DeviceCapabilities {
     pub first_pedal_index: usize,
     /// Pedal names for display
     pub pedal_names: Vec<String>,
+    /// Trigger modes the firmware honors
+    pub supported_triggers: Vec<Trigger>,
+    /// Configuration types the protocol can encode
+    pub supported_config_types: Vec<ConfigurationType>,
+    /// Whether mouse pedals can move the pointer and wheel, not just click
+    pub supports_mouse_axis: bool,
+    /// Maximum encoded length of a text configuration, in bytes
+    pub max_text_length: usize,
+    /// Pedal indices from the physically leftmost (or topmost) pedal on
+    ///
+    /// The identity when the layout of the model is unknown.
+    pub physical_order: Vec<usize>,
 }
 
 impl DeviceCapabilities {
@@ -24,16 +51,176 @@ impl DeviceCapabilities {
         }
     }
 
+    /// How logs refer to a pedal: its 1-based number, the 0-based protocol slot
+    /// and the slot byte sent on the wire, e.g. `pedal 1 (slot 1, wire 0x02)`
+    ///
+    /// Every per-pedal log line uses this, so transcripts never leave it to
+    /// the reader which of the three numbers is meant.
+    pub(crate) fn pedal_log_label(&self, pedal_index: usize) -> String {
+        match self.get_protocol_index(pedal_index) {
+            Some(slot) => format!("pedal {} (slot {}, wire 0x{:02x})", pedal_index + 1, slot, slot + 1),
+            None => format!("pedal {} (no protocol slot)", pedal_index + 1),
+        }
+    }
+
+    /// Replace the default pedal names, e.g. with `top`/`center`/`bottom`
+    ///
+    /// Names are given in pedal order. Pedals without a name in the list keep
+    /// their default one; names past the last pedal are ignored.
+    pub fn with_names(mut self, names: &[String]) -> Self {
+        for (slot, name) in self.pedal_names.iter_mut().zip(names) {
+            *slot = name.clone();
+        }
+        self
+    }
+
+    /// Replace the physical order, given as pedal indices from the leftmost pedal
+    ///
+    /// An order that isn't a permutation of the pedal indices is logged and
+    /// ignored, keeping the current one.
+    pub fn with_physical_order(mut self, order: &[usize]) -> Self {
+        if order.is_empty() {
+            return self;
+        }
+
+        let mut sorted = order.to_vec();
+        sorted.sort_unstable();
+        if sorted != (0..self.pedal_count).collect::<Vec<_>>() {
+            warn!("Ignoring pedal order {:?}: expected each of the {} pedals once", order, self.pedal_count);
+            return self;
+        }
+
+        self.physical_order = order.to_vec();
+        self
+    }
+
+    /// Refuse a device without pedals, so the rest of the library can assume one
+    ///
+    /// `device` names the device in the `PedalError::UnsupportedDevice`.
+    pub fn check_pedal_count(&self, device: &str) -> Result<()> {
+        if self.pedal_count == 0 {
+            return Err(PedalError::UnsupportedDevice(format!("{} reports no pedals", device)));
+        }
+        Ok(())
+    }
+
+    /// Check that a text configuration can be typed and fits in [`Self::max_text_length`]
+    pub fn validate_text(&self, text: &TextConfiguration) -> Result<()> {
+        text.validate_with_limit(self.max_text_length)
+    }
+
     /// Get pedal name by index
     pub fn get_pedal_name(&self, pedal_index: usize) -> Option<&str> {
         self.pedal_names.get(pedal_index).map(|s| s.as_str())
     }
 
+    /// Check whether the device supports a trigger mode
+    pub fn supports_trigger(&self, trigger: Trigger) -> bool {
+        self.supported_triggers.contains(&trigger)
+    }
+
+    /// Check whether the device supports a configuration type
+    pub fn supports_config_type(&self, config_type: ConfigurationType) -> bool {
+        self.supported_config_types.contains(&config_type)
+    }
+
     /// Find pedal index by name
     pub fn find_pedal_by_name(&self, name: &str) -> Option<usize> {
         self.pedal_names.iter()
             .position(|n| n.eq_ignore_ascii_case(name))
     }
+
+    /// Resolve a user pedal selector to a 0-based pedal index
+    ///
+    /// Numbers are 1-based pedal indices (0 is invalid); anything else is matched
+    /// against the pedal names, ignoring case.
+    pub fn resolve_pedal(&self, selector: &str) -> Result<usize> {
+        self.resolve_pedal_numbered(selector, PedalNumbering::OneBased)
+    }
+
+    /// Resolve a user pedal selector, counting numeric indices as `numbering` says
+    ///
+    /// Names resolve the same way whatever the numbering.
+    pub fn resolve_pedal_numbered(&self, selector: &str, numbering: PedalNumbering) -> Result<usize> {
+        if let Ok(num) = selector.parse::<usize>() {
+            let index = match numbering {
+                PedalNumbering::OneBased => num.checked_sub(1),
+                PedalNumbering::ZeroBased => Some(num),
+            };
+            return index
+                .filter(|&index| index < self.pedal_count)
+                .ok_or(PedalError::InvalidPedalIndex(num, self.pedal_count));
+        }
+
+        self.find_pedal_by_name(selector)
+            .ok_or_else(|| PedalError::InvalidPedalSelector(selector.to_string()))
+    }
+
+    /// Resolve a selector that may also be [`ALL_PEDALS`] to 0-based pedal indices
+    ///
+    /// A pedal that is literally named "all" takes precedence over the wildcard,
+    /// so such a device can still address that one pedal.
+    pub fn resolve_pedals(&self, selector: &str) -> Result<Vec<usize>> {
+        self.resolve_pedals_numbered(selector, PedalNumbering::OneBased)
+    }
+
+    /// [`resolve_pedals`](Self::resolve_pedals) with a choice of numbering
+    pub fn resolve_pedals_numbered(&self, selector: &str, numbering: PedalNumbering) -> Result<Vec<usize>> {
+        if selector.eq_ignore_ascii_case(ALL_PEDALS) && self.find_pedal_by_name(selector).is_none() {
+            return Ok((0..self.pedal_count).collect());
+        }
+
+        self.resolve_pedal_numbered(selector, numbering).map(|index| vec![index])
+    }
+}
+
+/// Device-wide settings, beyond the per-pedal configuration
+///
+/// A field is `None` when the model doesn't expose that setting. When writing,
+/// `None` leaves the setting unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GlobalSettings {
+    /// Pedal debounce time in milliseconds
+    pub debounce_ms: Option<u16>,
+}
+
+/// Persistent behaviour of a device's LED
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LedMode {
+    Off,
+    On,
+    /// Off, lighting up while a pedal is pressed
+    BlinkOnPress,
+}
+
+impl LedMode {
+    /// Lowercase name, as used on the command line
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedMode::Off => "off",
+            LedMode::On => "on",
+            LedMode::BlinkOnPress => "blink",
+        }
+    }
+}
+
+impl std::fmt::Display for LedMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for LedMode {
+    type Err = PedalError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [LedMode::Off, LedMode::On, LedMode::BlinkOnPress]
+            .into_iter()
+            .find(|mode| mode.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| PedalError::ParseError(format!(
+                "Unknown LED mode '{}' (expected on, off or blink)", s
+            )))
+    }
 }
 
 /// Trait for pedal devices
@@ -41,6 +228,9 @@ pub trait PedalDevice {
     /// Get device ID
     fn id(&self) -> usize;
 
+    /// Get the USB `(vendor_id, product_id)` the device was opened with
+    fn usb_ids(&self) -> (u16, u16);
+
     /// Get device model name
     fn model(&self) -> &str;
 
@@ -54,17 +244,323 @@ pub trait PedalDevice {
     fn load_configuration(&mut self) -> Result<()>;
 
     /// Save configuration to device
+    ///
+    /// Does nothing when no pedal was modified since the last load or save.
+    /// Neither protocol has a separate apply or save-to-flash command: the
+    /// firmware persists each write as it arrives, so a saved configuration
+    /// survives unplugging once this returns `Ok`. A save that fails part way
+    /// (e.g. because the pedal was unplugged) can leave some pedals written
+    /// and others not; reload to see what the device holds.
     fn save_configuration(&mut self) -> Result<()>;
 
     /// Get pedal configuration
     fn get_pedal_configuration(&self, pedal_index: usize) -> Result<Configuration>;
 
     /// Set pedal configuration
+    ///
+    /// The pedal only counts as modified if `config` differs from its current
+    /// configuration, so load the configuration first. Rewriting a pedal with
+    /// what it already holds would only wear the flash, so drivers skip it.
     fn set_pedal_configuration(&mut self, pedal_index: usize, config: Configuration) -> Result<()>;
 
+    /// Configuration type of every pedal, as last loaded
+    ///
+    /// `None` marks an unconfigured pedal, a raw configuration clutchctl doesn't
+    /// model, or a pedal whose configuration can't be read.
+    fn config_summary(&self) -> Vec<Option<ConfigurationType>> {
+        (0..self.capabilities().pedal_count)
+            .map(|i| {
+                self.get_pedal_configuration(i)
+                    .ok()
+                    .and_then(|config| config.configuration_type())
+            })
+            .collect()
+    }
+
+    /// Get the bytes of a pedal's configuration as last read from the device
+    ///
+    /// iKKEGOL devices return the 40-byte config packet; PCsensor devices the
+    /// reports they answered with, zero-padded. All zeros before the first load.
+    /// The default returns `PedalError::UnsupportedDevice`.
+    fn get_pedal_raw(&self, _pedal_index: usize) -> Result<[u8; 40]> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "{} does not keep raw configuration bytes",
+            self.model()
+        )))
+    }
+
+    /// Get the trigger the device holds for a pedal
+    ///
+    /// This is the trigger-mode table entry on iKKEGOL devices, which exists
+    /// even for unconfigured pedals. The default takes the trigger of the
+    /// pedal's configuration, and on press for an unconfigured pedal.
+    fn get_pedal_trigger(&self, pedal_index: usize) -> Result<Trigger> {
+        Ok(self.get_pedal_configuration(pedal_index)?.trigger().unwrap_or(Trigger::OnPress))
+    }
+
+    /// Parse bytes in the layout of [`get_pedal_raw`](Self::get_pedal_raw)
+    ///
+    /// The trigger isn't part of the bytes, so the result triggers on press.
+    /// The default returns `PedalError::UnsupportedDevice`.
+    fn parse_pedal_raw(&self, _raw: &[u8; 40]) -> Result<Configuration> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "{} does not parse raw configuration bytes",
+            self.model()
+        )))
+    }
+
+    /// HID reports a save would write to put `config` on one pedal
+    ///
+    /// Nothing is sent, so a dry run can show the exact bytes. A trigger kept
+    /// in a separate table (iKKEGOL) isn't part of the reports. The default
+    /// returns `PedalError::UnsupportedDevice`.
+    fn encode_pedal_reports(&self, _pedal_index: usize, _config: &Configuration) -> Result<Vec<[u8; 8]>> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "{} does not encode pedal reports",
+            self.model()
+        )))
+    }
+
     /// Check if any configuration has been modified
     fn has_modifications(&self) -> bool;
 
     /// Get last error message if any
     fn last_error(&self) -> Option<&str>;
-}
\ No newline at end of file
+
+    /// Return the device to normal operation after configuration reads or writes
+    ///
+    /// Devices that need this call it at the end of `load_configuration` and
+    /// `save_configuration`, even when those fail. The default does nothing.
+    fn exit_config_mode(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Read the device-wide settings
+    ///
+    /// None of the supported protocols has a known command for global settings
+    /// (command codes such as 0x84 are unused but undocumented), so the default
+    /// returns `PedalError::UnsupportedDevice`.
+    fn get_global_settings(&self) -> Result<GlobalSettings> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "{} has no readable global settings",
+            self.model()
+        )))
+    }
+
+    /// Write the device-wide settings; `None` fields are left unchanged
+    ///
+    /// The default returns `PedalError::UnsupportedDevice`.
+    fn set_global_settings(&mut self, _settings: &GlobalSettings) -> Result<()> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "{} has no writable global settings",
+            self.model()
+        )))
+    }
+
+    /// Set every pedal to trigger on press, keeping its configuration, and save
+    ///
+    /// Call on a loaded device. The default rewrites the pedals that trigger on
+    /// release through [`set_pedal_configuration`](Self::set_pedal_configuration)
+    /// and saves; models that keep triggers apart from the pedal configuration
+    /// write only that.
+    fn reset_triggers(&mut self) -> Result<()> {
+        for i in 0..self.capabilities().pedal_count {
+            let mut config = self.get_pedal_configuration(i)?;
+            if config.trigger() == Some(Trigger::OnRelease) {
+                config.set_trigger(Trigger::OnPress);
+                self.set_pedal_configuration(i, config)?;
+            }
+        }
+        self.save_configuration()
+    }
+
+    /// Read the persistent LED mode
+    ///
+    /// None of the supported protocols has a known LED command, so no model
+    /// overrides the default, which returns `PedalError::UnsupportedDevice`.
+    fn get_led_mode(&self) -> Result<LedMode> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "{} has no controllable LED",
+            self.model()
+        )))
+    }
+
+    /// Write the persistent LED mode
+    ///
+    /// The default returns `PedalError::UnsupportedDevice`.
+    fn set_led_mode(&mut self, _mode: LedMode) -> Result<()> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "{} has no controllable LED",
+            self.model()
+        )))
+    }
+
+    /// Configuration the device shipped with, one entry per pedal
+    ///
+    /// Unlike unconfiguring every pedal, this restores the vendor's
+    /// out-of-box behaviour. Models whose defaults aren't known return
+    /// `PedalError::UnsupportedDevice`; that is the default, and no model's
+    /// defaults have been read back from a never-reconfigured unit yet.
+    fn factory_defaults(&self) -> Result<Vec<Configuration>> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "factory defaults of {} are not known",
+            self.model()
+        )))
+    }
+
+    /// Wait up to `timeout_ms` for a pedal press or release
+    ///
+    /// Returns `Ok(None)` on timeout. Pedal key presses are delivered on the
+    /// keyboard interface, which the OS owns, so devices that can't observe them
+    /// return `PedalError::UnsupportedDevice`; that is the default.
+    ///
+    /// Scaffolding for host-side press handling: no device overrides this yet.
+    fn poll_event(&self, _timeout_ms: i32) -> Result<Option<PedalEvent>> {
+        Err(PedalError::UnsupportedDevice(format!(
+            "{} does not report pedal events",
+            self.model()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::text::MAX_TEXT_BYTES;
+
+    fn three_pedals() -> DeviceCapabilities {
+        DeviceCapabilities {
+            pedal_count: 3,
+            first_pedal_index: 0,
+            pedal_names: vec![
+                "left".to_string(),
+                "middle".to_string(),
+                "right".to_string(),
+            ],
+            supported_triggers: vec![Trigger::OnPress, Trigger::OnRelease],
+            supported_config_types: ConfigurationType::all().to_vec(),
+            supports_mouse_axis: true,
+            max_text_length: MAX_TEXT_BYTES,
+            physical_order: vec![0, 1, 2],
+        }
+    }
+
+    #[test]
+    fn test_led_mode() {
+        assert_eq!("Blink".parse::<LedMode>().unwrap(), LedMode::BlinkOnPress);
+        assert_eq!("off".parse::<LedMode>().unwrap(), LedMode::Off);
+        assert!("dim".parse::<LedMode>().is_err());
+
+        let mut device = crate::device::MockDevice::new(0, 3).unwrap();
+        assert!(matches!(device.get_led_mode(), Err(PedalError::UnsupportedDevice(_))));
+        assert!(matches!(device.set_led_mode(LedMode::On), Err(PedalError::UnsupportedDevice(_))));
+    }
+
+    #[test]
+    fn test_max_text_length() {
+        let text = TextConfiguration::new("a".repeat(MAX_TEXT_BYTES + 2));
+        assert!(three_pedals().validate_text(&text).is_err());
+
+        let capabilities = DeviceCapabilities {
+            max_text_length: MAX_TEXT_BYTES + 2,
+            ..three_pedals()
+        };
+        assert!(capabilities.validate_text(&text).is_ok());
+        let longer = TextConfiguration::new("a".repeat(MAX_TEXT_BYTES + 3));
+        assert!(matches!(
+            capabilities.validate_text(&longer),
+            Err(PedalError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn test_pedal_log_label() {
+        assert_eq!(three_pedals().pedal_log_label(0), "pedal 1 (slot 0, wire 0x01)");
+        let offset = DeviceCapabilities { pedal_count: 1, first_pedal_index: 1, ..three_pedals() };
+        assert_eq!(offset.pedal_log_label(0), "pedal 1 (slot 1, wire 0x02)");
+        assert_eq!(offset.pedal_log_label(1), "pedal 2 (no protocol slot)");
+    }
+
+    #[test]
+    fn test_physical_order() {
+        assert_eq!(three_pedals().physical_order, vec![0, 1, 2]);
+        assert_eq!(three_pedals().with_physical_order(&[2, 0, 1]).physical_order, vec![2, 0, 1]);
+
+        // Orders that skip or repeat a pedal are ignored
+        assert_eq!(three_pedals().with_physical_order(&[0, 1]).physical_order, vec![0, 1, 2]);
+        assert_eq!(three_pedals().with_physical_order(&[0, 0, 1]).physical_order, vec![0, 1, 2]);
+        assert_eq!(three_pedals().with_physical_order(&[1, 2, 3]).physical_order, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_custom_names_resolve() {
+        let names = vec!["top".to_string(), "center".to_string()];
+        let capabilities = three_pedals().with_names(&names);
+
+        assert_eq!(capabilities.find_pedal_by_name("Top"), Some(0));
+        assert_eq!(capabilities.find_pedal_by_name("center"), Some(1));
+        assert_eq!(capabilities.find_pedal_by_name("left"), None);
+        // Pedals past the override keep their default name
+        assert_eq!(capabilities.find_pedal_by_name("right"), Some(2));
+        assert_eq!(capabilities.resolve_pedals("top").unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_resolve_pedal_by_index() {
+        let capabilities = three_pedals();
+
+        assert_eq!(capabilities.resolve_pedal("1").unwrap(), 0);
+        assert_eq!(capabilities.resolve_pedal("3").unwrap(), 2);
+        assert!(matches!(
+            capabilities.resolve_pedal("0"),
+            Err(PedalError::InvalidPedalIndex(0, 3))
+        ));
+        assert!(matches!(
+            capabilities.resolve_pedal("4"),
+            Err(PedalError::InvalidPedalIndex(4, 3))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_pedal_zero_based() {
+        let capabilities = three_pedals();
+        let zero = PedalNumbering::ZeroBased;
+
+        assert_eq!(capabilities.resolve_pedal_numbered("0", zero).unwrap(), 0);
+        assert_eq!(capabilities.resolve_pedal_numbered("2", zero).unwrap(), 2);
+        assert!(matches!(
+            capabilities.resolve_pedal_numbered("3", zero),
+            Err(PedalError::InvalidPedalIndex(3, 3))
+        ));
+        // Names and "all" don't depend on the numbering
+        assert_eq!(capabilities.resolve_pedal_numbered("middle", zero).unwrap(), 1);
+        assert_eq!(capabilities.resolve_pedals_numbered("all", zero).unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_resolve_pedal_by_name() {
+        let capabilities = three_pedals();
+
+        assert_eq!(capabilities.resolve_pedal("left").unwrap(), 0);
+        assert_eq!(capabilities.resolve_pedal("Middle").unwrap(), 1);
+        assert_eq!(capabilities.resolve_pedal("RIGHT").unwrap(), 2);
+        assert!(matches!(
+            capabilities.resolve_pedal("pedal"),
+            Err(PedalError::InvalidPedalSelector(s)) if s == "pedal"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_all_pedals() {
+        let mut capabilities = three_pedals();
+
+        assert_eq!(capabilities.resolve_pedals("all").unwrap(), vec![0, 1, 2]);
+        assert_eq!(capabilities.resolve_pedals("ALL").unwrap(), vec![0, 1, 2]);
+        assert_eq!(capabilities.resolve_pedals("middle").unwrap(), vec![1]);
+        assert_eq!(capabilities.resolve_pedals("3").unwrap(), vec![2]);
+
+        // A pedal named "all" wins over the wildcard
+        capabilities.pedal_names[2] = "all".to_string();
+        assert_eq!(capabilities.resolve_pedals("all").unwrap(), vec![2]);
+    }
+}
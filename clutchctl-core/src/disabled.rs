@@ -0,0 +1,203 @@
+//! Pedals disabled without losing their configuration (requires the `serialization` feature)
+//!
+//! None of the supported models has an enable bit in its firmware, so
+//! disabling is emulated on the host: the pedal is written as unconfigured and
+//! its configuration is kept in a store file until it is enabled again. The
+//! device itself only ever holds the unconfigured pedal, so another computer
+//! (or a lost store file) can't re-enable it.
+//!
+//! Store files live in the clutchctl config directory as
+//! `disabled-<key>.json`, in the export format with 1-based pedal indices as
+//! keys. The key should identify the physical device, e.g. its serial number
+//! (see [`store_key`]), since device IDs shift when devices are plugged in.
+
+use crate::config_file::{ConfigFile, PedalEntry, PedalSpec};
+use crate::configuration::Configuration;
+use crate::device::PedalDevice;
+use crate::error::{PedalError, Result};
+use crate::usb::HidDeviceInfo;
+use crate::user_config::config_dir;
+use std::path::{Path, PathBuf};
+
+/// Store key of a device: its serial number, or its device ID without one
+pub fn store_key(info: &HidDeviceInfo, device_id: usize) -> String {
+    match info.serial_number.as_deref().filter(|serial| !serial.is_empty()) {
+        Some(serial) => serial
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect(),
+        None => format!("id{}", device_id),
+    }
+}
+
+/// Configurations of the disabled pedals of one device
+#[derive(Debug)]
+pub struct DisabledPedals {
+    path: PathBuf,
+    file: ConfigFile,
+}
+
+impl DisabledPedals {
+    /// Read the store of the device with `key` from the clutchctl config directory
+    pub fn load(key: &str) -> Result<Self> {
+        let dir = config_dir().ok_or_else(|| {
+            PedalError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "cannot determine the clutchctl config directory",
+            ))
+        })?;
+        Self::load_from(&dir.join(format!("disabled-{}.json", key)))
+    }
+
+    /// Read a store file; a missing file is an empty store
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let file = match std::fs::read_to_string(path) {
+            Ok(contents) => ConfigFile::from_json(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ConfigFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path: path.to_path_buf(), file })
+    }
+
+    /// Path of the store file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether a pedal (0-based) is disabled
+    pub fn is_disabled(&self, pedal_index: usize) -> bool {
+        self.file.pedals.contains_key(&key(pedal_index))
+    }
+
+    /// Disabled pedals, 0-based and in ascending order
+    pub fn disabled(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self.file.pedals.keys()
+            .filter_map(|key| key.parse::<usize>().ok())
+            .filter_map(|number| number.checked_sub(1))
+            .collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// Keep a loaded device's pedal configuration and set the pedal unconfigured
+    ///
+    /// Neither the device nor the store is saved. Save the store first, so a
+    /// failed device save leaves a configuration that can still be restored.
+    pub fn disable(&mut self, device: &mut dyn PedalDevice, pedal_index: usize) -> Result<()> {
+        if self.is_disabled(pedal_index) {
+            return Err(PedalError::InvalidConfiguration(format!(
+                "pedal {} is already disabled", pedal_index + 1
+            )));
+        }
+        self.check_model(device)?;
+
+        let config = device.get_pedal_configuration(pedal_index)?;
+        device.set_pedal_configuration(pedal_index, Configuration::Unconfigured)?;
+
+        self.file.model = Some(device.model().to_string());
+        self.file.pedals.insert(key(pedal_index), PedalEntry::Config(PedalSpec::from_configuration(&config)));
+        Ok(())
+    }
+
+    /// Set a disabled pedal of a loaded device back to its kept configuration
+    ///
+    /// This replaces whatever the pedal was set to while disabled. Neither the
+    /// device nor the store is saved; save the device first.
+    pub fn enable(&mut self, device: &mut dyn PedalDevice, pedal_index: usize) -> Result<Configuration> {
+        self.check_model(device)?;
+
+        let config = match self.file.pedals.get(&key(pedal_index)) {
+            Some(PedalEntry::Config(spec)) => spec.to_configuration()?,
+            Some(PedalEntry::Keyword(keyword)) => {
+                return Err(PedalError::InvalidConfiguration(format!(
+                    "Unknown keyword '{}' for pedal {}", keyword, pedal_index + 1
+                )))
+            }
+            None => {
+                return Err(PedalError::InvalidConfiguration(format!(
+                    "pedal {} is not disabled", pedal_index + 1
+                )))
+            }
+        };
+
+        device.set_pedal_configuration(pedal_index, config.clone())?;
+        self.file.pedals.remove(&key(pedal_index));
+        Ok(config)
+    }
+
+    /// Write the store, removing the file once no pedal is disabled
+    pub fn save(&self) -> Result<()> {
+        if self.file.pedals.is_empty() {
+            return match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, self.file.to_json()? + "\n")?;
+        Ok(())
+    }
+
+    /// Refuse a store written for another model
+    fn check_model(&self, device: &dyn PedalDevice) -> Result<()> {
+        match &self.file.model {
+            Some(model) if model != device.model() => Err(PedalError::InvalidConfiguration(format!(
+                "{} holds pedals disabled on {}, but the device is {}",
+                self.path.display(), model, device.model()
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Store key of a pedal
+fn key(pedal_index: usize) -> String {
+    (pedal_index + 1).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::TextConfiguration;
+    use crate::device::MockDevice;
+
+    #[test]
+    fn test_disable_then_enable() {
+        let path = std::env::temp_dir()
+            .join(format!("clutchctl-disabled-test-{}", std::process::id()))
+            .join("disabled-test.json");
+        let mut device = MockDevice::new(0, 3).unwrap();
+        let config = Configuration::Text(TextConfiguration::new("hello".to_string()));
+        device.set_pedal_configuration(1, config.clone()).unwrap();
+
+        let mut store = DisabledPedals::load_from(&path).unwrap();
+        store.disable(&mut device, 1).unwrap();
+        assert_eq!(device.get_pedal_configuration(1).unwrap(), Configuration::Unconfigured);
+        assert!(store.disable(&mut device, 1).is_err());
+        store.save().unwrap();
+
+        // The kept configuration survives a reload
+        let mut store = DisabledPedals::load_from(&path).unwrap();
+        assert_eq!(store.disabled(), vec![1]);
+        assert_eq!(store.enable(&mut device, 1).unwrap(), config);
+        assert_eq!(device.get_pedal_configuration(1).unwrap(), config);
+        assert!(store.enable(&mut device, 1).is_err());
+
+        store.save().unwrap();
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_store_key() {
+        let mut info = crate::device::mock::simulated_info();
+        assert_eq!(store_key(&info, 2), "SIMULATED");
+        info.serial_number = Some("ab/cd 1".to_string());
+        assert_eq!(store_key(&info, 2), "ab_cd_1");
+        info.serial_number = None;
+        assert_eq!(store_key(&info, 2), "id2");
+    }
+}
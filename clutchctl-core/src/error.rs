@@ -9,6 +9,10 @@ pub enum PedalError {
     #[error("HID error: {0}")]
     Hid(String),
 
+    /// Error reported by hidapi, kept as the source so error chains show it
+    #[error("HID error")]
+    HidSource(#[source] hidapi::HidError),
+
     /// Device not found
     #[error("Device not found with ID {0}")]
     DeviceNotFound(usize),
@@ -25,6 +29,10 @@ pub enum PedalError {
     #[error("Invalid pedal index {0} for device with {1} pedals")]
     InvalidPedalIndex(usize, usize),
 
+    /// Pedal selector matches neither an index nor a pedal name
+    #[error("Unknown pedal '{0}'")]
+    InvalidPedalSelector(String),
+
     /// Invalid configuration
     #[error("Invalid configuration: {0}")]
     InvalidConfiguration(String),
@@ -37,6 +45,10 @@ pub enum PedalError {
     #[error("USB communication timeout")]
     Timeout,
 
+    /// Device unplugged while it was open
+    #[error("Device disconnected")]
+    DeviceDisconnected,
+
     /// Device busy or in use
     #[error("Device busy or in use by another application")]
     DeviceBusy,
@@ -52,6 +64,42 @@ pub enum PedalError {
     /// Unsupported device
     #[error("Unsupported device: {0}")]
     UnsupportedDevice(String),
+
+    /// Reading or writing one pedal failed; `index` is 0-based
+    #[error("Failed {kind} pedal {}", .index + 1)]
+    PedalOperation {
+        index: usize,
+        kind: PedalOperationKind,
+        #[source]
+        source: Box<PedalError>,
+    },
+}
+
+/// Direction of a failed per-pedal transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PedalOperationKind {
+    Read,
+    Write,
+}
+
+impl std::fmt::Display for PedalOperationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read => write!(f, "reading"),
+            Self::Write => write!(f, "writing"),
+        }
+    }
+}
+
+impl PedalError {
+    /// Attribute this error to a read or write of a pedal (0-based index)
+    pub fn for_pedal(self, index: usize, kind: PedalOperationKind) -> Self {
+        PedalError::PedalOperation {
+            index,
+            kind,
+            source: Box::new(self),
+        }
+    }
 }
 
 /// Result type alias for PedalError
@@ -60,15 +108,47 @@ pub type Result<T> = std::result::Result<T, PedalError>;
 impl From<hidapi::HidError> for PedalError {
     fn from(err: hidapi::HidError) -> Self {
         let msg = err.to_string();
-        // Try to categorize common errors
-        if msg.contains("Permission denied") || msg.contains("access denied") {
+        let lower = msg.to_lowercase();
+        // Try to categorize common errors; the disconnect wording differs per backend
+        if lower.contains("no such device") || lower.contains("disconnected") || lower.contains("not connected") {
+            PedalError::DeviceDisconnected
+        } else if msg.contains("Permission denied") || msg.contains("access denied") {
             PedalError::PermissionDenied
         } else if msg.contains("timed out") || msg.contains("timeout") {
             PedalError::Timeout
         } else if msg.contains("busy") || msg.contains("in use") {
             PedalError::DeviceBusy
         } else {
-            PedalError::Hid(msg)
+            PedalError::HidSource(err)
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_pedal_operation_names_pedal_and_keeps_source() {
+        let err = PedalError::Timeout.for_pedal(1, PedalOperationKind::Write);
+        assert_eq!(err.to_string(), "Failed writing pedal 2");
+        assert_eq!(err.source().unwrap().to_string(), "USB communication timeout");
+    }
+
+    #[test]
+    fn test_hid_error_keeps_source() {
+        let err = PedalError::from(hidapi::HidError::HidApiError { message: "broken pipe".to_string() });
+        assert!(matches!(err, PedalError::HidSource(_)));
+        assert_eq!(err.source().unwrap().to_string(), "hidapi error: broken pipe");
+
+        // Recognized messages are still categorized
+        let err = PedalError::from(hidapi::HidError::HidApiError { message: "Permission denied".to_string() });
+        assert!(matches!(err, PedalError::PermissionDenied));
+
+        // libusb/hidraw, IOKit and Windows word unplugging differently
+        for message in ["No such device", "hid_read_timeout: device disconnected", "The device is not connected."] {
+            let err = PedalError::from(hidapi::HidError::HidApiError { message: message.to_string() });
+            assert!(matches!(err, PedalError::DeviceDisconnected), "{}", message);
+        }
+    }
+}
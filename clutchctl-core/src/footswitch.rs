@@ -0,0 +1,303 @@
+//! Configurations written for the footswitch tool
+//!
+//! [footswitch](https://github.com/rgerganov/footswitch) is configured with
+//! command lines such as `footswitch -1 -m ctrl -k c -2 -s hello`; users keep
+//! these in shell scripts. [`parse_script`] reads such a script, one
+//! invocation per line. Blank lines, `#` comments, a leading `sudo` and
+//! trailing-backslash continuations are allowed. These options are understood:
+//!
+//! | Option        | Meaning                                               |
+//! |---------------|-------------------------------------------------------|
+//! | `-1` `-2` `-3`| Select the pedal the following options apply to       |
+//! | `-k <key>`    | Set the key, replacing earlier keys                   |
+//! | `-a <key>`    | Add a key                                             |
+//! | `-m <mod>`    | Add a modifier: `ctrl`, `shift`, `alt` or `win`       |
+//! | `-s <text>`   | Append text                                           |
+//! | `-S <codes>`  | Append raw scan codes, hex separated by spaces        |
+//! | `-b <button>` | Add a mouse button: `mouse_left`, `mouse_right`, `mouse_middle` |
+//! | `-x` `-y` `-w`| Move the mouse or wheel by a signed amount            |
+//! | `-r`          | Read the pedals; ignored                              |
+//!
+//! Anything else, including footswitch's double click and configurations
+//! mixing keyboard and mouse, is a `PedalError::ParseError` naming the line.
+//! When a later line programs a pedal again, the later configuration wins.
+
+use crate::configuration::keyboard::KeyMode;
+use crate::configuration::mouse::MouseButton;
+use crate::configuration::{Configuration, KeyboardConfiguration, MouseConfiguration, TextConfiguration};
+use crate::error::{PedalError, Result};
+use crate::protocol::ModifierKeys;
+use std::collections::{BTreeMap, HashSet};
+
+/// What the options given so far set a pedal to
+#[derive(Debug)]
+enum Pending {
+    Nothing,
+    Keys(ModifierKeys, Vec<String>),
+    Text(String),
+    Buttons(HashSet<MouseButton>),
+    Axis(i8, i8, i8),
+}
+
+impl Pending {
+    fn kind(&self) -> &'static str {
+        match self {
+            Pending::Nothing => "nothing",
+            Pending::Keys(..) => "keyboard",
+            Pending::Text(_) => "text",
+            Pending::Buttons(_) => "mouse buttons",
+            Pending::Axis(..) => "mouse movement",
+        }
+    }
+
+    fn into_configuration(self) -> Option<Configuration> {
+        Some(match self {
+            Pending::Nothing => return None,
+            Pending::Keys(modifiers, keys) => Configuration::Keyboard(
+                KeyboardConfiguration::with_modifiers(KeyMode::Standard, keys, modifiers),
+            ),
+            Pending::Text(text) => Configuration::Text(TextConfiguration::new(text)),
+            Pending::Buttons(buttons) => Configuration::Mouse(MouseConfiguration::buttons(buttons)),
+            Pending::Axis(x, y, wheel) => Configuration::Mouse(MouseConfiguration::axis(x, y, wheel)),
+        })
+    }
+}
+
+/// Parse a footswitch script into (0-based pedal index, configuration), by pedal
+pub fn parse_script(script: &str) -> Result<Vec<(usize, Configuration)>> {
+    let mut pedals = BTreeMap::new();
+    let mut joined = String::new();
+    let mut first_line = 0;
+
+    for (i, line) in script.lines().enumerate() {
+        if joined.is_empty() {
+            first_line = i + 1;
+        }
+        match line.strip_suffix('\\') {
+            Some(start) => {
+                joined.push_str(start);
+                joined.push(' ');
+            }
+            None => {
+                joined.push_str(line);
+                parse_line(&joined, &mut pedals)
+                    .map_err(|e| PedalError::ParseError(format!("line {}: {}", first_line, e)))?;
+                joined.clear();
+            }
+        }
+    }
+    if !joined.is_empty() {
+        parse_line(&joined, &mut pedals)
+            .map_err(|e| PedalError::ParseError(format!("line {}: {}", first_line, e)))?;
+    }
+
+    Ok(pedals.into_iter().collect())
+}
+
+/// Apply one footswitch invocation; errors are plain messages for `parse_script` to place
+fn parse_line(line: &str, pedals: &mut BTreeMap<usize, Configuration>) -> std::result::Result<(), String> {
+    let mut words = split_words(line)?.into_iter().peekable();
+    if words.peek().map(String::as_str) == Some("sudo") {
+        words.next();
+    }
+    match words.next() {
+        None => return Ok(()),
+        Some(program) if program == "footswitch" || program.ends_with("/footswitch") => {}
+        Some(other) => return Err(format!("expected a footswitch command, found '{}'", other)),
+    }
+
+    let mut selected: Option<(usize, Pending)> = None;
+    let mut finish = |selected: &mut Option<(usize, Pending)>| {
+        if let Some((pedal, pending)) = selected.take() {
+            if let Some(config) = pending.into_configuration() {
+                pedals.insert(pedal, config);
+            }
+        }
+    };
+
+    while let Some(option) = words.next() {
+        let pedal = match option.as_str() {
+            "-1" => Some(0),
+            "-2" => Some(1),
+            "-3" => Some(2),
+            _ => None,
+        };
+        if let Some(pedal) = pedal {
+            finish(&mut selected);
+            selected = Some((pedal, Pending::Nothing));
+            continue;
+        }
+        if option == "-r" {
+            continue;
+        }
+
+        if !matches!(option.as_str(), "-k" | "-a" | "-m" | "-s" | "-S" | "-b" | "-x" | "-y" | "-w") {
+            return Err(format!("unsupported footswitch option '{}'", option));
+        }
+        let value = words.next().ok_or_else(|| format!("option {} needs a value", option))?;
+        let (pedal, pending) = selected.as_mut()
+            .ok_or_else(|| format!("option {} comes before a pedal (-1, -2 or -3)", option))?;
+        apply_option(pending, &option, &value)
+            .map_err(|e| format!("pedal {}: {}", *pedal + 1, e))?;
+    }
+
+    finish(&mut selected);
+    Ok(())
+}
+
+/// Apply an option that takes a value to the pedal being programmed
+fn apply_option(pending: &mut Pending, option: &str, value: &str) -> std::result::Result<(), String> {
+    if let Pending::Nothing = pending {
+        *pending = match option {
+            "-k" | "-a" | "-m" => Pending::Keys(ModifierKeys::empty(), Vec::new()),
+            "-s" | "-S" => Pending::Text(String::new()),
+            "-b" => Pending::Buttons(HashSet::new()),
+            _ => Pending::Axis(0, 0, 0),
+        };
+    }
+
+    match (pending, option) {
+        (Pending::Keys(modifiers, keys), "-k" | "-a") => {
+            let (key_modifiers, mut main_keys) = KeyboardConfiguration::parse_keys(value)
+                .map_err(|e| e.to_string())?;
+            if option == "-k" {
+                keys.clear();
+            }
+            *modifiers |= key_modifiers;
+            keys.append(&mut main_keys);
+        }
+        (Pending::Keys(modifiers, _), "-m") => {
+            *modifiers |= match value {
+                "ctrl" => ModifierKeys::LEFT_CONTROL,
+                "shift" => ModifierKeys::LEFT_SHIFT,
+                "alt" => ModifierKeys::LEFT_ALT,
+                "win" => ModifierKeys::LEFT_SUPER,
+                other => return Err(format!("unknown modifier '{}' (expected ctrl, shift, alt or win)", other)),
+            };
+        }
+        (Pending::Text(text), "-s") => text.push_str(value),
+        (Pending::Text(text), "-S") => {
+            let codes = value.split_whitespace()
+                .map(|code| {
+                    let digits = code.trim_start_matches("0x").trim_start_matches("0X");
+                    u8::from_str_radix(digits, 16).map_err(|_| format!("invalid scan code '{}'", code))
+                })
+                .collect::<std::result::Result<Vec<u8>, String>>()?;
+            text.push_str(&TextConfiguration::decode_scan_codes(&codes));
+        }
+        (Pending::Buttons(buttons), "-b") => {
+            buttons.insert(match value {
+                "mouse_left" => MouseButton::Left,
+                "mouse_right" => MouseButton::Right,
+                "mouse_middle" => MouseButton::Middle,
+                "mouse_double" => return Err("double click isn't supported".to_string()),
+                other => return Err(format!("unknown mouse button '{}'", other)),
+            });
+        }
+        (Pending::Axis(x, y, wheel), "-x" | "-y" | "-w") => {
+            let amount: i8 = value.parse()
+                .map_err(|_| format!("invalid amount '{}' for {} (expected -128 to 127)", value, option))?;
+            match option {
+                "-x" => *x = amount,
+                "-y" => *y = amount,
+                _ => *wheel = amount,
+            }
+        }
+        (other, _) => {
+            return Err(format!("{} can't be combined with {} on one pedal", option, other.kind()))
+        }
+    }
+    Ok(())
+}
+
+/// Split a command line into words, following shell quoting
+///
+/// Single quotes are literal, double quotes allow `\"` and `\\`, a backslash
+/// outside quotes escapes the next character, and `#` at the start of a word
+/// begins a comment.
+fn split_words(line: &str) -> std::result::Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '#' if word.is_none() => break,
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.next());
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fixture() {
+        let pedals = parse_script(include_str!("../tests/fixtures/footswitch.sh")).unwrap();
+        let rendered: Vec<(usize, String)> = pedals.iter()
+            .map(|(pedal, config)| (*pedal, config.to_string()))
+            .collect();
+        assert_eq!(rendered, vec![
+            (0, "Keyboard: LCtrl+LShift+t".to_string()),
+            (1, "Text: \"git status\\n\"".to_string()),
+            (2, "Mouse: axis(0, 0, -3)".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_rejects_unsupported() {
+        for script in [
+            "footswitch -1 -b mouse_double",
+            "footswitch -1 -k a -b mouse_left",
+            "footswitch -k a",
+            "footswitch -1 -m hyper",
+            "footswitch -1 -k ctlr",
+            "footswitch -1 -z",
+            "pedalctl -1 -k a",
+            "footswitch -1 -s 'open",
+        ] {
+            assert!(parse_script(script).is_err(), "{}", script);
+        }
+
+        let error = parse_script("\nfootswitch -1 -x 200").unwrap_err().to_string();
+        assert!(error.contains("line 2"), "{}", error);
+    }
+}
@@ -3,11 +3,19 @@
 //! This library provides the core functionality for discovering, configuring,
 //! and communicating with USB HID pedal devices, particularly iKKEGOL models.
 
+#[cfg(feature = "serialization")]
+pub mod config_file;
+pub mod backup;
 pub mod configuration;
 pub mod device;
+#[cfg(feature = "serialization")]
+pub mod disabled;
 pub mod error;
+pub mod footswitch;
+pub mod prelude;
 pub mod protocol;
 pub mod usb;
+pub mod user_config;
 
 // Re-export commonly used types
 pub use error::{PedalError, Result};
@@ -0,0 +1,20 @@
+//! Types most users of the library need, for glob import
+//!
+//! ```no_run
+//! use clutchctl_core::prelude::*;
+//!
+//! for device in discover_devices()? {
+//!     println!("{} has {} pedal(s)", device.model(), device.capabilities().pedal_count);
+//! }
+//! # Ok::<(), PedalError>(())
+//! ```
+//!
+//! `Result` is left out so that it doesn't shadow `std::result::Result`; use
+//! [`crate::Result`] for the library's alias.
+
+pub use crate::configuration::keyboard::KeyMode;
+pub use crate::configuration::mouse::MouseButton;
+pub use crate::configuration::{Configuration, ConfigurationType, Trigger};
+pub use crate::device::{discover_devices, DeviceCapabilities, PedalDevice};
+pub use crate::error::PedalError;
+pub use crate::protocol::{GameKey, MediaButton};
@@ -3,9 +3,62 @@
 //! Based on the USB HID Usage Tables specification
 //! See: http://www.freebsddiary.org/APC/usb_hid_usages.php
 
+use crate::error::{PedalError, Result};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 
+/// High bit the firmware uses to type a scan code with shift held
+pub const SHIFT_FLAG: u8 = 0x80;
+
+/// Parse a hex scan code such as `0x2c`
+///
+/// Returns `None` for keys without the `0x` prefix, which are looked up by
+/// name instead. A prefix without valid hex digits is an error rather than an
+/// unassigned key.
+pub fn parse_hex_scan_code(key: &str) -> Option<Result<u8>> {
+    let hex = key.strip_prefix("0x")?;
+    Some(u8::from_str_radix(hex, 16).map_err(|_| {
+        PedalError::InvalidConfiguration(format!(
+            "Invalid hex scan code '{}': expected 0x00 to 0xff",
+            key
+        ))
+    }))
+}
+
+/// Prefix of a key given as a raw USB HID usage code, e.g. `raw:0x68`
+pub const RAW_KEY_PREFIX: &str = "raw:";
+
+/// Parse a raw usage code such as `raw:0x68`, bypassing the keymap
+///
+/// Returns `None` for keys without the `raw:` prefix. The code must be a
+/// plausible keyboard usage: 0x00-0x03 are the "no key" and error codes, and
+/// 0xe0-0xe7 are the modifiers, which are given by name instead. Codes from
+/// 0x80 are typed with shift held (see [`SHIFT_FLAG`]).
+pub fn parse_raw_key(key: &str) -> Option<Result<u8>> {
+    let code = key.strip_prefix(RAW_KEY_PREFIX)?;
+    let parsed = code.strip_prefix("0x")
+        .or_else(|| code.strip_prefix("0X"))
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    Some(match parsed {
+        Some(code @ (0x00..=0x03 | 0xe0..=0xe7)) => Err(PedalError::InvalidConfiguration(format!(
+            "'{}' is not a keyboard usage code (0x{:02x} is {})",
+            key, code, if code <= 0x03 { "reserved" } else { "a modifier; give it by name" }
+        ))),
+        Some(code) => Ok(code),
+        None => Err(PedalError::InvalidConfiguration(format!(
+            "Invalid raw usage code '{}': expected raw:0x04 to raw:0xff", key
+        ))),
+    })
+}
+
+/// Name of a scan code: its keymap name, or `raw:0xNN` for codes the keymap lacks
+pub fn key_name(code: u8) -> String {
+    match HID_KEYMAP.decode_key(code) {
+        Some(name) => name.to_string(),
+        None => format!("{}0x{:02x}", RAW_KEY_PREFIX, code),
+    }
+}
+
 /// Static keymap table with all HID key mappings
 static KEYMAP_TABLE: &[(&str, u8)] = &[
     // Control codes
@@ -136,6 +189,7 @@ static KEYMAP_TABLE: &[(&str, u8)] = &[
     ("less", 0x64),
     ("multi_key", 0x65),
     ("compose", 0x65),
+    ("application", 0x65),
 
     // Extended function keys F13-F24 (0x68-0x73)
     ("f13", 0x68),
@@ -151,12 +205,25 @@ static KEYMAP_TABLE: &[(&str, u8)] = &[
     ("f23", 0x72),
     ("f24", 0x73),
 
+    // Command keys (0x74-0x7e)
+    ("execute", 0x74),
+    ("help", 0x75),
+    ("menu", 0x76),
+    ("select", 0x77),
+    ("stop", 0x78),
+    ("again", 0x79),
+    ("undo", 0x7a),
+    ("cut", 0x7b),
+    ("copy", 0x7c),
+    ("paste", 0x7d),
+    ("find", 0x7e),
+
     // Media keys
     ("xf86audiomute", 0x7f),
     ("xf86audioraisevolume", 0x80),
     ("xf86audiolowervolume", 0x81),
 
-    // Uppercase letters A-Z (0x84-0x9d) - shifted versions
+    // Uppercase letters A-Z (0x84-0x9d) - shifted versions, base code | SHIFT_FLAG
     ("A", 0x84),
     ("B", 0x85),
     ("C", 0x86),
@@ -246,8 +313,29 @@ static KEYMAP_TABLE: &[(&str, u8)] = &[
     ("xf86calculator", 0xfb),
 ];
 
+/// Extra spellings accepted when encoding, as (alias, name in `KEYMAP_TABLE`)
+///
+/// Aliases are kept out of the table so that decoding keeps returning the
+/// table's name even when an alias is shorter (`delete`, not `del`).
+static KEY_ALIASES: &[(&str, &str)] = &[
+    ("ret", "enter"),
+    ("bs", "backspace"),
+    ("caps", "capslock"),
+    ("pscr", "printscreen"),
+    ("prtsc", "printscreen"),
+    ("ins", "insert"),
+    ("del", "delete"),
+    ("pgup", "pageup"),
+    ("pgdn", "pagedown"),
+    ("pgdown", "pagedown"),
+    ("arrowup", "up"),
+    ("arrowdown", "down"),
+    ("arrowleft", "left"),
+    ("arrowright", "right"),
+];
+
 /// Lazy-initialized lookup maps for efficient key name <-> scan code conversion
-pub static HID_KEYMAP: Lazy<HidKeymap> = Lazy::new(|| HidKeymap::new());
+pub static HID_KEYMAP: Lazy<HidKeymap> = Lazy::new(HidKeymap::new);
 
 /// HID keymap for bidirectional key name <-> scan code conversion
 pub struct HidKeymap {
@@ -291,6 +379,12 @@ impl HidKeymap {
                 .or_insert(name);
         }
 
+        for &(alias, name) in KEY_ALIASES {
+            if let Some(&code) = name_to_code.get(name) {
+                name_to_code.insert(alias.to_string(), code);
+            }
+        }
+
         Self {
             name_to_code,
             code_to_name,
@@ -308,6 +402,7 @@ impl HidKeymap {
     ///
     /// # Examples
     /// ```
+    /// # use clutchctl_core::protocol::HID_KEYMAP;
     /// assert_eq!(HID_KEYMAP.encode_key("a"), Some(0x04));
     /// assert_eq!(HID_KEYMAP.encode_key("A"), Some(0x84));
     /// assert_eq!(HID_KEYMAP.encode_key("F5"), Some(0x3e));
@@ -323,6 +418,18 @@ impl HidKeymap {
         self.name_to_code.get(&name.to_lowercase()).copied()
     }
 
+    /// Every key name in the keymap, in table order, followed by the aliases
+    ///
+    /// Placeholder names for unassigned codes (`<00>` etc.) are left out.
+    pub fn all_key_names(&self) -> Vec<&'static str> {
+        KEYMAP_TABLE
+            .iter()
+            .map(|&(name, _)| name)
+            .filter(|name| !(name.len() > 1 && name.starts_with('<')))
+            .chain(KEY_ALIASES.iter().map(|&(alias, _)| alias))
+            .collect()
+    }
+
     /// Convert a USB HID scan code to its key name
     ///
     /// # Arguments
@@ -334,6 +441,7 @@ impl HidKeymap {
     ///
     /// # Examples
     /// ```
+    /// # use clutchctl_core::protocol::HID_KEYMAP;
     /// assert_eq!(HID_KEYMAP.decode_key(0x04), Some("a"));
     /// assert_eq!(HID_KEYMAP.decode_key(0x3e), Some("f5"));
     /// assert_eq!(HID_KEYMAP.decode_key(0x28), Some("enter"));
@@ -356,14 +464,15 @@ impl HidKeymap {
     ///
     /// # Examples
     /// ```
+    /// # use clutchctl_core::protocol::HID_KEYMAP;
     /// assert_eq!(HID_KEYMAP.encode_char('a'), Some(0x04));
     /// assert_eq!(HID_KEYMAP.encode_char('A'), Some(0x84));
     /// assert_eq!(HID_KEYMAP.encode_char(' '), Some(0x2c));
     /// assert_eq!(HID_KEYMAP.encode_char('!'), Some(0x9e));
     /// ```
     pub fn encode_char(&self, ch: char) -> Option<u8> {
-        let key = ch.to_string();
-        self.name_to_code.get(&key.to_lowercase()).copied()
+        // Exact lookup only: 'A' must map to its shifted code, not to 'a'
+        self.name_to_code.get(ch.to_string().as_str()).copied()
     }
 
     /// Check if a character requires shift modifier
@@ -375,7 +484,30 @@ impl HidKeymap {
     /// * `true` if the character requires shift modifier
     /// * `false` otherwise
     pub fn requires_shift(&self, ch: char) -> bool {
-        matches!(self.encode_char(ch), Some(code) if code >= 0x84)
+        matches!(self.encode_char_with_shift(ch), Some((_, true)))
+    }
+
+    /// Convert a character to its base USB HID scan code and whether shift is needed
+    ///
+    /// Text configurations are typed by the firmware one scan code at a time with
+    /// no separate modifier byte; a shifted character is sent as its base code
+    /// with [`SHIFT_FLAG`] set (e.g. `!` is `0x1e | 0x80`).
+    ///
+    /// # Examples
+    /// ```
+    /// # use clutchctl_core::protocol::HID_KEYMAP;
+    /// assert_eq!(HID_KEYMAP.encode_char_with_shift('1'), Some((0x1e, false)));
+    /// assert_eq!(HID_KEYMAP.encode_char_with_shift('!'), Some((0x1e, true)));
+    /// assert_eq!(HID_KEYMAP.encode_char_with_shift('A'), Some((0x04, true)));
+    /// ```
+    pub fn encode_char_with_shift(&self, ch: char) -> Option<(u8, bool)> {
+        let code = self.encode_char(ch)?;
+        // The shifted range starts at 0x84 ('A'); media keys below it use the high bit too
+        if code >= 0x84 {
+            Some((code & !SHIFT_FLAG, true))
+        } else {
+            Some((code, false))
+        }
     }
 }
 
@@ -413,6 +545,48 @@ mod tests {
         assert_eq!(keymap.encode_key("unknown"), None);
     }
 
+    #[test]
+    fn test_aliases_encode_like_canonical_names() {
+        let keymap = &*HID_KEYMAP;
+
+        for &(alias, name) in KEY_ALIASES {
+            assert!(keymap.encode_key(name).is_some(), "{} is not in the table", name);
+            assert_eq!(keymap.encode_key(alias), keymap.encode_key(name), "{}", alias);
+        }
+        assert_eq!(keymap.encode_key("PgUp"), keymap.encode_key("prior"));
+        assert_eq!(keymap.encode_key("return"), keymap.encode_key("enter"));
+        assert_eq!(keymap.encode_key("escape"), keymap.encode_key("esc"));
+
+        // Decoding keeps the table's names
+        assert_eq!(keymap.decode_key(0x4c), Some("delete"));
+        assert_eq!(keymap.decode_key(0x49), Some("insert"));
+        assert_eq!(keymap.decode_key(0x2a), Some("backspace"));
+        assert_eq!(keymap.decode_key(0x28), Some("enter"));
+        assert_eq!(keymap.decode_key(0x52), Some("up"));
+    }
+
+    #[test]
+    fn test_all_key_names() {
+        let names = HID_KEYMAP.all_key_names();
+        assert!(names.contains(&"enter"));
+        assert!(names.contains(&"<"));
+        assert!(!names.contains(&"<00>"));
+        assert!(names.iter().all(|name| HID_KEYMAP.encode_key(name).is_some()));
+    }
+
+    #[test]
+    fn test_encode_char_with_shift() {
+        let keymap = &*HID_KEYMAP;
+
+        assert_eq!(keymap.encode_char_with_shift('a'), Some((0x04, false)));
+        assert_eq!(keymap.encode_char_with_shift('Z'), Some((0x1d, true)));
+        assert_eq!(keymap.encode_char_with_shift('?'), Some((0x38, true)));
+        assert_eq!(keymap.encode_char_with_shift('_'), Some((0x2d, true)));
+        assert_eq!(keymap.encode_char_with_shift('€'), None);
+        assert!(keymap.requires_shift('!'));
+        assert!(!keymap.requires_shift('1'));
+    }
+
     #[test]
     fn test_decode_key() {
         let keymap = &*HID_KEYMAP;
@@ -426,6 +600,18 @@ mod tests {
         assert_eq!(keymap.decode_key(0xff), None);
     }
 
+    #[test]
+    fn test_encode_char_matches_case() {
+        let keymap = &*HID_KEYMAP;
+
+        // Lowercasing would type every capital as its lowercase letter
+        for (lower, upper) in [('a', 'A'), ('h', 'H'), ('z', 'Z')] {
+            let (lower_code, upper_code) = (keymap.encode_char(lower).unwrap(), keymap.encode_char(upper).unwrap());
+            assert_ne!(lower_code, upper_code);
+            assert_eq!(upper_code, lower_code | 0x80);
+        }
+    }
+
     #[test]
     fn test_encode_char() {
         let keymap = &*HID_KEYMAP;
@@ -447,5 +633,29 @@ mod tests {
                 assert!(keymap.decode_key(code).is_some());
             }
         }
+
+        // Command keys decode to their own names
+        for (key, code) in [("copy", 0x7c), ("paste", 0x7d), ("menu", 0x76), ("Undo", 0x7a)] {
+            assert_eq!(keymap.encode_key(key), Some(code));
+            assert_eq!(keymap.decode_key(code), Some(key.to_lowercase().as_str()));
+        }
+        assert_eq!(keymap.encode_key("application"), Some(0x65));
+    }
+
+    #[test]
+    fn test_raw_key() {
+        assert_eq!(parse_raw_key("raw:0x68").unwrap().unwrap(), 0x68);
+        assert_eq!(parse_raw_key("raw:0X2C").unwrap().unwrap(), 0x2c);
+        assert!(parse_raw_key("0x68").is_none());
+        assert!(parse_raw_key("f13").is_none());
+        for key in ["raw:0x00", "raw:0x03", "raw:0xe1", "raw:0x100", "raw:68", "raw:"] {
+            assert!(parse_raw_key(key).unwrap().is_err(), "{} should be rejected", key);
+        }
+
+        // Codes outside the keymap decode to the raw syntax, which parses back
+        assert_eq!(HID_KEYMAP.decode_key(0x67), None);
+        assert_eq!(key_name(0x67), "raw:0x67");
+        assert_eq!(parse_raw_key(&key_name(0x67)).unwrap().unwrap(), 0x67);
+        assert_eq!(key_name(0x68), "f13");
     }
 }
\ No newline at end of file
@@ -1,16 +1,24 @@
 //! iKKEGOL protocol encoding and decoding
+//!
+//! A pedal's configuration is split over two places on the device: its 40-byte
+//! config packet, whose type byte also carries the one-shot flag for keyboard
+//! configurations, and the trigger-mode table, which holds the press/release
+//! trigger of every pedal. [`encode_config`] produces the former and
+//! [`encode_trigger_modes`] the latter.
 
 use crate::configuration::{
     Configuration, GamepadConfiguration, KeyboardConfiguration, MediaConfiguration,
-    MouseConfiguration, TextConfiguration,
+    MouseConfiguration, TextConfiguration, Trigger,
     keyboard::KeyMode,
     mouse::{MouseButton, MouseMode},
 };
 use crate::error::{PedalError, Result};
 use crate::protocol::{
     ConfigPacket, ConfigType, GameKey, KeyboardData, MediaButton, MediaData,
-    ModifierKeys, MouseData, ProtocolMouseButton, HID_KEYMAP,
+    ModifierKeys, MouseData, ProtocolMouseButton, TriggerMode, HID_KEYMAP,
+    key_name, parse_hex_scan_code, parse_raw_key,
 };
+use log::warn;
 use std::collections::HashSet;
 
 /// Parse a configuration packet into a Configuration
@@ -23,9 +31,7 @@ pub fn parse_config(packet: &ConfigPacket) -> Result<Configuration> {
             let data = packet.parse_data();
             if let crate::protocol::ConfigData::Keyboard(kbd) = data {
                 let mode = match packet.get_config_type() {
-                    Some(ConfigType::KeyboardOnce) | Some(ConfigType::KeyboardMultiOnce) => {
-                        KeyMode::OneShot
-                    }
+                    Some(config_type) if config_type.is_one_shot() => KeyMode::OneShot,
                     _ => KeyMode::Standard,
                 };
 
@@ -33,13 +39,7 @@ pub fn parse_config(packet: &ConfigPacket) -> Result<Configuration> {
                 let mut keys = Vec::new();
                 for &scan_code in &kbd.keys {
                     if scan_code != 0 {
-                        // Try to decode scan code to key name using HID keymap
-                        if let Some(key_name) = HID_KEYMAP.decode_key(scan_code) {
-                            keys.push(key_name.to_string());
-                        } else {
-                            // Fall back to hex representation for unknown codes
-                            keys.push(format!("0x{:02x}", scan_code));
-                        }
+                        keys.push(key_name(scan_code));
                     }
                 }
 
@@ -106,7 +106,9 @@ pub fn parse_config(packet: &ConfigPacket) -> Result<Configuration> {
                 if let Some(button) = MediaButton::from_u8(media.key) {
                     Ok(Configuration::Media(MediaConfiguration::new(button)))
                 } else {
-                    Err(PedalError::Protocol(format!("Unknown media button: {}", media.key)))
+                    // A bad byte shouldn't fail reading the other pedals
+                    warn!("Unknown media button code {}, treating pedal as unconfigured", media.key);
+                    Ok(Configuration::Unconfigured)
                 }
             } else {
                 Err(PedalError::Protocol("Invalid media data".to_string()))
@@ -119,18 +121,29 @@ pub fn parse_config(packet: &ConfigPacket) -> Result<Configuration> {
                 if let Some(key) = GameKey::from_u8(game.key) {
                     Ok(Configuration::Gamepad(GamepadConfiguration::new(key)))
                 } else {
-                    Err(PedalError::Protocol(format!("Unknown game key: {}", game.key)))
+                    warn!("Unknown game key code {}, treating pedal as unconfigured", game.key);
+                    Ok(Configuration::Unconfigured)
                 }
             } else {
                 Err(PedalError::Protocol("Invalid game data".to_string()))
             }
         }
 
-        None => Err(PedalError::Protocol(format!("Unknown config type: {}", packet.config_type))),
+        // Keep configurations we don't model so saving doesn't destroy them
+        Some(ConfigType::KeyboardMouse) | None => Ok(Configuration::Raw {
+            config_type: packet.config_type,
+            data: packet.data,
+            trigger: Trigger::OnPress,
+        }),
     }
 }
 
+/// Number of entries in the trigger-mode table
+pub const TRIGGER_TABLE_SIZE: usize = 8;
+
 /// Encode a Configuration into a ConfigPacket
+///
+/// The packet doesn't carry the trigger; use [`encode_trigger_modes`] for that.
 pub fn encode_config(config: &Configuration) -> Result<ConfigPacket> {
     let mut packet = ConfigPacket::unconfigured();
 
@@ -163,34 +176,37 @@ pub fn encode_config(config: &Configuration) -> Result<ConfigPacket> {
                 keys: [0; 6],
             };
 
+            if key_count > kbd_data.keys.len() {
+                return Err(PedalError::InvalidConfiguration(format!(
+                    "A pedal holds at most {} keys, got {}",
+                    kbd_data.keys.len(),
+                    key_count
+                )));
+            }
+
             // Convert key names to scan codes
             for (i, key) in kbd.keys.iter().enumerate() {
-                if i >= 6 {
-                    break;
+
+                // Raw usage codes bypass the keymap
+                if let Some(code) = parse_raw_key(key) {
+                    kbd_data.keys[i] = code?;
+                    continue;
                 }
 
-                // First try hex scan codes for backward compatibility
-                if let Some(hex) = key.strip_prefix("0x") {
-                    if let Ok(code) = u8::from_str_radix(hex, 16) {
-                        kbd_data.keys[i] = code;
-                        continue;
-                    }
+                // Then try hex scan codes for backward compatibility
+                if let Some(code) = parse_hex_scan_code(key) {
+                    kbd_data.keys[i] = code?;
+                    continue;
                 }
 
                 // Try to encode key name using HID keymap
-                if let Some(code) = HID_KEYMAP.encode_key(key) {
-                    kbd_data.keys[i] = code;
-                }
+                kbd_data.keys[i] = HID_KEYMAP.encode_key(key).ok_or_else(|| {
+                    PedalError::InvalidConfiguration(format!("unknown key '{}'", key))
+                })?;
             }
 
             // Copy keyboard data to packet
-            unsafe {
-                let kbd_bytes = std::slice::from_raw_parts(
-                    &kbd_data as *const _ as *const u8,
-                    std::mem::size_of::<KeyboardData>(),
-                );
-                packet.data[..kbd_bytes.len()].copy_from_slice(kbd_bytes);
-            }
+            kbd_data.write_to(&mut packet.data);
 
             packet.size = 40; // Full packet size
         }
@@ -208,6 +224,12 @@ pub fn encode_config(config: &Configuration) -> Result<ConfigPacket> {
 
             match &mouse.mode {
                 MouseMode::Buttons(buttons) => {
+                    // No buttons would read back as a motionless axis configuration
+                    if buttons.is_empty() {
+                        return Err(PedalError::InvalidConfiguration(
+                            "Mouse button configuration needs at least one button".to_string(),
+                        ));
+                    }
                     let mut proto_buttons = ProtocolMouseButton::empty();
                     for button in buttons {
                         match button {
@@ -228,13 +250,7 @@ pub fn encode_config(config: &Configuration) -> Result<ConfigPacket> {
             }
 
             // Copy mouse data to packet
-            unsafe {
-                let mouse_bytes = std::slice::from_raw_parts(
-                    &mouse_data as *const _ as *const u8,
-                    std::mem::size_of::<MouseData>(),
-                );
-                packet.data[..mouse_bytes.len()].copy_from_slice(mouse_bytes);
-            }
+            mouse_data.write_to(&mut packet.data);
 
             packet.size = 40;
         }
@@ -265,11 +281,36 @@ pub fn encode_config(config: &Configuration) -> Result<ConfigPacket> {
             packet.data[0] = gamepad.button as u8;
             packet.size = 40;
         }
+
+        Configuration::Raw { config_type, data, .. } => {
+            packet.config_type = *config_type;
+            packet.data = *data;
+            packet.size = 40;
+        }
     }
 
     Ok(packet)
 }
 
+/// Encode the trigger-mode table from the configuration of each pedal
+///
+/// The table is indexed like the config slots, so pedal `i` goes in entry
+/// `first_pedal_index + i`. Unconfigured pedals and unused entries trigger on
+/// press.
+pub fn encode_trigger_modes(configs: &[Configuration], first_pedal_index: usize) -> [u8; TRIGGER_TABLE_SIZE] {
+    let mut table = [TriggerMode::Press as u8; TRIGGER_TABLE_SIZE];
+    for (entry, config) in table.iter_mut().skip(first_pedal_index).zip(configs) {
+        let trigger = config.trigger().unwrap_or(Trigger::OnPress);
+        *entry = TriggerMode::from(trigger) as u8;
+    }
+    table
+}
+
+/// Decode one entry of the trigger-mode table, defaulting to press
+pub fn decode_trigger_mode(entry: u8) -> Trigger {
+    TriggerMode::from_u8(entry).unwrap_or(TriggerMode::Press).into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -280,7 +321,7 @@ mod tests {
         let packet = encode_config(&config).unwrap();
         let parsed = parse_config(&packet).unwrap();
 
-        assert!(matches!(parsed, Configuration::Unconfigured));
+        assert_eq!(parsed, config);
     }
 
     #[test]
@@ -289,11 +330,7 @@ mod tests {
         let packet = encode_config(&config).unwrap();
         let parsed = parse_config(&packet).unwrap();
 
-        if let Configuration::Media(media) = parsed {
-            assert_eq!(media.button, MediaButton::Play);
-        } else {
-            panic!("Expected media configuration");
-        }
+        assert_eq!(parsed, config);
     }
 
     #[test]
@@ -302,10 +339,195 @@ mod tests {
         let packet = encode_config(&config).unwrap();
         let parsed = parse_config(&packet).unwrap();
 
-        if let Configuration::Gamepad(gamepad) = parsed {
-            assert_eq!(gamepad.button, GameKey::Button1);
-        } else {
-            panic!("Expected gamepad configuration");
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn test_unknown_media_and_game_codes_read_as_unconfigured() {
+        let mut packet = ConfigPacket::unconfigured();
+        packet.size = 40;
+        packet.data[0] = 0xee;
+
+        packet.config_type = ConfigType::Media as u8;
+        assert_eq!(parse_config(&packet).unwrap(), Configuration::Unconfigured);
+
+        packet.config_type = ConfigType::Game as u8;
+        assert_eq!(parse_config(&packet).unwrap(), Configuration::Unconfigured);
+    }
+
+    #[test]
+    fn test_modifier_only_keyboard_round_trip() {
+        let config = Configuration::Keyboard(KeyboardConfiguration::with_modifiers(
+            KeyMode::Standard,
+            Vec::new(),
+            ModifierKeys::LEFT_SHIFT,
+        ));
+        let packet = encode_config(&config).unwrap();
+
+        assert_eq!(packet.config_type, ConfigType::Keyboard as u8);
+        assert_eq!(packet.data[..7], [ModifierKeys::LEFT_SHIFT.bits(), 0, 0, 0, 0, 0, 0]);
+        assert_eq!(parse_config(&packet).unwrap(), config);
+    }
+
+    #[test]
+    fn test_invalid_hex_key_is_rejected() {
+        for key in ["0xGG", "0x"] {
+            let config = Configuration::Keyboard(KeyboardConfiguration::new(
+                KeyMode::Standard,
+                vec![key.to_string()],
+            ));
+            assert!(matches!(
+                encode_config(&config),
+                Err(PedalError::InvalidConfiguration(_))
+            ), "{} should be rejected", key);
+        }
+
+        let config = Configuration::Keyboard(KeyboardConfiguration::new(
+            KeyMode::Standard,
+            vec!["0x2c".to_string()],
+        ));
+        assert_eq!(encode_config(&config).unwrap().data[1], 0x2c);
+    }
+
+    #[test]
+    fn test_unknown_key_name_is_rejected() {
+        let config = Configuration::Keyboard(KeyboardConfiguration::new(
+            KeyMode::Standard,
+            vec!["a".to_string(), "notakey".to_string()],
+        ));
+        match encode_config(&config) {
+            Err(PedalError::InvalidConfiguration(message)) => assert_eq!(message, "unknown key 'notakey'"),
+            _ => panic!("notakey should be rejected"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_size_is_whole_packet() {
+        let one_key = KeyboardConfiguration::new(KeyMode::Standard, vec!["a".to_string()]);
+        let six_keys = KeyboardConfiguration::new(KeyMode::Standard, ["a", "b", "c", "d", "e", "f"].map(String::from).to_vec());
+        for config in [
+            Configuration::Keyboard(one_key),
+            Configuration::Keyboard(six_keys),
+            Configuration::Text(TextConfiguration::new("hi".to_string())),
+            Configuration::Mouse(MouseConfiguration::axis(0, 0, 1)),
+            Configuration::Media(MediaConfiguration::new(MediaButton::Play)),
+        ] {
+            let packet = encode_config(&config).unwrap();
+            assert_eq!(packet.size as usize, ConfigPacket::PACKET_SIZE, "{}", config);
+            assert_eq!(packet.to_bytes()[ConfigPacket::SIZE_OFFSET] as usize, ConfigPacket::PACKET_SIZE);
+        }
+        assert_eq!(encode_config(&Configuration::Unconfigured).unwrap().size, 0);
+    }
+
+    #[test]
+    fn test_raw_key_round_trip() {
+        let config = Configuration::Keyboard(KeyboardConfiguration::new(
+            KeyMode::Standard,
+            vec!["raw:0x68".to_string(), "raw:0x67".to_string()],
+        ));
+        let packet = encode_config(&config).unwrap();
+        assert_eq!(packet.data[1..3], [0x68, 0x67]);
+
+        // The device keeps only the code: known codes come back by name
+        assert_eq!(parse_config(&packet).unwrap().to_string(), "Keyboard: f13+raw:0x67");
+        assert_eq!(encode_config(&parse_config(&packet).unwrap()).unwrap().data, packet.data);
+    }
+
+    #[test]
+    fn test_empty_mouse_buttons_are_rejected() {
+        let config = Configuration::Mouse(MouseConfiguration::buttons(HashSet::new()));
+        assert!(matches!(encode_config(&config), Err(PedalError::InvalidConfiguration(_))));
+
+        let config = Configuration::Mouse(MouseConfiguration::buttons([MouseButton::Back].into_iter().collect()));
+        assert_eq!(parse_config(&encode_config(&config).unwrap()).unwrap(), config);
+    }
+
+    #[test]
+    fn test_back_forward_display_after_roundtrip() {
+        // As `set <DEVICE> <PEDAL> mouse buttons left+back` builds it
+        let buttons = MouseConfiguration::parse_buttons("left+back").unwrap();
+        let config = Configuration::Mouse(MouseConfiguration::buttons(buttons));
+
+        let read_back = parse_config(&ConfigPacket::from_bytes(&encode_config(&config).unwrap().to_bytes())).unwrap();
+        assert_eq!(read_back, config);
+        assert_eq!(read_back.to_string(), "Mouse: back+left");
+
+        let buttons = MouseConfiguration::parse_buttons("forward+middle").unwrap();
+        let config = Configuration::Mouse(MouseConfiguration::buttons(buttons));
+        assert_eq!(parse_config(&encode_config(&config).unwrap()).unwrap().to_string(), "Mouse: forward+middle");
+    }
+
+    #[test]
+    fn test_multi_key_encoding() {
+        let (modifiers, keys) = KeyboardConfiguration::parse_modifiers("ctrl+a+0x05");
+        let config = Configuration::Keyboard(KeyboardConfiguration::with_modifiers(
+            KeyMode::Standard,
+            keys,
+            modifiers,
+        ));
+        let packet = encode_config(&config).unwrap();
+
+        assert_eq!(packet.config_type, ConfigType::KeyboardMulti as u8);
+        assert_eq!(packet.data[..4], [ModifierKeys::LEFT_CONTROL.bits(), 0x04, 0x05, 0]);
+
+        let (_, keys) = KeyboardConfiguration::parse_modifiers("a+b+c+d+e+f+g");
+        let config = Configuration::Keyboard(KeyboardConfiguration::new(KeyMode::Standard, keys));
+        assert!(matches!(encode_config(&config), Err(PedalError::InvalidConfiguration(_))));
+    }
+
+    #[test]
+    fn test_raw_round_trip() {
+        // An unknown type, and the combined keyboard+mouse type whose layout is unknown
+        for config_type in [0x0b, ConfigType::KeyboardMouse as u8] {
+            let mut bytes = [0u8; ConfigPacket::PACKET_SIZE];
+            bytes[0] = 40;
+            bytes[1] = config_type;
+            for (i, b) in bytes[2..].iter_mut().enumerate() {
+                *b = i as u8;
+            }
+
+            let config = parse_config(&ConfigPacket::from_bytes(&bytes)).unwrap();
+            assert!(matches!(config, Configuration::Raw { config_type: t, .. } if t == config_type));
+            assert_eq!(encode_config(&config).unwrap().to_bytes(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_one_shot_on_release_round_trip() {
+        let mut config = Configuration::Keyboard(KeyboardConfiguration::new(
+            KeyMode::OneShot,
+            vec!["a".to_string()],
+        ));
+        config.set_trigger(Trigger::OnRelease);
+
+        // One-shot goes in the config type byte, the trigger in the table
+        let packet = encode_config(&config).unwrap();
+        assert_eq!(packet.config_type, ConfigType::KeyboardOnce as u8);
+        let table = encode_trigger_modes(&[Configuration::Unconfigured, config.clone()], 0);
+        assert_eq!(table[0], TriggerMode::Press as u8);
+        assert_eq!(table[1], TriggerMode::Release as u8);
+
+        let mut parsed = parse_config(&packet).unwrap();
+        parsed.set_trigger(decode_trigger_mode(table[1]));
+
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn test_standard_on_release_is_not_one_shot() {
+        let mut config = Configuration::Keyboard(KeyboardConfiguration::new(
+            KeyMode::Standard,
+            vec!["a".to_string(), "b".to_string()],
+        ));
+        config.set_trigger(Trigger::OnRelease);
+
+        let packet = encode_config(&config).unwrap();
+        assert_eq!(packet.config_type, ConfigType::KeyboardMulti as u8);
+        assert_eq!(encode_trigger_modes(&[config.clone()], 0)[0], TriggerMode::Release as u8);
+
+        // Single-pedal models start at slot 1
+        let table = encode_trigger_modes(&[config], 1);
+        assert_eq!(table[0], TriggerMode::Press as u8);
+        assert_eq!(table[1], TriggerMode::Release as u8);
+    }
+}
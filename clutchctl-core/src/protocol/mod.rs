@@ -6,4 +6,4 @@ pub mod hid_keymap;
 
 pub use packets::*;
 pub use ikkegol::*;
-pub use hid_keymap::HID_KEYMAP;
\ No newline at end of file
+pub use hid_keymap::{key_name, parse_hex_scan_code, parse_raw_key, HID_KEYMAP, RAW_KEY_PREFIX, SHIFT_FLAG};
\ No newline at end of file
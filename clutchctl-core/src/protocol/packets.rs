@@ -4,6 +4,12 @@
 use bitflags::bitflags;
 
 /// Configuration type identifiers
+///
+/// This is the `config_type` byte of a [`ConfigPacket`]. For keyboard types the
+/// high bit ([`ConfigType::ONE_SHOT_FLAG`]) selects one-shot mode (the key is sent
+/// once instead of held while the pedal is down). It says nothing about press or
+/// release: that lives in the separate trigger-mode table, one [`TriggerMode`]
+/// byte per pedal (see [`Command::ReadTriggerModes`]).
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConfigType {
@@ -11,6 +17,9 @@ pub enum ConfigType {
     Keyboard = 0x01,
     KeyboardOnce = 0x81,
     Mouse = 0x02,
+    /// Combined keyboard and mouse; the iKKEGOL data layout is unknown, so
+    /// these packets parse to [`ConfigData::Raw`] and are kept byte for byte
+    KeyboardMouse = 0x03,
     Text = 0x04,
     KeyboardMulti = 0x06,
     KeyboardMultiOnce = 0x86,
@@ -19,12 +28,21 @@ pub enum ConfigType {
 }
 
 impl ConfigType {
+    /// Bit of the config type byte that marks one-shot keyboard types
+    pub const ONE_SHOT_FLAG: u8 = 0x80;
+
+    /// Check whether this is a one-shot keyboard type
+    pub fn is_one_shot(self) -> bool {
+        self as u8 & Self::ONE_SHOT_FLAG != 0
+    }
+
     pub fn from_u8(value: u8) -> Option<Self> {
         match value {
             0x00 => Some(Self::Unconfigured),
             0x01 => Some(Self::Keyboard),
             0x81 => Some(Self::KeyboardOnce),
             0x02 => Some(Self::Mouse),
+            0x03 => Some(Self::KeyboardMouse),
             0x04 => Some(Self::Text),
             0x06 => Some(Self::KeyboardMulti),
             0x86 => Some(Self::KeyboardMultiOnce),
@@ -85,6 +103,15 @@ pub enum MediaButton {
     OpenSearch = 17,
     Shutdown = 18,
     Sleep = 19,
+    /// Unconfirmed code, see [`MediaButton::is_experimental`]
+    #[cfg(feature = "experimental")]
+    BrightnessUp = 20,
+    /// Unconfirmed code, see [`MediaButton::is_experimental`]
+    #[cfg(feature = "experimental")]
+    BrightnessDown = 21,
+    /// Unconfirmed code, see [`MediaButton::is_experimental`]
+    #[cfg(feature = "experimental")]
+    MicMute = 22,
 }
 
 impl MediaButton {
@@ -109,9 +136,23 @@ impl MediaButton {
             17 => Some(Self::OpenSearch),
             18 => Some(Self::Shutdown),
             19 => Some(Self::Sleep),
+            #[cfg(feature = "experimental")]
+            20 => Some(Self::BrightnessUp),
+            #[cfg(feature = "experimental")]
+            21 => Some(Self::BrightnessDown),
+            #[cfg(feature = "experimental")]
+            22 => Some(Self::MicMute),
             _ => None,
         }
     }
+
+    /// Whether the code is a guess rather than taken from the vendor software
+    ///
+    /// Codes above `Sleep` are only built with the `experimental` feature; the
+    /// firmware may ignore them or map them to a different key.
+    pub fn is_experimental(self) -> bool {
+        self as u8 > Self::Sleep as u8
+    }
 }
 
 /// Game button codes
@@ -153,6 +194,9 @@ impl GameKey {
 }
 
 /// Trigger mode for pedal activation
+///
+/// One byte per pedal in the trigger-mode table, independent of the pedal's
+/// config packet.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TriggerMode {
@@ -171,6 +215,9 @@ impl TriggerMode {
 }
 
 /// Keyboard configuration data
+///
+/// Wire layout, as offsets into [`ConfigPacket::data`]: modifiers at 0, up to
+/// six scan codes at 1..7. Read and written byte by byte, never by casting.
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy)]
 pub struct KeyboardData {
@@ -179,6 +226,9 @@ pub struct KeyboardData {
 }
 
 /// Mouse configuration data
+///
+/// Wire layout, as offsets into [`ConfigPacket::data`]: two unknown bytes at
+/// 0..2, buttons at 2, then signed x, y and wheel at 3, 4 and 5.
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy)]
 pub struct MouseData {
@@ -189,6 +239,43 @@ pub struct MouseData {
     pub mouse_wheel: i8,
 }
 
+impl KeyboardData {
+    /// Read from the start of a packet's data field
+    pub fn from_data(data: &[u8; 38]) -> Self {
+        let mut keys = [0u8; 6];
+        keys.copy_from_slice(&data[1..7]);
+        Self { modifiers: data[0], keys }
+    }
+
+    /// Write to the start of a packet's data field
+    pub fn write_to(&self, data: &mut [u8; 38]) {
+        data[0] = self.modifiers;
+        data[1..7].copy_from_slice(&self.keys);
+    }
+}
+
+impl MouseData {
+    /// Read from the start of a packet's data field
+    pub fn from_data(data: &[u8; 38]) -> Self {
+        Self {
+            unknown: [data[0], data[1]],
+            buttons: data[2],
+            mouse_x: data[3] as i8,
+            mouse_y: data[4] as i8,
+            mouse_wheel: data[5] as i8,
+        }
+    }
+
+    /// Write to the start of a packet's data field
+    pub fn write_to(&self, data: &mut [u8; 38]) {
+        data[..2].copy_from_slice(&self.unknown);
+        data[2] = self.buttons;
+        data[3] = self.mouse_x as u8;
+        data[4] = self.mouse_y as u8;
+        data[5] = self.mouse_wheel as u8;
+    }
+}
+
 /// Media configuration data
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy)]
@@ -226,6 +313,11 @@ pub enum ConfigData {
 /// This must maintain binary compatibility with the C++ implementation
 #[repr(C, packed)]
 pub struct ConfigPacket {
+    /// Bytes the write header announces: the whole packet, size and type bytes
+    /// included, for any configuration, and 0 for an unconfigured pedal
+    ///
+    /// This is not the payload length: the full packet is sent either way, and
+    /// the encoding carried over from pedalctl has always announced 40.
     pub size: u8,
     pub config_type: u8,  // We use u8 instead of ConfigType for binary compatibility
     pub data: [u8; 38],    // Raw data that will be interpreted based on config_type
@@ -234,6 +326,13 @@ pub struct ConfigPacket {
 impl ConfigPacket {
     pub const PACKET_SIZE: usize = 40;
 
+    /// Byte offset of `size` on the wire
+    pub const SIZE_OFFSET: usize = 0;
+    /// Byte offset of `config_type` on the wire
+    pub const CONFIG_TYPE_OFFSET: usize = 1;
+    /// Byte offset of `data` on the wire
+    pub const DATA_OFFSET: usize = 2;
+
     /// Create an empty/unconfigured packet
     pub fn unconfigured() -> Self {
         Self {
@@ -253,70 +352,111 @@ impl ConfigPacket {
         match self.get_config_type() {
             Some(ConfigType::Keyboard) | Some(ConfigType::KeyboardOnce) |
             Some(ConfigType::KeyboardMulti) | Some(ConfigType::KeyboardMultiOnce) => {
-                let keyboard = unsafe {
-                    std::ptr::read_unaligned(self.data.as_ptr() as *const KeyboardData)
-                };
-                ConfigData::Keyboard(keyboard)
-            }
-            Some(ConfigType::Mouse) => {
-                let mouse = unsafe {
-                    std::ptr::read_unaligned(self.data.as_ptr() as *const MouseData)
-                };
-                ConfigData::Mouse(mouse)
-            }
-            Some(ConfigType::Media) => {
-                let media = unsafe {
-                    std::ptr::read_unaligned(self.data.as_ptr() as *const MediaData)
-                };
-                ConfigData::Media(media)
-            }
-            Some(ConfigType::Game) => {
-                let game = unsafe {
-                    std::ptr::read_unaligned(self.data.as_ptr() as *const GameData)
-                };
-                ConfigData::Game(game)
-            }
-            Some(ConfigType::Text) => {
-                let text = unsafe {
-                    std::ptr::read_unaligned(self.data.as_ptr() as *const TextData)
-                };
-                ConfigData::Text(text)
+                ConfigData::Keyboard(KeyboardData::from_data(&self.data))
             }
+            Some(ConfigType::Mouse) => ConfigData::Mouse(MouseData::from_data(&self.data)),
+            Some(ConfigType::Media) => ConfigData::Media(MediaData { key: self.data[0] }),
+            Some(ConfigType::Game) => ConfigData::Game(GameData { key: self.data[0] }),
+            Some(ConfigType::Text) => ConfigData::Text(TextData { string: self.data }),
             _ => ConfigData::Raw(self.data),
         }
     }
 
     /// Convert to bytes for USB transmission
+    ///
+    /// Every field is a byte or byte array, so the wire layout is the same on any
+    /// host: `size` at [`Self::SIZE_OFFSET`], `config_type` at
+    /// [`Self::CONFIG_TYPE_OFFSET`] and `data` from [`Self::DATA_OFFSET`].
     pub fn to_bytes(&self) -> [u8; Self::PACKET_SIZE] {
-        unsafe {
-            std::mem::transmute_copy(self)
-        }
+        let mut bytes = [0u8; Self::PACKET_SIZE];
+        bytes[Self::SIZE_OFFSET] = self.size;
+        bytes[Self::CONFIG_TYPE_OFFSET] = self.config_type;
+        bytes[Self::DATA_OFFSET..].copy_from_slice(&self.data);
+        bytes
     }
 
     /// Create from raw bytes
     pub fn from_bytes(bytes: &[u8; Self::PACKET_SIZE]) -> Self {
-        unsafe {
-            std::mem::transmute_copy(bytes)
+        let mut data = [0u8; 38];
+        data.copy_from_slice(&bytes[Self::DATA_OFFSET..]);
+        Self {
+            size: bytes[Self::SIZE_OFFSET],
+            config_type: bytes[Self::CONFIG_TYPE_OFFSET],
+            data,
         }
     }
 }
 
-/// USB command codes
+/// Commands sent to the device, each an 8-byte HID report
+///
+/// `0x80` begins a write session, `0x81` and `0x85` write a pedal and the
+/// trigger-mode table, and `0x82`, `0x83` and `0x86` read a pedal, the model
+/// and the trigger modes. There is no commit command: writes are persistent
+/// as soon as the device has received them. Pedals are addressed by their
+/// 0-based protocol slot, which goes on the wire 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Begin a write session
+    ///
+    /// The meaning of the argument is unknown; the iKKEGOL path sends `1`,
+    /// the PCsensor path `0`.
+    BeginWrite { arg: u8 },
+    /// Header of a pedal write; followed by `size` bytes of configuration
+    WriteHeader { size: u8, pedal: u8 },
+    /// Read the configuration of a pedal
+    ReadConfig { pedal: u8 },
+    /// Read the model and firmware version string
+    ReadModel,
+    /// Header of a trigger-mode table write; followed by the table payload
+    WriteTriggerModes { payload_size: u8 },
+    /// Read the trigger-mode table
+    ReadTriggerModes,
+}
+
+impl Command {
+    /// Report ID every command starts with
+    pub const REPORT_ID: u8 = 0x01;
+
+    /// Encode as the 8-byte HID report
+    pub const fn to_bytes(self) -> [u8; 8] {
+        let (code, size, arg) = match self {
+            Command::BeginWrite { arg } => (0x80, 0x08, arg),
+            Command::WriteHeader { size, pedal } => (0x81, size, pedal + 1),
+            Command::ReadConfig { pedal } => (0x82, 0x08, pedal + 1),
+            Command::ReadModel => (0x83, 0x08, 0x00),
+            Command::WriteTriggerModes { payload_size } => (0x85, payload_size, 0x00),
+            Command::ReadTriggerModes => (0x86, 0x00, 0x00),
+        };
+        [Self::REPORT_ID, code, size, arg, 0x00, 0x00, 0x00, 0x00]
+    }
+}
+
+/// Command reports by name, from before [`Command`]
+///
+/// Kept so existing callers still build; to be removed in the next major release.
 pub mod commands {
-    pub const BEGIN_WRITE: [u8; 8] = [0x01, 0x80, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00];
-    pub const READ_MODEL: [u8; 8] = [0x01, 0x83, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
-    pub const READ_TRIGGER_MODES: [u8; 8] = [0x01, 0x86, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    use super::Command;
 
+    #[deprecated(note = "use `Command::BeginWrite { arg: 1 }.to_bytes()`")]
+    pub const BEGIN_WRITE: [u8; 8] = Command::BeginWrite { arg: 1 }.to_bytes();
+    #[deprecated(note = "use `Command::ReadModel.to_bytes()`")]
+    pub const READ_MODEL: [u8; 8] = Command::ReadModel.to_bytes();
+    #[deprecated(note = "use `Command::ReadTriggerModes.to_bytes()`")]
+    pub const READ_TRIGGER_MODES: [u8; 8] = Command::ReadTriggerModes.to_bytes();
+
+    #[deprecated(note = "use `Command::ReadConfig { pedal }.to_bytes()`")]
     pub fn read_config(pedal_index: u8) -> [u8; 8] {
-        [0x01, 0x82, 0x08, pedal_index + 1, 0x00, 0x00, 0x00, 0x00]
+        Command::ReadConfig { pedal: pedal_index }.to_bytes()
     }
 
+    #[deprecated(note = "use `Command::WriteHeader { size, pedal }.to_bytes()`")]
     pub fn write_config_header(size: u8, pedal_index: u8) -> [u8; 8] {
-        [0x01, 0x81, size, pedal_index + 1, 0x00, 0x00, 0x00, 0x00]
+        Command::WriteHeader { size, pedal: pedal_index }.to_bytes()
     }
 
+    #[deprecated(note = "use `Command::WriteTriggerModes { payload_size }.to_bytes()`")]
     pub fn write_trigger_modes(payload_size: u8) -> [u8; 8] {
-        [0x01, 0x85, payload_size, 0x00, 0x00, 0x00, 0x00, 0x00]
+        Command::WriteTriggerModes { payload_size }.to_bytes()
     }
 }
 
@@ -336,6 +476,18 @@ mod tests {
         assert_eq!(ConfigType::from_u8(0xFF), None);
     }
 
+    #[test]
+    fn test_command_bytes() {
+        assert_eq!(Command::BeginWrite { arg: 1 }.to_bytes(), [0x01, 0x80, 0x08, 0x01, 0, 0, 0, 0]);
+        assert_eq!(Command::ReadModel.to_bytes(), [0x01, 0x83, 0x08, 0, 0, 0, 0, 0]);
+        assert_eq!(Command::ReadTriggerModes.to_bytes(), [0x01, 0x86, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(Command::WriteTriggerModes { payload_size: 8 }.to_bytes(), [0x01, 0x85, 0x08, 0, 0, 0, 0, 0]);
+
+        // Pedals go on the wire 1-based
+        assert_eq!(Command::ReadConfig { pedal: 0 }.to_bytes(), [0x01, 0x82, 0x08, 0x01, 0, 0, 0, 0]);
+        assert_eq!(Command::WriteHeader { size: 40, pedal: 2 }.to_bytes(), [0x01, 0x81, 40, 0x03, 0, 0, 0, 0]);
+    }
+
     #[test]
     fn test_packet_round_trip() {
         let packet = ConfigPacket::unconfigured();
@@ -345,4 +497,71 @@ mod tests {
         assert_eq!(packet.size, restored.size);
         assert_eq!(packet.config_type, restored.config_type);
     }
+
+    #[test]
+    fn test_packet_byte_offsets() {
+        let mut data = [0u8; 38];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = 0x10 + i as u8;
+        }
+        let packet = ConfigPacket { size: 40, config_type: 0x86, data };
+        let bytes = packet.to_bytes();
+
+        assert_eq!(bytes[ConfigPacket::SIZE_OFFSET], 40);
+        assert_eq!(bytes[ConfigPacket::CONFIG_TYPE_OFFSET], 0x86);
+        assert_eq!(&bytes[ConfigPacket::DATA_OFFSET..], &data[..]);
+        assert_eq!((ConfigPacket::SIZE_OFFSET, ConfigPacket::CONFIG_TYPE_OFFSET, ConfigPacket::DATA_OFFSET), (0, 1, 2));
+
+        let restored = ConfigPacket::from_bytes(&bytes);
+        assert_eq!(restored.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_mouse_data_layout() {
+        let mouse = MouseData { unknown: [0, 0], buttons: 0x05, mouse_x: -5, mouse_y: 7, mouse_wheel: -1 };
+        let mut data = [0u8; 38];
+        mouse.write_to(&mut data);
+
+        assert_eq!(&data[..6], &[0, 0, 0x05, 0xfb, 0x07, 0xff]);
+        let parsed = MouseData::from_data(&data);
+        assert_eq!({ parsed.mouse_x }, -5);
+        assert_eq!({ parsed.mouse_wheel }, -1);
+    }
+
+    #[test]
+    fn test_parse_data_reads_wire_offsets() {
+        let mut bytes = [0u8; ConfigPacket::PACKET_SIZE];
+        bytes[ConfigPacket::CONFIG_TYPE_OFFSET] = ConfigType::Mouse as u8;
+        bytes[ConfigPacket::DATA_OFFSET..ConfigPacket::DATA_OFFSET + 6]
+            .copy_from_slice(&[0xaa, 0xbb, 0x04, 0x01, 0x02, 0x03]);
+
+        match ConfigPacket::from_bytes(&bytes).parse_data() {
+            ConfigData::Mouse(mouse) => {
+                assert_eq!({ mouse.unknown }, [0xaa, 0xbb]);
+                assert_eq!({ mouse.buttons }, 0x04);
+                assert_eq!(({ mouse.mouse_x }, { mouse.mouse_y }, { mouse.mouse_wheel }), (1, 2, 3));
+            }
+            other => panic!("expected mouse data, got {:?}", other),
+        }
+
+        bytes[ConfigPacket::CONFIG_TYPE_OFFSET] = ConfigType::Keyboard as u8;
+        match ConfigPacket::from_bytes(&bytes).parse_data() {
+            ConfigData::Keyboard(keyboard) => {
+                assert_eq!({ keyboard.modifiers }, 0xaa);
+                assert_eq!({ keyboard.keys }, [0xbb, 0x04, 0x01, 0x02, 0x03, 0x00]);
+            }
+            other => panic!("expected keyboard data, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_commands_keep_their_bytes() {
+        assert_eq!(commands::BEGIN_WRITE, [0x01, 0x80, 0x08, 0x01, 0, 0, 0, 0]);
+        assert_eq!(commands::READ_MODEL, [0x01, 0x83, 0x08, 0x00, 0, 0, 0, 0]);
+        assert_eq!(commands::READ_TRIGGER_MODES, [0x01, 0x86, 0x00, 0x00, 0, 0, 0, 0]);
+        assert_eq!(commands::read_config(2), [0x01, 0x82, 0x08, 0x03, 0, 0, 0, 0]);
+        assert_eq!(commands::write_config_header(40, 0), [0x01, 0x81, 40, 0x01, 0, 0, 0, 0]);
+        assert_eq!(commands::write_trigger_modes(8), [0x01, 0x85, 8, 0x00, 0, 0, 0, 0]);
+    }
 }
\ No newline at end of file
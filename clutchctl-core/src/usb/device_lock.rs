@@ -0,0 +1,183 @@
+//! Advisory cross-process device lock
+//!
+//! Reading or writing a pedal takes several HID transactions. Two processes (or
+//! threads) talking to the same device at once interleave those transactions and
+//! corrupt the configuration, and one writing between another's load and save
+//! loses its update. So an open device holds a lock file named after the device
+//! path in the runtime directory (`$XDG_RUNTIME_DIR/clutchctl`, or the system
+//! temp directory) until it is dropped. The file records the owner's PID so a
+//! lock left behind by a crashed process can be reclaimed; a file without a PID,
+//! e.g. from a crash before it was written, is reclaimed once it is old.
+
+use crate::error::{PedalError, Result};
+use log::{debug, warn};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Age after which a lock is considered stale where the owner can't be checked
+const STALE_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Held lock on a device, released when dropped
+#[derive(Debug)]
+pub struct DeviceLock {
+    path: PathBuf,
+}
+
+impl DeviceLock {
+    /// Acquire the lock for a device, identified by its HID path or serial number
+    ///
+    /// Returns `PedalError::DeviceBusy` if another process or thread holds it.
+    /// Drivers acquire it before their first transaction, so probing and
+    /// reading the model string are covered as well.
+    pub fn acquire(key: &str) -> Result<Self> {
+        Self::acquire_in(&lock_dir(), key)
+    }
+
+    fn acquire_in(dir: &Path, key: &str) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.lock", sanitize(key)));
+
+        match Self::create(&path) {
+            Err(PedalError::DeviceBusy) if is_stale(&path) => {
+                warn!("Removing stale device lock {}", path.display());
+                // Another process may reclaim it first; create() then reports busy
+                let _ = fs::remove_file(&path);
+                Self::create(&path)
+            }
+            result => result,
+        }
+    }
+
+    fn create(path: &Path) -> Result<Self> {
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => return Err(PedalError::DeviceBusy),
+            Err(e) => return Err(e.into()),
+        };
+
+        let lock = Self { path: path.to_path_buf() };
+        write!(file, "{}", std::process::id())?;
+        debug!("Acquired device lock {}", path.display());
+        Ok(lock)
+    }
+}
+
+impl Drop for DeviceLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to release device lock {}: {}", self.path.display(), e);
+        } else {
+            debug!("Released device lock {}", self.path.display());
+        }
+    }
+}
+
+/// Directory holding the lock files
+fn lock_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("clutchctl")
+}
+
+/// Turn a device key into a file name
+fn sanitize(key: &str) -> String {
+    key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Check whether a lock file was left behind by a process that no longer runs
+fn is_stale(path: &Path) -> bool {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return false;
+    };
+    let Ok(pid) = contents.trim().parse::<u32>() else {
+        // Still being written, or garbage; only garbage gets old
+        return lock_age_exceeded(path, STALE_AFTER);
+    };
+
+    #[cfg(target_os = "linux")]
+    {
+        !Path::new("/proc").join(pid.to_string()).exists()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        lock_age_exceeded(path, STALE_AFTER)
+    }
+}
+
+/// Check whether a lock file was last written more than `max_age` ago
+fn lock_age_exceeded(path: &Path, max_age: std::time::Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > max_age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("clutchctl-lock-test-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_lock_is_exclusive_and_released_on_drop() {
+        let dir = test_dir("exclusive");
+        let key = "/dev/hidraw7";
+
+        let lock = DeviceLock::acquire_in(&dir, key).unwrap();
+        assert!(matches!(DeviceLock::acquire_in(&dir, key), Err(PedalError::DeviceBusy)));
+
+        // A different device is unaffected
+        let other = DeviceLock::acquire_in(&dir, "/dev/hidraw8").unwrap();
+
+        drop(lock);
+        let relocked = DeviceLock::acquire_in(&dir, key).unwrap();
+
+        drop(relocked);
+        drop(other);
+        let _ = fs::remove_dir(&dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stale_lock_is_reclaimed() {
+        let dir = test_dir("stale");
+        let key = "/dev/hidraw9";
+        fs::create_dir_all(&dir).unwrap();
+
+        // PIDs are capped well below u32::MAX, so this owner can't be running
+        let path = dir.join(format!("{}.lock", sanitize(key)));
+        fs::write(&path, u32::MAX.to_string()).unwrap();
+
+        let lock = DeviceLock::acquire_in(&dir, key).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), std::process::id().to_string());
+
+        drop(lock);
+        assert!(!path.exists());
+        let _ = fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn test_lock_without_pid_goes_stale_with_age() {
+        let dir = test_dir("empty");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("empty.lock");
+        fs::write(&path, "").unwrap();
+
+        assert!(!is_stale(&path));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(lock_age_exceeded(&path, std::time::Duration::from_millis(10)));
+
+        fs::remove_file(&path).unwrap();
+        let _ = fs::remove_dir(&dir);
+    }
+}
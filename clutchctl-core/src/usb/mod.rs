@@ -2,14 +2,38 @@
 //!
 //! This module provides cross-platform HID device access using the hidapi library.
 //! On Windows, this uses the native HID driver (no Zadig/WinUSB required).
-//! On Linux, this uses hidraw.
+//! On Linux, this uses hidapi's statically linked libusb backend rather than
+//! hidraw. Opening a device detaches the kernel HID driver from the interface
+//! and closing it reattaches the driver, so the kernel grabbing the interrupt
+//! interface doesn't block configuration and no separate interface claim is needed.
 //! On macOS, this uses IOKit.
 
+pub mod device_lock;
+
+pub use device_lock::DeviceLock;
+
 use crate::error::{PedalError, Result};
 use hidapi::{HidApi, HidDevice};
+use log::debug;
 use once_cell::sync::OnceCell;
 use std::sync::Mutex;
 
+/// HID backend hidapi was built with
+#[cfg(target_os = "linux")]
+pub const HID_BACKEND: &str = "libusb (static)";
+/// HID backend hidapi was built with
+#[cfg(target_os = "windows")]
+pub const HID_BACKEND: &str = "Windows native HID";
+/// HID backend hidapi was built with
+#[cfg(target_os = "macos")]
+pub const HID_BACKEND: &str = "IOKit";
+/// HID backend hidapi was built with
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+pub const HID_BACKEND: &str = "hidapi";
+
+/// USB interface that carries the configuration protocol on composite pedals
+pub const CONFIG_INTERFACE: i32 = 1;
+
 /// Global HidApi instance (thread-safe singleton)
 static HID_API: OnceCell<Mutex<HidApi>> = OnceCell::new();
 
@@ -31,6 +55,20 @@ pub fn refresh_devices() -> Result<()> {
     Ok(())
 }
 
+/// Get the global HidApi instance with an up-to-date device list
+///
+/// hidapi caches the device list, so this re-enumerates unless the instance
+/// was created by this call (creating it enumerates too). Discovery uses this
+/// so hot-plugged devices show up without restarting the process.
+pub fn get_refreshed_hid_api() -> Result<std::sync::MutexGuard<'static, HidApi>> {
+    let just_created = HID_API.get().is_none();
+    let mut api = get_hid_api()?;
+    if !just_created {
+        api.refresh_devices()?;
+    }
+    Ok(api)
+}
+
 /// Open a HID device by vendor and product ID
 pub fn open_device(vendor_id: u16, product_id: u16) -> Result<HidDevice> {
     let api = get_hid_api()?;
@@ -43,6 +81,50 @@ pub fn open_device_path(path: &std::ffi::CStr) -> Result<HidDevice> {
     api.open_path(path).map_err(PedalError::from)
 }
 
+/// Open a device by vendor and product ID, trying every matching HID interface
+///
+/// Interfaces numbered [`CONFIG_INTERFACE`] are tried first. Returns the first
+/// interface that opens, or the last error if none does.
+pub fn open_device_best_effort(vendor_id: u16, product_id: u16) -> Result<(HidDevice, HidDeviceInfo)> {
+    let candidates = config_interface_first(list_devices(vendor_id, product_id)?);
+
+    let mut last_error = PedalError::Hid(format!(
+        "No HID interface found for {:04x}:{:04x}",
+        vendor_id, product_id
+    ));
+
+    for info in candidates {
+        match open_device_path(&info.path) {
+            Ok(device) => return Ok((device, info)),
+            Err(e) => {
+                debug!("Failed to open {:04x}:{:04x} interface {} at {:?}: {}",
+                       vendor_id, product_id, info.interface_number, info.path, e);
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Order interfaces so the config interface comes first, keeping enumeration order otherwise
+fn config_interface_first(mut infos: Vec<HidDeviceInfo>) -> Vec<HidDeviceInfo> {
+    infos.sort_by_key(|info| info.interface_number != CONFIG_INTERFACE);
+    infos
+}
+
+/// Interpret the result of `HidDevice::read_timeout`
+///
+/// `Ok(0)` means nothing arrived in time and is `PedalError::Timeout`. Errors
+/// go through `From<HidError>`, so an unplugged device is
+/// `PedalError::DeviceDisconnected` and anything else keeps hidapi's error.
+pub fn check_read(result: hidapi::HidResult<usize>) -> Result<usize> {
+    match result? {
+        0 => Err(PedalError::Timeout),
+        n => Ok(n),
+    }
+}
+
 /// Device information from HID enumeration
 #[derive(Debug, Clone)]
 pub struct HidDeviceInfo {
@@ -68,6 +150,14 @@ impl HidDeviceInfo {
             interface_number: info.interface_number(),
         }
     }
+
+    /// Key of the physical device while it stays plugged in: its HID path
+    ///
+    /// Device locks and staged sessions are keyed by it, so neither follows
+    /// the enumeration ID to another device.
+    pub fn device_key(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
 }
 
 /// List all HID devices matching the given vendor and product IDs
@@ -94,3 +184,43 @@ pub fn list_all_devices() -> Result<Vec<HidDeviceInfo>> {
 
     Ok(devices)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    fn info(path: &str, interface_number: i32) -> HidDeviceInfo {
+        HidDeviceInfo {
+            vendor_id: 0x1a86,
+            product_id: 0xe026,
+            path: CString::new(path).unwrap(),
+            serial_number: None,
+            manufacturer: None,
+            product: None,
+            interface_number,
+        }
+    }
+
+    #[test]
+    fn test_config_interface_first() {
+        let ordered = config_interface_first(vec![
+            info("/dev/hidraw0", 0),
+            info("/dev/hidraw1", CONFIG_INTERFACE),
+            info("/dev/hidraw2", 2),
+        ]);
+
+        let paths: Vec<_> = ordered.iter().map(|i| i.path.to_str().unwrap()).collect();
+        assert_eq!(paths, vec!["/dev/hidraw1", "/dev/hidraw0", "/dev/hidraw2"]);
+    }
+
+    #[test]
+    fn test_check_read() {
+        let error = |message: &str| Err(hidapi::HidError::HidApiError { message: message.to_string() });
+
+        assert_eq!(check_read(Ok(8)).unwrap(), 8);
+        assert!(matches!(check_read(Ok(0)), Err(PedalError::Timeout)));
+        assert!(matches!(check_read(error("No such device")), Err(PedalError::DeviceDisconnected)));
+        assert!(matches!(check_read(error("broken pipe")), Err(PedalError::HidSource(_))));
+    }
+}
@@ -0,0 +1,442 @@
+//! User configuration files
+//!
+//! Extra device IDs can be added without recompiling by listing them in
+//! `devices.toml` inside the clutchctl config directory
+//! (`$XDG_CONFIG_HOME/clutchctl`, `~/.config/clutchctl`, or `%APPDATA%\clutchctl`):
+//!
+//! ```toml
+//! [[device]]
+//! vendor_id = 0x1a86
+//! product_id = 0xe027
+//! type = "iKKEGOL"
+//! ```
+//!
+//! `type` selects the protocol and must be one of the types in `SUPPORTED_DEVICES`.
+//!
+//! Pedal names can be overridden per device in `pedal-names.toml`, keyed by the
+//! USB serial number (devices without one keep their default names):
+//!
+//! ```toml
+//! [[device]]
+//! serial = "A1B2C3"
+//! names = ["top", "center", "bottom"]
+//!
+//! [[device]]
+//! serial = "D4E5F6"
+//! order = [3, 1, 2]
+//! ```
+//!
+//! `order` lists the pedal numbers shown by `clutchctl show` from the
+//! physically leftmost (or topmost) pedal on, for units whose pedals aren't
+//! wired in that order. An entry needs `names`, `order` or both.
+//!
+//! Only this small subset of TOML is understood.
+
+use crate::error::{PedalError, Result};
+use crate::SUPPORTED_DEVICES;
+use log::{debug, warn};
+use std::path::PathBuf;
+
+/// File name of the extra device ID list
+pub const DEVICES_FILE: &str = "devices.toml";
+
+/// File name of the per-device pedal name overrides
+pub const PEDAL_NAMES_FILE: &str = "pedal-names.toml";
+
+/// Get the clutchctl configuration directory, if one can be determined
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("clutchctl"));
+    }
+
+    #[cfg(windows)]
+    if let Some(dir) = std::env::var_os("APPDATA") {
+        return Some(PathBuf::from(dir).join("clutchctl"));
+    }
+
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("clutchctl"))
+}
+
+/// Load extra `(vendor_id, product_id, type)` entries from the user's `devices.toml`
+///
+/// A missing file yields an empty list. A malformed file is logged and ignored so
+/// that discovery of built-in devices keeps working.
+pub fn load_extra_device_ids() -> Vec<(u16, u16, String)> {
+    load_entries(DEVICES_FILE, "extra device ID", parse_device_ids)
+}
+
+/// Per-device overrides from `pedal-names.toml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PedalOverride {
+    /// USB serial number the override applies to
+    pub serial: String,
+    /// Pedal names in pedal order; empty keeps the defaults
+    pub names: Vec<String>,
+    /// 0-based pedal indices from the leftmost pedal on; empty keeps the model's order
+    pub order: Vec<usize>,
+}
+
+/// `serial`, `names` and `order` of a `pedal-names.toml` table while it is read
+type PartialOverride = (Option<String>, Option<Vec<String>>, Option<Vec<usize>>);
+
+/// Load the per-device overrides from the user's `pedal-names.toml`
+///
+/// Missing and malformed files are handled as in [`load_extra_device_ids`].
+pub fn load_pedal_names() -> Vec<PedalOverride> {
+    load_entries(PEDAL_NAMES_FILE, "pedal name override", parse_pedal_names)
+}
+
+/// Read and parse a file from the config directory, logging instead of failing
+fn load_entries<T>(file_name: &str, what: &str, parse: fn(&str) -> Result<Vec<T>>) -> Vec<T> {
+    let Some(path) = config_dir().map(|dir| dir.join(file_name)) else {
+        return Vec::new();
+    };
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            debug!("No {}s loaded from {}: {}", what, path.display(), e);
+            return Vec::new();
+        }
+    };
+
+    match parse(&contents) {
+        Ok(entries) => {
+            debug!("Loaded {} {}(s) from {}", entries.len(), what, path.display());
+            entries
+        }
+        Err(e) => {
+            warn!("Ignoring {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// Parse the contents of a `devices.toml` file
+pub fn parse_device_ids(contents: &str) -> Result<Vec<(u16, u16, String)>> {
+    parse_device_tables(
+        contents,
+        |entry: &mut (Option<u16>, Option<u16>, Option<String>), key, value, line_no| {
+            match key {
+                "vendor_id" => entry.0 = Some(parse_u16(value, line_no)?),
+                "product_id" => entry.1 = Some(parse_u16(value, line_no)?),
+                "type" => entry.2 = Some(parse_string(value, line_no)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        },
+        finish_entry,
+    )
+}
+
+/// Parse the contents of a `pedal-names.toml` file
+///
+/// Names must be unique within a device, and can't be numbers since those
+/// select pedals by index.
+pub fn parse_pedal_names(contents: &str) -> Result<Vec<PedalOverride>> {
+    parse_device_tables(
+        contents,
+        |entry: &mut PartialOverride, key, value, line_no| {
+            match key {
+                "serial" => entry.0 = Some(parse_string(value, line_no)?),
+                "names" => entry.1 = Some(parse_string_list(value, line_no)?),
+                "order" => entry.2 = Some(parse_pedal_order(value, line_no)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        },
+        finish_names_entry,
+    )
+}
+
+/// Read the `[[device]]` tables of a file
+///
+/// `set` stores one `key = value` line in the fields of the current table and
+/// returns `false` for a key the table doesn't have. `finish` checks the
+/// fields of each table, given the line number of its `[[device]]` header.
+fn parse_device_tables<E: Default, T>(
+    contents: &str,
+    mut set: impl FnMut(&mut E, &str, &str, usize) -> Result<bool>,
+    finish: impl Fn(E, usize) -> Result<T>,
+) -> Result<Vec<T>> {
+    let mut entries = Vec::new();
+    // Fields of the [[device]] table currently being read, and its header line
+    let mut current: Option<(E, usize)> = None;
+
+    for (line_no, raw_line) in contents.lines().enumerate() {
+        let line_no = line_no + 1;
+        let line = strip_comment(raw_line).trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line == "[[device]]" {
+            if let Some((entry, header_line)) = current.take() {
+                entries.push(finish(entry, header_line)?);
+            }
+            current = Some((E::default(), line_no));
+            continue;
+        }
+
+        let (entry, _) = current.as_mut().ok_or_else(|| {
+            PedalError::ParseError(format!("line {}: expected [[device]] before '{}'", line_no, line))
+        })?;
+
+        let (key, value) = line.split_once('=').ok_or_else(|| {
+            PedalError::ParseError(format!("line {}: expected 'key = value'", line_no))
+        })?;
+
+        if !set(entry, key.trim(), value.trim(), line_no)? {
+            return Err(PedalError::ParseError(format!(
+                "line {}: unknown key '{}'",
+                line_no, key.trim()
+            )));
+        }
+    }
+
+    if let Some((entry, header_line)) = current.take() {
+        entries.push(finish(entry, header_line)?);
+    }
+
+    Ok(entries)
+}
+
+/// Remove a trailing `#` comment, ignoring `#` inside quoted strings
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_u16(value: &str, line_no: usize) -> Result<u16> {
+    let parsed = if let Some(hex) = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        u16::from_str_radix(hex, 16)
+    } else {
+        value.parse()
+    };
+
+    parsed.map_err(|_| PedalError::ParseError(format!("line {}: invalid ID '{}'", line_no, value)))
+}
+
+fn parse_string(value: &str, line_no: usize) -> Result<String> {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .map(|v| v.to_string())
+        .ok_or_else(|| PedalError::ParseError(format!("line {}: expected quoted string", line_no)))
+}
+
+/// Parse a one-line array of quoted strings such as `["a", "b"]`
+fn parse_string_list(value: &str, line_no: usize) -> Result<Vec<String>> {
+    let inner = value
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .ok_or_else(|| PedalError::ParseError(format!("line {}: expected [\"...\", ...]", line_no)))?;
+
+    // Scan quote to quote, so a comma inside a name doesn't split it
+    let mut items = Vec::new();
+    let mut rest = inner.trim();
+    while !rest.is_empty() {
+        let (item, after) = rest
+            .strip_prefix('"')
+            .and_then(|v| v.split_once('"'))
+            .ok_or_else(|| PedalError::ParseError(format!("line {}: expected quoted string", line_no)))?;
+        items.push(item.to_string());
+
+        rest = after.trim_start();
+        if !rest.is_empty() {
+            rest = rest
+                .strip_prefix(',')
+                .ok_or_else(|| PedalError::ParseError(format!("line {}: expected ',' after \"{}\"", line_no, item)))?
+                .trim_start();
+        }
+    }
+
+    Ok(items)
+}
+
+/// Parse a one-line array of 1-based pedal numbers such as `[3, 1, 2]` into 0-based indices
+fn parse_pedal_order(value: &str, line_no: usize) -> Result<Vec<usize>> {
+    let inner = value
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .ok_or_else(|| PedalError::ParseError(format!("line {}: expected [1, 2, ...]", line_no)))?;
+
+    let mut order = Vec::new();
+    for item in inner.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let index = item
+            .parse::<usize>()
+            .ok()
+            .and_then(|num| num.checked_sub(1))
+            .ok_or_else(|| PedalError::ParseError(format!("line {}: invalid pedal number '{}'", line_no, item)))?;
+        if order.contains(&index) {
+            return Err(PedalError::ParseError(format!("line {}: pedal {} is listed twice", line_no, item)));
+        }
+        order.push(index);
+    }
+
+    Ok(order)
+}
+
+fn finish_names_entry(
+    entry: PartialOverride,
+    line_no: usize,
+) -> Result<PedalOverride> {
+    let (Some(serial), names, order) = entry else {
+        return Err(PedalError::ParseError(format!(
+            "line {}: [[device]] needs serial",
+            line_no
+        )));
+    };
+    if names.is_none() && order.is_none() {
+        return Err(PedalError::ParseError(format!(
+            "line {}: [[device]] needs names or order",
+            line_no
+        )));
+    }
+    let names = names.unwrap_or_default();
+
+    for (i, name) in names.iter().enumerate() {
+        if name.is_empty() || name.parse::<usize>().is_ok() {
+            return Err(PedalError::ParseError(format!(
+                "device {}: pedal name '{}' must be a non-empty, non-numeric string",
+                serial, name
+            )));
+        }
+        if names[..i].iter().any(|other| other.eq_ignore_ascii_case(name)) {
+            return Err(PedalError::ParseError(format!(
+                "device {}: pedal name '{}' is used twice",
+                serial, name
+            )));
+        }
+    }
+
+    Ok(PedalOverride { serial, names, order: order.unwrap_or_default() })
+}
+
+fn finish_entry(
+    entry: (Option<u16>, Option<u16>, Option<String>),
+    line_no: usize,
+) -> Result<(u16, u16, String)> {
+    match entry {
+        (Some(vendor_id), Some(product_id), Some(device_type)) => {
+            if !SUPPORTED_DEVICES.iter().any(|&(_, _, t)| t == device_type) {
+                return Err(PedalError::ParseError(format!(
+                    "device {:04x}:{:04x}: unknown type '{}'",
+                    vendor_id, product_id, device_type
+                )));
+            }
+            Ok((vendor_id, product_id, device_type))
+        }
+        _ => Err(PedalError::ParseError(format!(
+            "line {}: [[device]] needs vendor_id, product_id and type",
+            line_no
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device_ids() {
+        let contents = r#"
+# My clones
+[[device]]
+vendor_id = 0x1a86
+product_id = 0xe027   # newer batch
+type = "iKKEGOL"
+
+[[device]]
+vendor_id = 4660
+product_id = 0x5678
+type = "PCsensor"
+"#;
+
+        let ids = parse_device_ids(contents).unwrap();
+        assert_eq!(
+            ids,
+            vec![
+                (0x1a86, 0xe027, "iKKEGOL".to_string()),
+                (0x1234, 0x5678, "PCsensor".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_device_ids_errors() {
+        // Unknown device type
+        let contents = "[[device]]\nvendor_id = 0x1\nproduct_id = 0x2\ntype = \"Nope\"\n";
+        assert!(parse_device_ids(contents).is_err());
+
+        // Missing field
+        let contents = "[[device]]\nvendor_id = 0x1\ntype = \"PCsensor\"\n";
+        assert!(parse_device_ids(contents).is_err());
+
+        // Key outside of a table
+        assert!(parse_device_ids("vendor_id = 0x1\n").is_err());
+
+        // Empty file is fine
+        assert!(parse_device_ids("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_pedal_names() {
+        let contents = r#"
+[[device]]
+serial = "A1B2C3"
+names = ["top", "center", "bottom"]  # vertical layout
+"#;
+
+        assert_eq!(
+            parse_pedal_names(contents).unwrap(),
+            vec![PedalOverride {
+                serial: "A1B2C3".to_string(),
+                names: vec!["top".to_string(), "center".to_string(), "bottom".to_string()],
+                order: Vec::new(),
+            }]
+        );
+
+        // An order alone is enough, and is converted to 0-based indices
+        let overrides = parse_pedal_names("[[device]]\nserial = \"x\"\norder = [3, 1, 2]\n").unwrap();
+        assert_eq!(overrides[0].order, vec![2, 0, 1]);
+        assert!(overrides[0].names.is_empty());
+        assert!(parse_pedal_names("[[device]]\nserial = \"x\"\norder = [0, 1]\n").is_err());
+        assert!(parse_pedal_names("[[device]]\nserial = \"x\"\norder = [1, 1]\n").is_err());
+
+        // Numeric and duplicate names would hide other pedals
+        assert!(parse_pedal_names("[[device]]\nserial = \"x\"\nnames = [\"2\"]\n").is_err());
+        assert!(parse_pedal_names("[[device]]\nserial = \"x\"\nnames = [\"a\", \"A\"]\n").is_err());
+
+        // Neither names nor order
+        assert!(parse_pedal_names("[[device]]\nserial = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn test_name_with_comma() {
+        let overrides = parse_pedal_names("[[device]]\nserial = \"x\"\nnames = [\"copy, paste\", \"undo\",]\n").unwrap();
+        assert_eq!(overrides[0].names, vec!["copy, paste".to_string(), "undo".to_string()]);
+
+        assert!(parse_pedal_names("[[device]]\nserial = \"x\"\nnames = [\"a\" \"b\"]\n").is_err());
+        assert!(parse_pedal_names("[[device]]\nserial = \"x\"\nnames = [\"a]\n").is_err());
+    }
+
+    #[test]
+    fn test_incomplete_table_error_names_its_header() {
+        let contents = "[[device]]\nvendor_id = 0x1\n\n[[device]]\nvendor_id = 0x1\nproduct_id = 0x2\ntype = \"PCsensor\"\n";
+        let error = parse_device_ids(contents).unwrap_err().to_string();
+        assert!(error.contains("line 1:"), "{}", error);
+
+        let contents = "[[device]]\nserial = \"x\"\nnames = [\"a\"]\n\n[[device]]\nnames = [\"b\"]\n";
+        let error = parse_pedal_names(contents).unwrap_err().to_string();
+        assert!(error.contains("line 5:"), "{}", error);
+    }
+}
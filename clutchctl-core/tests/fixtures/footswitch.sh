@@ -0,0 +1,9 @@
+#!/bin/sh
+# Pedal setup for the footswitch tool, as kept by users migrating to clutchctl
+
+# Reopen the last closed tab
+sudo footswitch -1 -m ctrl -m shift -k t
+
+# Type a command; pedal 3 scrolls down
+footswitch -2 -s "git status" -S "28" \
+           -3 -w -3